@@ -0,0 +1,81 @@
+//! 標準入出力で動く最小限のMCPサーバー（`echo`ツールのみ）
+//!
+//! MCPクライアントの動作確認とテストに使う
+
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(request) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+
+        // 通知には応答しない
+        let Some(id) = request.get("id").cloned() else {
+            continue;
+        };
+
+        let response = match request["method"].as_str() {
+            Some("initialize") => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "mcp-echo-server", "version": "0.1.0" },
+                },
+            }),
+            Some("tools/list") => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "tools": [{
+                        "name": "echo",
+                        "description": "Echo back the given text.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": { "text": { "type": "string" } },
+                            "required": ["text"],
+                        },
+                    }],
+                },
+            }),
+            Some("tools/call") if request["params"]["name"] == "echo" => {
+                let text = request["params"]["arguments"]["text"]
+                    .as_str()
+                    .unwrap_or_default();
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": {
+                        "content": [{ "type": "text", "text": text }],
+                        "isError": false,
+                    },
+                })
+            }
+            Some("tools/call") => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [{ "type": "text", "text": "unknown tool" }],
+                    "isError": true,
+                },
+            }),
+            _ => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": "method not found" },
+            }),
+        };
+
+        if writeln!(stdout, "{}", response).is_err() || stdout.flush().is_err() {
+            break;
+        }
+    }
+}
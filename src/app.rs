@@ -1,19 +1,29 @@
 use ratatui::crossterm::event::{self, Event};
-use reqwest::Client;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc;
 
+use crate::audio::{self, Speaker};
+use crate::cassette::{HttpClient, HttpMode};
 use crate::config::AppConfig;
+use crate::error::{Error, Result};
 use crate::features::chat::{
     components::render_ui,
-    events::{handle_chat_event, handle_key_event, ScrollAction},
-    state::{AppState, MessageRole},
-    worker::{create_chat_worker, ChatWorkerConfig},
+    events::{handle_chat_event, handle_key_event, ChatEvent, ScrollAction},
+    state::{AppState, MessageRole, ModelListSource},
+    worker::{create_chat_worker, ChatWorkerConfig, WorkerCommand},
 };
 use crate::features::voice;
+use crate::mcp;
 use crate::sound;
+use crate::tools::{builtin::builtin_tools, ToolRegistry};
+use crate::usage::UsageTracker;
+
+/// `http_mode`で通信を記録・再生できる
+pub async fn run_chat_terminal(http_mode: HttpMode) -> color_eyre::Result<()> {
+    // 端末をrawモードにする前に、記録先や再生元のディレクトリを確認する
+    let client = Arc::new(HttpClient::new(&http_mode)?);
 
-pub async fn run_chat_terminal() -> color_eyre::Result<()> {
     let mut terminal = ratatui::init();
     let mut app_state = AppState::new();
 
@@ -21,17 +31,38 @@ pub async fn run_chat_terminal() -> color_eyre::Result<()> {
     let mut config = AppConfig::load();
 
     // 環境変数から設定を読み取り
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
     let env_model = std::env::var("OPENAI_MODEL").ok();
-    let model = config.get_model(env_model.as_deref(), "gpt-5-nano");
+    let backend = config.backend_config();
+    let model = config.get_model(env_model.as_deref(), backend.kind.default_model());
     let system_prompt = std::env::var("PROMPT").unwrap_or_else(|_| {
         r"あなたはチャットAIです。ユーザーと楽しく会話をしてください。
 口語で話すときのように、一文を短く、会話形式での応答を心がけてください。"
             .to_string()
     });
 
-    // AppStateにモデル情報を設定
+    // 料金表と今日の利用額を引き継ぐ
+    app_state.usage = UsageTracker::new(
+        config.prices.clone(),
+        config.daily_spending_cap,
+        config.daily_spending.clone(),
+    );
+
+    // AppStateにモデル情報を設定（起動後にプロバイダーの一覧で置き換える）
     app_state.set_current_model(model.clone());
+    app_state.backend_kind = backend.kind;
+    app_state.generation_params = config.generation.clone();
+    app_state.favorite_models = config.favorite_models.clone();
+    app_state.structured_output = config.structured_output.clone();
+    app_state.speaker_style = config.speaker_style.unwrap_or(voice::DEFAULT_SPEAKER);
+    app_state.voice_params = config.voice_params.clone();
+    match config.cached_models(&backend) {
+        Some(cache) => app_state.set_available_models(
+            cache.models.clone(),
+            ModelListSource::Cache(cache.fetched_at.clone()),
+        ),
+        None => app_state
+            .set_available_models(backend.kind.fallback_models(), ModelListSource::Fallback),
+    }
 
     // 設定情報をAppStateに初期化
     let settings = config.get_all_settings();
@@ -41,24 +72,59 @@ pub async fn run_chat_terminal() -> color_eyre::Result<()> {
     config.set_last_used_model(model.clone());
     config.save();
 
+    // タイマーツールから届くリマインダー
+    let (reminder_tx, mut reminder_rx) = mpsc::unbounded_channel::<String>();
+    let mut tools = if config.tools.enabled {
+        builtin_tools(&config.tools, reminder_tx)
+    } else {
+        ToolRegistry::new()
+    };
+
+    // 設定されたMCPサーバーを起動してツールを追加
+    let (mcp_tools, mcp_errors) = mcp::connect_servers(&config.mcp_servers).await;
+    for tool in mcp_tools {
+        tools.register(tool);
+    }
+
     // ChatWorkerを起動
-    let client = Arc::new(Client::new());
     let worker_config = ChatWorkerConfig {
-        api_key,
+        backend: backend.clone(),
         model,
         system_prompt,
+        tools,
+        tool_permissions: config.tools.permissions.clone(),
     };
 
-    let (user_input_tx, mut chat_event_rx) = create_chat_worker(worker_config, client.clone());
+    let (command_tx, mut chat_event_rx) = create_chat_worker(worker_config, client.clone());
+
+    // 音声合成・再生で発生したエラーをUIに届けるチャネル
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel::<Error>();
 
     // Audio loopを開始
-    let audio_tx = sound::start_audio_loop();
+    let audio_tx = sound::start_audio_loop(error_tx.clone());
+    // 音声合成の再試行の状況をステータス行に表示するチャネル
+    let (speech_status_tx, mut speech_status_rx) = mpsc::unbounded_channel::<String>();
+    // 読み上げの順番待ち（キャンセル時に破棄する）
+    let mut speech = voice::SpeechQueue::new(voice::SynthesisContext {
+        client: client.clone(),
+        audio_tx,
+        error_tx: error_tx.clone(),
+        status_tx: speech_status_tx,
+        retry_policy: config.retry.clone(),
+    });
+    speech.set_speaker(app_state.speaker_style);
+    speech.set_voice_params(app_state.voice_params.clone());
+    // 話者選択画面に表示するキャラクターの一覧
+    let (speakers_tx, mut speakers_rx) = mpsc::unbounded_channel::<Result<Vec<Speaker>>>();
 
     // 初期メッセージを追加
     let _system_id = app_state.add_message(
         MessageRole::System,
         "Chat Terminal Started. Type 'i' to enter insert mode and start chatting!".to_string(),
     );
+    for error in mcp_errors {
+        handle_chat_event(&mut app_state, ChatEvent::Error(error));
+    }
 
     loop {
         // UI描画とレイアウト情報の取得
@@ -72,7 +138,7 @@ pub async fn run_chat_terminal() -> color_eyre::Result<()> {
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
                 let (should_quit, scroll_action) =
-                    handle_key_event(key, &mut app_state, Some(&user_input_tx));
+                    handle_key_event(key, &mut app_state, Some(&command_tx));
 
                 // スクロールアクションの処理
                 if let Some(action) = scroll_action {
@@ -97,6 +163,62 @@ pub async fn run_chat_terminal() -> color_eyre::Result<()> {
                     }
                 }
 
+                // 生成中の応答と読み上げの中止
+                //
+                // 要約中やストリーミング開始前でも応答を止める（応答がなければChatWorkerが無視する）
+                if app_state.take_cancel_request() {
+                    if app_state.is_busy() {
+                        let _ = command_tx.send(WorkerCommand::Cancel);
+                    }
+                    speech.stop();
+                }
+
+                // 「常に許可/拒否」の回答を設定に保存
+                let permission_updates = app_state.take_permission_updates();
+                if !permission_updates.is_empty() {
+                    for (tool_name, permission) in permission_updates {
+                        config.set_tool_permission(tool_name, permission);
+                    }
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+
+                // 話者選択画面の一覧の取得・見本の読み上げ・声の切り替え
+                if app_state.take_speakers_request() {
+                    let client = client.clone();
+                    let speakers_tx = speakers_tx.clone();
+                    tokio::spawn(async move {
+                        let speakers = match audio::engine_origin() {
+                            Ok(origin) => audio::fetch_speakers(&client, &origin).await,
+                            Err(e) => Err(e),
+                        };
+                        let _ = speakers_tx.send(speakers);
+                    });
+                }
+                // 調整した読み上げパラメータは見本を読み上げる前に反映する
+                if let Some((style_id, params)) = app_state.take_voice_params_update() {
+                    speech.set_voice_params(app_state.voice_params.clone());
+                    config.set_voice_params(style_id, params);
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+                if let Some(style_id) = app_state.take_speaker_preview() {
+                    speech.preview(style_id);
+                }
+                if let Some(style_id) = app_state.take_speaker_update() {
+                    speech.set_speaker(style_id);
+                    config.set_speaker_style(style_id);
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+
+                // ピン留めしたモデルを設定に保存
+                if let Some(favorite_models) = app_state.take_favorite_models_update() {
+                    config.set_favorite_models(favorite_models);
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+
                 if should_quit || app_state.should_quit {
                     break;
                 }
@@ -105,11 +227,56 @@ pub async fn run_chat_terminal() -> color_eyre::Result<()> {
 
         // ChatEventの処理（ノンブロッキング）
         while let Ok(chat_event) = chat_event_rx.try_recv() {
-            voice::handle_voice_event(&chat_event, &app_state, client.clone(), audio_tx.clone());
+            speech.handle_chat_event(&chat_event, &app_state);
+            match &chat_event {
+                // ChatWorkerが切り替えたモデルを次回も使う
+                ChatEvent::ModelChanged(model) => {
+                    config.set_last_used_model(model.clone());
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+                // 取得したモデル一覧は次回のオフライン時に備えて保存する
+                ChatEvent::ModelsLoaded(models) => {
+                    config.set_model_cache(&backend, models.clone());
+                    config.save();
+                    app_state.update_settings(config.get_all_settings());
+                }
+                _ => {}
+            }
             handle_chat_event(&mut app_state, chat_event);
             // ストリーミング中は自動的に最下部にスクロール
             app_state.auto_scroll_to_bottom(display_width);
         }
+
+        // 今日の利用額を設定に保存
+        if let Some(daily_spending) = app_state.usage.take_daily_update() {
+            config.daily_spending = daily_spending;
+            config.save();
+        }
+
+        // 時間になったタイマーのリマインダーを表示し、選択中の声と読み上げパラメータで読み上げる
+        while let Ok(reminder) = reminder_rx.try_recv() {
+            app_state.add_message(MessageRole::System, format!("Reminder: {}", reminder));
+            app_state.auto_scroll_to_bottom(display_width);
+            speech.speak(reminder, app_state.speaker_style);
+        }
+
+        while let Ok(result) = speakers_rx.try_recv() {
+            match result {
+                Ok(speakers) => app_state.set_speakers(speakers),
+                Err(e) => app_state.set_speakers_failed(e.to_string()),
+            }
+        }
+
+        while let Ok(status) = speech_status_rx.try_recv() {
+            app_state.set_status(status);
+        }
+
+        // 音声関連のエラーをChatEventとして表示
+        while let Ok(error) = error_rx.try_recv() {
+            handle_chat_event(&mut app_state, ChatEvent::Error(error));
+            app_state.auto_scroll_to_bottom(display_width);
+        }
     }
 
     ratatui::restore();
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use base64::Engine;
+
+use crate::error::{Error, Result};
+
+/// 添付できる画像の最大サイズ（Chat Completions APIの制限）
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// ユーザーの発言に添付する画像
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAttachment {
+    pub file_name: String,
+    /// 元のファイルのバイト数
+    pub size: usize,
+    pub mime_type: &'static str,
+    /// Base64でエンコードした画像データ
+    pub data: String,
+}
+
+impl ImageAttachment {
+    /// PNGまたはJPEGのファイルを読み込む（形式はファイルの先頭で判定する）
+    pub fn load(path: &Path) -> Result<Self> {
+        let read_error =
+            |e: std::io::Error| Error::Attachment(format!("cannot read {}: {}", path.display(), e));
+        let too_large = || {
+            Error::Attachment(format!(
+                "{} is larger than {} MB",
+                path.display(),
+                MAX_IMAGE_BYTES / 1024 / 1024
+            ))
+        };
+
+        // 巨大なファイルを読み込む前にサイズで弾く
+        if std::fs::metadata(path).map_err(read_error)?.len() > MAX_IMAGE_BYTES as u64 {
+            return Err(too_large());
+        }
+        let bytes = std::fs::read(path).map_err(read_error)?;
+        // 確認した後にファイルが大きくなった場合
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err(too_large());
+        }
+        let mime_type = detect_mime_type(&bytes).ok_or_else(|| {
+            Error::Attachment(format!("{} is not a PNG or JPEG image", path.display()))
+        })?;
+
+        Ok(Self {
+            file_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string()),
+            size: bytes.len(),
+            mime_type,
+            data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+        })
+    }
+
+    /// `image_url`に指定するdata URL
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+
+    /// チャット欄に画像の代わりに表示する行（例: "[image: shot.png, 120.5 KB]"）
+    pub fn placeholder(&self) -> String {
+        format!("[image: {}, {}]", self.file_name, format_size(self.size))
+    }
+}
+
+fn detect_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
@@ -1,15 +1,39 @@
 use std::{env, sync::Arc};
 
 use log::{debug, error, info};
-use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::cassette::HttpClient;
+use crate::error::{Error, Result};
+use crate::retry::{with_retry, RetryPolicy, RetryStatus};
+use crate::voice_params::VoiceParams;
+
+/// `audio_query`のクエリパラメータ
 #[derive(Serialize)]
-struct AudioQuery {
+struct AudioQueryParams {
     text: String,
     speaker: u32,
 }
 
+/// `audio_query`が返す合成の設定
+///
+/// 調整できる値だけを型で持ち、アクセント句などの残りはそのまま`synthesis`に送り返す
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioQuery {
+    pub speed_scale: f64,
+    pub pitch_scale: f64,
+    pub intonation_scale: f64,
+    pub volume_scale: f64,
+    pub pre_phoneme_length: f64,
+    pub post_phoneme_length: f64,
+    /// 句読点などの無音の長さの倍率（古いエンジンは返さない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_length_scale: Option<f64>,
+    #[serde(flatten)]
+    pub rest: serde_json::Map<String, serde_json::Value>,
+}
+
 #[derive(Serialize)]
 pub enum Speakers {
     Metan = 2,
@@ -22,12 +46,68 @@ impl From<Speakers> for u32 {
     }
 }
 
+/// `/speakers`が返すキャラクター
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Speaker {
+    pub name: String,
+    pub styles: Vec<SpeakerStyle>,
+}
+
+/// キャラクターの声のスタイル（ノーマル・あまあまなど）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SpeakerStyle {
+    pub name: String,
+    /// 合成時に`speaker`として指定するスタイルID
+    pub id: u32,
+}
+
+/// `origin`のエンジンで使えるキャラクターとスタイルの一覧を取得する
+pub async fn fetch_speakers(client: &HttpClient, origin: &str) -> Result<Vec<Speaker>> {
+    debug!("Fetching speakers from {}/speakers", origin);
+
+    let res = client
+        .send(client.request(reqwest::Method::GET, format!("{}/speakers", origin)))
+        .await
+        .map_err(|e| {
+            error!("Failed to send speakers request: {}", e);
+            engine_error(e)
+        })?;
+
+    let status = res.status();
+    if !status.is_success() {
+        let error_text = res
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to get error text".to_string());
+        error!(
+            "speakers request failed with status {}: {}",
+            status, error_text
+        );
+        return Err(Error::Tts(format!(
+            "speakers failed with status {}: {}",
+            status, error_text
+        )));
+    }
+
+    let speakers: Vec<Speaker> = res.json().await.map_err(|e| {
+        error!("Failed to parse speakers response: {}", e);
+        Error::Tts(format!("Invalid speakers response: {}", e))
+    })?;
+    info!("Loaded {} speakers", speakers.len());
+    Ok(speakers)
+}
+
+/// `speaker`はVOICEVOXのスタイルID、`params`はエンジンが返した値に上書きする読み上げパラメータ
+///
+/// 一時的な失敗は`retry_policy`に従って再試行し、待機する前に`on_retry`を呼ぶ
 pub async fn generate_wav(
-    client: Arc<Client>,
+    client: Arc<HttpClient>,
     input: &str,
-    speaker: Speakers,
-) -> Result<Vec<u8>, String> {
-    let speaker: u32 = speaker.into();
+    speaker: u32,
+    params: &VoiceParams,
+    retry_policy: &RetryPolicy,
+    on_retry: impl FnMut(&RetryStatus),
+) -> Result<Vec<u8>> {
     info!(
         "Starting WAV generation for speaker {} with text length: {}",
         speaker,
@@ -35,28 +115,48 @@ pub async fn generate_wav(
     );
     debug!("Text content: {}", input);
 
-    let query = AudioQuery {
+    let query = AudioQueryParams {
         text: input.to_string(),
         speaker,
     };
 
-    let Ok(origin) = env::var("VOICEVOX_ENGINE_URL") else {
+    let origin = engine_origin()?;
+    info!("Using VOICEVOX Engine at: {}", origin);
+
+    // エンジンの再起動中や接続リセットなど一時的な失敗は再試行する
+    with_retry(
+        retry_policy,
+        || synthesize(&client, &origin, &query, params),
+        on_retry,
+    )
+    .await
+}
+
+/// 環境変数`VOICEVOX_ENGINE_URL`で指定されたエンジンのURL
+pub fn engine_origin() -> Result<String> {
+    env::var("VOICEVOX_ENGINE_URL").map_err(|_| {
         error!("VOICEVOX_ENGINE_URL environment variable not set");
-        return Err("VOICEVOX_ENGINE_URL not set".to_string());
-    };
+        Error::Config("VOICEVOX_ENGINE_URL not set".to_string())
+    })
+}
 
-    info!("Using VOICEVOX Engine at: {}", origin);
+/// audio_query と synthesis を順に呼び出してWAVデータを得る
+async fn synthesize(
+    client: &HttpClient,
+    origin: &str,
+    query: &AudioQueryParams,
+    params: &VoiceParams,
+) -> Result<Vec<u8>> {
+    let speaker = query.speaker;
 
     // Step 1: Generate audio query
     debug!("Sending audio_query request to {}/audio_query", origin);
     let res = client
-        .post(format!("{}/audio_query", origin))
-        .query(&query)
-        .send()
+        .send(client.post(format!("{}/audio_query", origin)).query(query))
         .await
         .map_err(|e| {
             error!("Failed to send audio_query request: {}", e);
-            format!("Failed to send request: {}", e)
+            engine_error(e)
         })?;
 
     debug!("audio_query response status: {}", res.status());
@@ -68,23 +168,29 @@ pub async fn generate_wav(
             .await
             .unwrap_or_else(|_| "Unable to get error text".to_string());
         error!("Error response body: {}", error_text);
-        return Err(format!(
+        return Err(Error::Tts(format!(
             "audio_query failed with status {}: {}",
             status, error_text
-        ));
+        )));
     }
 
     let bytes = res.bytes().await.map_err(|e| {
         error!("Failed to get audio_query response bytes: {}", e);
-        format!("Failed to get response bytes: {}", e)
+        engine_error(e)
     })?;
 
-    let query = String::from_utf8(bytes.to_vec()).map_err(|e| {
-        error!("Failed to convert audio_query response to string: {}", e);
-        format!("Failed to convert bytes to string: {}", e)
+    let mut audio_query: AudioQuery = serde_json::from_slice(&bytes).map_err(|e| {
+        error!("Failed to parse audio_query response: {}", e);
+        Error::Tts(format!("Invalid audio_query response: {}", e))
     })?;
+    debug!("audio_query response length: {} bytes", bytes.len());
 
-    debug!("audio_query response length: {} bytes", query.len());
+    // 話者ごとに調整した速さや高さを反映する
+    params.apply(&mut audio_query);
+    let query = serde_json::to_string(&audio_query).map_err(|e| {
+        error!("Failed to serialize audio_query: {}", e);
+        Error::Tts(format!("Failed to serialize audio_query: {}", e))
+    })?;
 
     // Step 2: Synthesize audio
     debug!(
@@ -92,21 +198,23 @@ pub async fn generate_wav(
         origin, speaker
     );
     let res = client
-        .post(format!("{}/synthesis?speaker={}", origin, speaker))
-        .header("Content-Type", "application/json")
-        .body(query)
-        .send()
+        .send(
+            client
+                .post(format!("{}/synthesis?speaker={}", origin, speaker))
+                .header("Content-Type", "application/json")
+                .body(query),
+        )
         .await
         .map_err(|e| {
             error!("Failed to send synthesis request: {}", e);
-            format!("Failed to send synthesis request: {}", e)
+            engine_error(e)
         })?;
 
     debug!("synthesis response status: {}", res.status());
     if res.status().is_success() {
         let bytes = res.bytes().await.map_err(|e| {
             error!("Failed to get synthesis response bytes: {}", e);
-            format!("Failed to get synthesis response bytes: {}", e)
+            engine_error(e)
         })?;
 
         info!("Successfully generated WAV data: {} bytes", bytes.len());
@@ -119,9 +227,18 @@ pub async fn generate_wav(
             .await
             .unwrap_or_else(|_| "Unable to get error text".to_string());
         error!("Error response body: {}", error_text);
-        Err(format!(
+        Err(Error::Tts(format!(
             "synthesis failed with status {}: {}",
             status, error_text
-        ))
+        )))
+    }
+}
+
+/// 接続できない・途中で切断された場合はエンジン停止とみなし、それ以外は合成失敗として扱う
+fn engine_error(e: reqwest::Error) -> Error {
+    if e.is_connect() || e.is_timeout() || e.is_request() || e.is_body() {
+        Error::EngineUnreachable(e.to_string())
+    } else {
+        Error::Tts(e.to_string())
     }
 }
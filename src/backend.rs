@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::attachment::ImageAttachment;
+use crate::cassette::HttpClient;
+use crate::error::Result;
+use crate::generation::GenerationParams;
+use crate::history::{ContextUsage, Message, ToolCall};
+use crate::ollama::OllamaChat;
+use crate::openai::ChatCompletion;
+use crate::retry::{RetryNotifier, RetryPolicy, RetryStatus};
+use crate::summary::SummarizationConfig;
+use crate::tools::ToolDefinition;
+use crate::usage::TokenUsage;
+
+/// ストリーミング中にバックエンドから通知されるイベント
+#[derive(Debug)]
+pub enum StreamEvent<'a> {
+    /// 応答本文のチャンク
+    Content(&'a str),
+    /// 推論モデルの思考過程（要約）のチャンク。履歴にも読み上げにも使わない
+    Reasoning(&'a str),
+    /// 一時的な失敗のため再試行を待機している
+    Retrying(&'a RetryStatus),
+}
+
+/// 1回のリクエストに対する応答
+#[derive(Debug, Clone, Default)]
+pub struct Completion {
+    pub content: String,
+    /// 空でなければ、ツールの実行結果を返して続きを生成させる必要がある
+    pub tool_calls: Vec<ToolCall>,
+    /// プロバイダーが報告したトークン数
+    pub usage: Option<TokenUsage>,
+}
+
+/// ストリーミング中のイベントを受け取るコールバック
+pub type StreamCallback<'a> = dyn FnMut(StreamEvent<'_>) + Send + 'a;
+
+/// 1回分のストリーミングリクエスト（再試行は`stream_with_retry`が行う）
+#[async_trait]
+pub(crate) trait StreamAttempt: Sync {
+    /// リクエストを送り、受信した本文とツール呼び出しを`completion`に追記する
+    async fn stream_once(
+        &self,
+        callback: &mut StreamCallback<'_>,
+        completion: &mut Completion,
+    ) -> Result<()>;
+}
+
+/// 一時的な失敗であれば待機してからストリーミングをやり直す
+///
+/// 思考過程を含め1つでもイベントを通知した後にやり直すと表示が重複するため、再試行は何も受信する前の失敗に限る
+pub(crate) async fn stream_with_retry(
+    backend: &impl StreamAttempt,
+    policy: &RetryPolicy,
+    callback: &mut StreamCallback<'_>,
+) -> Result<Completion> {
+    let mut retries_done = 0;
+    loop {
+        let mut completion = Completion::default();
+        let mut emitted = false;
+        let result = {
+            let mut tracking = |event: StreamEvent<'_>| {
+                emitted |= !matches!(event, StreamEvent::Retrying(_));
+                callback(event);
+            };
+            backend.stream_once(&mut tracking, &mut completion).await
+        };
+        let Err(e) = result else {
+            return Ok(completion);
+        };
+
+        let retry = if emitted {
+            None
+        } else {
+            policy.next_retry(retries_done, &e)
+        };
+        let Some(status) = retry else {
+            return Err(e);
+        };
+
+        warn!(
+            "Retrying chat stream ({}/{}) in {:?} after error: {}",
+            status.attempt, status.max_retries, status.delay, e
+        );
+        callback(StreamEvent::Retrying(&status));
+        tokio::time::sleep(status.delay).await;
+        retries_done += 1;
+    }
+}
+
+/// ChatWorkerから利用するLLMバックエンドの共通インターフェース
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// 使用するモデルを切り替える
+    fn set_model(&mut self, model: &str);
+
+    /// 現在のモデル名
+    fn current_model(&self) -> &str;
+
+    fn push_system_message(&mut self, prompt: &str);
+
+    /// システムプロンプトを置き換える
+    fn set_system_prompt(&mut self, prompt: &str);
+
+    /// 会話履歴を破棄して新しい会話を始める
+    fn reset_history(&mut self);
+
+    /// 最後のユーザー発言に対する応答（ツール呼び出しとその結果を含む）
+    fn last_reply(&self) -> Vec<Message>;
+
+    /// 会話履歴を置き換える（システムプロンプトは残す）
+    fn load_history(&mut self, messages: Vec<Message>);
+
+    /// ユーザー発言を追加する（`images`は発言に添付する画像）
+    fn push_user_message(&mut self, input: &str, images: &[ImageAttachment]);
+
+    fn push_assistant_message(&mut self, input: &str);
+
+    /// キャンセルで途中終了した応答を履歴に記録する
+    fn push_truncated_assistant_message(&mut self, input: &str);
+
+    /// ツール呼び出しを要求した応答を履歴に記録する
+    fn push_tool_calls(&mut self, content: &str, tool_calls: Vec<ToolCall>);
+
+    /// ツールの実行結果を履歴に記録する
+    fn push_tool_result(&mut self, tool_call_id: &str, result: &str);
+
+    /// 以降のリクエストに使う生成パラメータを設定する
+    fn set_generation_params(&mut self, params: GenerationParams);
+
+    /// LLMに公開するツールを設定する
+    fn set_tools(&mut self, tools: Vec<ToolDefinition>);
+
+    /// ストリーミング以外のリクエスト（要約など）を再試行するときの通知先を設定する
+    fn set_retry_notifier(&mut self, notifier: RetryNotifier);
+
+    /// 履歴が使用しているコンテキストの概算
+    fn context_usage(&self) -> ContextUsage;
+
+    /// システムメッセージと会話履歴を結合したメッセージ一覧
+    fn messages(&self) -> Vec<Message>;
+
+    /// 履歴から外れたターンが要約待ちになっているか
+    fn needs_summary(&self) -> bool;
+
+    /// 要約待ちのターンを要約し、「これまでの会話」として履歴に反映する
+    async fn summarize_history(&mut self) -> Result<()>;
+
+    /// 現在の履歴に対する応答を一括で取得する
+    async fn completion(&self) -> Result<Completion>;
+
+    /// 現在の履歴に対する応答をストリーミングで取得する
+    ///
+    /// 受信したチャンクごとに`callback`を呼び出し、最後に全文とツール呼び出しを返す
+    async fn completion_stream(&self, callback: &mut StreamCallback<'_>) -> Result<Completion>;
+
+    /// バックエンドで利用可能なモデル一覧を取得する
+    async fn list_models(&self) -> Result<Vec<String>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BackendKind {
+    #[default]
+    OpenAi,
+    Ollama,
+}
+
+impl BackendKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "openai" => Some(BackendKind::OpenAi),
+            "ollama" => Some(BackendKind::Ollama),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackendKind::OpenAi => "openai",
+            BackendKind::Ollama => "ollama",
+        }
+    }
+
+    pub fn default_base_url(&self) -> &'static str {
+        match self {
+            BackendKind::OpenAi => crate::openai::DEFAULT_BASE_URL,
+            BackendKind::Ollama => crate::ollama::DEFAULT_BASE_URL,
+        }
+    }
+
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            BackendKind::OpenAi => "gpt-5-nano",
+            BackendKind::Ollama => "llama3.2",
+        }
+    }
+
+    /// プロバイダーからモデル一覧を取得できないときに表示する一覧
+    pub fn fallback_models(&self) -> Vec<String> {
+        let models: &[&str] = match self {
+            BackendKind::OpenAi => &["gpt-5", "gpt-5-mini", "gpt-5-nano"],
+            BackendKind::Ollama => &["llama3.2"],
+        };
+        models.iter().map(|model| model.to_string()).collect()
+    }
+}
+
+/// プロバイダーから取得したモデル一覧のキャッシュ
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelCache {
+    pub backend: String,
+    pub base_url: String,
+    pub models: Vec<String>,
+    /// 取得した日時（ローカル時刻）
+    pub fetched_at: String,
+}
+
+/// バックエンドの接続設定
+#[derive(Debug, Clone, Default)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    pub api_key: Option<String>,
+    /// 省略時は各バックエンドの既定エンドポイントを使用する
+    pub base_url: Option<String>,
+    pub extra_headers: HashMap<String, String>,
+    /// Ollama の`options`にそのまま渡す値（`num_ctx`、`temperature`など）
+    pub options: serde_json::Map<String, serde_json::Value>,
+    /// Ollama の`keep_alive`（`"5m"`や`-1`など）
+    pub keep_alive: Option<serde_json::Value>,
+    pub retry_policy: RetryPolicy,
+    /// モデルごとのコンテキスト長の上書き
+    pub context_limits: HashMap<String, usize>,
+    pub summarization: SummarizationConfig,
+    /// 生成パラメータの初期値
+    pub generation: GenerationParams,
+    /// 応答に求めるJSON Schema（`None`なら自由なテキスト）
+    pub response_schema: Option<serde_json::Value>,
+}
+
+impl BackendConfig {
+    /// 実際に接続するエンドポイント
+    pub fn endpoint(&self) -> &str {
+        self.base_url
+            .as_deref()
+            .unwrap_or_else(|| self.kind.default_base_url())
+    }
+}
+
+/// 設定に応じたバックエンドを生成する
+pub fn create_backend(config: &BackendConfig, client: Arc<HttpClient>) -> Box<dyn ChatBackend> {
+    match config.kind {
+        BackendKind::OpenAi => {
+            let mut chat_completion = ChatCompletion::new(config.api_key.clone(), client);
+            if let Some(base_url) = &config.base_url {
+                chat_completion.base_url(base_url);
+            }
+            for (name, value) in &config.extra_headers {
+                chat_completion.header(name, value);
+            }
+            chat_completion.retry_policy(config.retry_policy.clone());
+            chat_completion.context_limits(config.context_limits.clone());
+            chat_completion.summarization(&config.summarization);
+            chat_completion.generation_params(config.generation.clone());
+            if let Some(schema) = &config.response_schema {
+                chat_completion.response_schema(schema.clone());
+            }
+            Box::new(chat_completion)
+        }
+        BackendKind::Ollama => {
+            let mut ollama = OllamaChat::new(client);
+            if let Some(api_key) = &config.api_key {
+                ollama.api_key(api_key);
+            }
+            if let Some(base_url) = &config.base_url {
+                ollama.base_url(base_url);
+            }
+            for (name, value) in &config.extra_headers {
+                ollama.header(name, value);
+            }
+            for (name, value) in &config.options {
+                ollama.option(name, value.clone());
+            }
+            if let Some(keep_alive) = &config.keep_alive {
+                ollama.keep_alive(keep_alive.clone());
+            }
+            ollama.retry_policy(config.retry_policy.clone());
+            ollama.context_limits(config.context_limits.clone());
+            ollama.summarization(&config.summarization);
+            ollama.generation_params(config.generation.clone());
+            if let Some(schema) = &config.response_schema {
+                ollama.response_schema(schema.clone());
+            }
+            Box::new(ollama)
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use futures::StreamExt;
+use log::{debug, error, info, warn};
+use reqwest::{IntoUrl, Method, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// HTTP通信の扱い
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum HttpMode {
+    /// そのまま通信する
+    #[default]
+    Live,
+    /// 通信しつつ、リクエストと応答をディレクトリに記録する
+    Record(PathBuf),
+    /// 記録した応答を返し、ネットワークには接続しない
+    Replay(PathBuf),
+}
+
+impl HttpMode {
+    /// コマンドライン引数（`--record <dir>`・`--replay <dir>`）から決める
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut mode = HttpMode::Live;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let flag = arg.as_str();
+            if flag != "--record" && flag != "--replay" {
+                return Err(Error::Config(format!("unknown argument '{}'", arg)));
+            }
+            let dir = args
+                .next()
+                .map(PathBuf::from)
+                .ok_or_else(|| Error::Config(format!("{} needs a directory", flag)))?;
+            mode = if flag == "--record" {
+                HttpMode::Record(dir)
+            } else {
+                HttpMode::Replay(dir)
+            };
+        }
+        Ok(mode)
+    }
+}
+
+/// 記録した1往復分の通信
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// 記録したリクエスト（認証ヘッダーなどは残さない）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    /// JSONとして読めれば値、読めなければ文字列
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+impl RecordedRequest {
+    fn new(request: &reqwest::Request) -> Self {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| {
+                serde_json::from_slice(bytes).unwrap_or_else(|_| {
+                    serde_json::Value::String(String::from_utf8_lossy(bytes).into_owned())
+                })
+            });
+        Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 受信したチャンク（SSEのストリーミングでは届いた単位とその間隔）
+    pub chunks: Vec<RecordedChunk>,
+}
+
+/// 受信したチャンクと、直前のチャンクからの経過時間
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedChunk {
+    pub delay_ms: u64,
+    /// UTF-8として読めるチャンク
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// 音声データなど、UTF-8として読めないチャンク
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+}
+
+impl RecordedChunk {
+    fn new(delay: Duration, bytes: &[u8]) -> Self {
+        let (text, base64) = match std::str::from_utf8(bytes) {
+            Ok(text) => (Some(text.to_string()), None),
+            Err(_) => (
+                None,
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+            ),
+        };
+        Self {
+            delay_ms: delay.as_millis() as u64,
+            text,
+            base64,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match (&self.text, &self.base64) {
+            (Some(text), _) => text.as_bytes().to_vec(),
+            (None, Some(data)) => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .unwrap_or_else(|e| {
+                    warn!("Ignoring invalid base64 chunk in cassette: {}", e);
+                    Vec::new()
+                }),
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+enum Cassette {
+    Live,
+    Record {
+        dir: PathBuf,
+        next_index: AtomicUsize,
+    },
+    /// `メソッド パス`ごとに、記録した順に返す
+    Replay {
+        interactions: Mutex<HashMap<String, VecDeque<Interaction>>>,
+    },
+}
+
+/// LLMとVOICEVOX Engineへの通信に共通で使うHTTPクライアント
+///
+/// 記録モードでは応答をそのまま返しつつカセット（ディレクトリ内のJSONファイル）に書き出し、
+/// 再生モードではカセットの応答をチャンクの間隔も含めて再現する
+pub struct HttpClient {
+    client: reqwest::Client,
+    cassette: Cassette,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            cassette: Cassette::Live,
+        }
+    }
+}
+
+impl HttpClient {
+    pub fn new(mode: &HttpMode) -> Result<Self> {
+        let cassette = match mode {
+            HttpMode::Live => Cassette::Live,
+            HttpMode::Record(dir) => {
+                std::fs::create_dir_all(dir).map_err(|e| {
+                    Error::Config(format!("cannot create {}: {}", dir.display(), e))
+                })?;
+                info!("Recording HTTP traffic to {}", dir.display());
+                Cassette::Record {
+                    dir: dir.clone(),
+                    next_index: AtomicUsize::new(next_file_index(dir)),
+                }
+            }
+            HttpMode::Replay(dir) => {
+                let interactions = load_interactions(dir)?;
+                info!(
+                    "Replaying {} recorded requests from {}",
+                    interactions.values().map(VecDeque::len).sum::<usize>(),
+                    dir.display()
+                );
+                Cassette::Replay {
+                    interactions: Mutex::new(interactions),
+                }
+            }
+        };
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            cassette,
+        })
+    }
+
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// リクエストを送信する（再生モードでは記録した応答を返す）
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        match &self.cassette {
+            Cassette::Live => request.send().await,
+            Cassette::Record { dir, next_index } => {
+                let request = request.build()?;
+                let recorded = RecordedRequest::new(&request);
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let path = dir.join(file_name(index, &recorded));
+
+                let response = self.client.execute(request).await?;
+                Ok(record(path, recorded, response))
+            }
+            Cassette::Replay { interactions } => {
+                let request = request.build()?;
+                let key = replay_key(request.method().as_str(), request.url());
+                let interaction = interactions
+                    .lock()
+                    .expect("cassette lock poisoned")
+                    .get_mut(&key)
+                    .and_then(VecDeque::pop_front);
+                Ok(match interaction {
+                    Some(interaction) => {
+                        debug!("Replaying {}", key);
+                        replay(interaction.response)
+                    }
+                    None => {
+                        warn!("No recorded response left for {}", key);
+                        missing_response(&key)
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// 応答の本文を読みながら呼び出し側に渡し、読み終えたらカセットに書き出す
+fn record(path: PathBuf, request: RecordedRequest, response: Response) -> Response {
+    let status = response.status();
+    let headers = response.headers().clone();
+
+    let recorded_headers = headers
+        .iter()
+        .filter(|(name, _)| *name != reqwest::header::SET_COOKIE)
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let recorder = Recorder {
+        path,
+        interaction: Interaction {
+            request,
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers: recorded_headers,
+                chunks: Vec::new(),
+            },
+        },
+        last: Instant::now(),
+    };
+    let body = RecordingStream {
+        inner: Box::pin(response.bytes_stream()),
+        recorder: Some(recorder),
+    };
+
+    let mut builder = http::Response::builder().status(status);
+    for (name, value) in &headers {
+        builder = builder.header(name, value);
+    }
+    Response::from(
+        builder
+            .body(reqwest::Body::wrap_stream(body))
+            .expect("headers copied from a valid response"),
+    )
+}
+
+/// 記録中の1往復分の通信
+struct Recorder {
+    path: PathBuf,
+    interaction: Interaction,
+    /// 直前のチャンクを受け取った時刻
+    last: Instant,
+}
+
+impl Recorder {
+    fn push(&mut self, bytes: &[u8]) {
+        self.interaction
+            .response
+            .chunks
+            .push(RecordedChunk::new(self.last.elapsed(), bytes));
+        self.last = Instant::now();
+    }
+
+    fn save(self) {
+        save_interaction(&self.path, &self.interaction);
+    }
+}
+
+/// 呼び出し側が読んだチャンクを記録し、本文の終わりでその場でカセットに書き出す
+///
+/// 別のタスクで書き出すと終了時に最後の記録が失われるため、読み終えた時点で同期的に書く。
+/// 呼び出し側が途中で読むのをやめた場合も、破棄されるときにそこまでの応答を残す
+struct RecordingStream<S> {
+    inner: std::pin::Pin<Box<S>>,
+    recorder: Option<Recorder>,
+}
+
+impl<S, B> futures::Stream for RecordingStream<S>
+where
+    S: futures::Stream<Item = reqwest::Result<B>>,
+    B: AsRef<[u8]>,
+{
+    type Item = reqwest::Result<B>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let item = futures::ready!(self.inner.as_mut().poll_next(cx));
+        match &item {
+            Some(Ok(bytes)) => {
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.push(bytes.as_ref());
+                }
+            }
+            Some(Err(_)) | None => {
+                if let Some(recorder) = self.recorder.take() {
+                    recorder.save();
+                }
+            }
+        }
+        std::task::Poll::Ready(item)
+    }
+}
+
+impl<S> Drop for RecordingStream<S> {
+    fn drop(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.save();
+        }
+    }
+}
+
+/// 記録した応答を、チャンクの間隔を再現しながら返す
+fn replay(recorded: RecordedResponse) -> Response {
+    let mut builder = http::Response::builder().status(recorded.status);
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name, value);
+    }
+
+    let chunks = futures::stream::iter(recorded.chunks).then(|chunk| async move {
+        tokio::time::sleep(Duration::from_millis(chunk.delay_ms)).await;
+        Ok::<_, std::io::Error>(chunk.bytes())
+    });
+    match builder.body(reqwest::Body::wrap_stream(chunks)) {
+        Ok(response) => Response::from(response),
+        Err(e) => {
+            error!("Invalid recorded response: {}", e);
+            missing_response("an invalid recording")
+        }
+    }
+}
+
+/// 記録がないリクエストへの応答（通常のAPIエラーとして表示させる）
+fn missing_response(key: &str) -> Response {
+    let body = serde_json::json!({
+        "error": {
+            "message": format!("no recorded response left for {}", key),
+            "type": "cassette_miss",
+        }
+    });
+    Response::from(
+        http::Response::builder()
+            .status(404)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .expect("static response is valid"),
+    )
+}
+
+fn replay_key(method: &str, url: &reqwest::Url) -> String {
+    format!("{} {}", method, url.path())
+}
+
+/// 記録順に並ぶファイル名（例: "0003-POST-v1_chat_completions.json"）
+fn file_name(index: usize, request: &RecordedRequest) -> String {
+    let path = reqwest::Url::parse(&request.url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    let path: String = path
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{:04}-{}-{}.json", index, request.method, path)
+}
+
+/// 既存の記録の後ろに続けて記録する
+fn next_file_index(dir: &Path) -> usize {
+    cassette_files(dir)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|path| path.file_name()?.to_str()?.split('-').next()?.parse().ok())
+        .map(|index: usize| index + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+fn cassette_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn load_interactions(dir: &Path) -> Result<HashMap<String, VecDeque<Interaction>>> {
+    let files = cassette_files(dir)
+        .map_err(|e| Error::Config(format!("cannot read cassette {}: {}", dir.display(), e)))?;
+
+    let mut interactions: HashMap<String, VecDeque<Interaction>> = HashMap::new();
+    for path in files {
+        let interaction: Interaction = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .map_err(|e| Error::Config(format!("invalid cassette {}: {}", path.display(), e)))?;
+        let url = reqwest::Url::parse(&interaction.request.url).map_err(|e| {
+            Error::Config(format!("invalid URL in cassette {}: {}", path.display(), e))
+        })?;
+
+        interactions
+            .entry(replay_key(&interaction.request.method, &url))
+            .or_default()
+            .push_back(interaction);
+    }
+    Ok(interactions)
+}
+
+fn save_interaction(path: &Path, interaction: &Interaction) {
+    let content = match serde_json::to_string_pretty(interaction) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Failed to serialize cassette: {}", e);
+            return;
+        }
+    };
+    match std::fs::write(path, content) {
+        Ok(()) => debug!("Recorded {}", path.display()),
+        Err(e) => error!("Failed to write cassette {}: {}", path.display(), e),
+    }
+}
@@ -1,3 +1,13 @@
+use crate::backend::{BackendConfig, BackendKind, ModelCache};
+use crate::features::voice::DEFAULT_SPEAKER;
+use crate::generation::GenerationParams;
+use crate::mcp::McpServerConfig;
+use crate::retry::RetryPolicy;
+use crate::structured::StructuredOutputConfig;
+use crate::summary::SummarizationConfig;
+use crate::tools::{ToolPermission, ToolsConfig};
+use crate::usage::{DailySpending, ModelPrice};
+use crate::voice_params::VoiceParams;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -7,6 +17,59 @@ use std::path::PathBuf;
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
     pub last_used_model: Option<String>,
+    pub backend: Option<String>,
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    /// OllamaのURL（省略時は`http://localhost:11434`。`base_url`はOpenAI互換API用）
+    pub ollama_base_url: Option<String>,
+    /// Ollamaに送るAPIキー（OpenAIの`api_key`は送らない）
+    pub ollama_api_key: Option<String>,
+    /// Ollamaへのリクエストに追加するヘッダー（`extra_headers`はOpenAI互換API用）
+    #[serde(default)]
+    pub ollama_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub ollama_options: serde_json::Map<String, serde_json::Value>,
+    pub ollama_keep_alive: Option<serde_json::Value>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// モデルごとのコンテキスト長（トークン数）の上書き
+    #[serde(default)]
+    pub context_limits: HashMap<String, usize>,
+    /// 古い会話を破棄せず要約して残す設定
+    #[serde(default)]
+    pub summarization: SummarizationConfig,
+    /// 生成パラメータの初期値（実行中は`/set`で変更できる）
+    #[serde(default)]
+    pub generation: GenerationParams,
+    /// 応答をJSONで受け取り、読み上げの声を切り替える設定
+    #[serde(default)]
+    pub structured_output: StructuredOutputConfig,
+    /// 読み上げに使うVOICEVOXのスタイルID（未設定ならずんだもん）
+    pub speaker_style: Option<u32>,
+    /// スタイルIDごとの読み上げの速さや高さ（未設定の値はエンジンの既定値）
+    #[serde(default)]
+    pub voice_params: HashMap<u32, VoiceParams>,
+    /// 組み込みツールの設定
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    /// 起動するMCPサーバー（キーはサーバー名）
+    #[serde(default)]
+    pub mcp_servers: HashMap<String, McpServerConfig>,
+    /// モデルごとの料金（USD / 100万トークン、キーはモデル名またはその前方一致）
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+    /// 1日の利用額の上限（USD）
+    pub daily_spending_cap: Option<f64>,
+    /// 今日の利用額（アプリが更新する）
+    #[serde(default)]
+    pub daily_spending: DailySpending,
+    /// 前回プロバイダーから取得したモデル一覧
+    pub model_cache: Option<ModelCache>,
+    /// モデル選択画面で先頭に表示するモデル
+    #[serde(default)]
+    pub favorite_models: Vec<String>,
 }
 
 impl AppConfig {
@@ -77,10 +140,132 @@ impl AppConfig {
         }
     }
 
+    pub fn get_backend(&self, env_backend: Option<&str>) -> BackendKind {
+        // Priority: environment variable > saved config > default
+        let name = env_backend.or(self.backend.as_deref());
+        match name {
+            Some(name) => BackendKind::from_name(name).unwrap_or_else(|| {
+                log::warn!("Unknown chat backend '{}', falling back to default", name);
+                BackendKind::default()
+            }),
+            None => BackendKind::default(),
+        }
+    }
+
+    /// バックエンドの接続設定を解決する
+    ///
+    /// 接続先・APIキー・ヘッダーはバックエンドごとに別々に解決し、OpenAIのキーをOllamaの接続先に送らない。
+    /// Priority: environment variable > saved config > default
+    pub fn backend_config(&self) -> BackendConfig {
+        let kind = self.get_backend(std::env::var("CHAT_BACKEND").ok().as_deref());
+
+        let config_api_key = match kind {
+            BackendKind::OpenAi => &self.api_key,
+            BackendKind::Ollama => &self.ollama_api_key,
+        };
+        let api_key = std::env::var(Self::api_key_env_key(kind))
+            .ok()
+            .or_else(|| config_api_key.clone())
+            .filter(|key| !key.is_empty());
+
+        let config_base_url = match kind {
+            BackendKind::OpenAi => &self.base_url,
+            BackendKind::Ollama => &self.ollama_base_url,
+        };
+        let base_url = std::env::var(Self::base_url_env_key(kind))
+            .ok()
+            .or_else(|| config_base_url.clone())
+            .filter(|url| !url.is_empty());
+
+        let mut extra_headers = match kind {
+            BackendKind::OpenAi => self.extra_headers.clone(),
+            BackendKind::Ollama => self.ollama_headers.clone(),
+        };
+        let headers_env_key = Self::headers_env_key(kind);
+        if let Ok(env_headers) = std::env::var(headers_env_key) {
+            match serde_json::from_str::<HashMap<String, String>>(&env_headers) {
+                Ok(headers) => extra_headers.extend(headers),
+                Err(e) => log::warn!("Failed to parse {}: {}", headers_env_key, e),
+            }
+        }
+
+        BackendConfig {
+            kind,
+            api_key,
+            base_url,
+            extra_headers,
+            options: self.ollama_options.clone(),
+            keep_alive: self.ollama_keep_alive.clone(),
+            retry_policy: self.retry.clone(),
+            context_limits: self.context_limits.clone(),
+            summarization: self.summarization.clone(),
+            generation: self.generation.clone(),
+            response_schema: self.structured_output.schema(),
+        }
+    }
+
+    fn api_key_env_key(kind: BackendKind) -> &'static str {
+        match kind {
+            BackendKind::OpenAi => "OPENAI_API_KEY",
+            BackendKind::Ollama => "OLLAMA_API_KEY",
+        }
+    }
+
+    fn headers_env_key(kind: BackendKind) -> &'static str {
+        match kind {
+            BackendKind::OpenAi => "OPENAI_EXTRA_HEADERS",
+            BackendKind::Ollama => "OLLAMA_EXTRA_HEADERS",
+        }
+    }
+
+    fn base_url_env_key(kind: BackendKind) -> &'static str {
+        match kind {
+            BackendKind::OpenAi => "OPENAI_BASE_URL",
+            BackendKind::Ollama => "OLLAMA_HOST",
+        }
+    }
+
     pub fn set_last_used_model(&mut self, model: String) {
         self.last_used_model = Some(model);
     }
 
+    pub fn set_tool_permission(&mut self, tool_name: String, permission: ToolPermission) {
+        self.tools.permissions.insert(tool_name, permission);
+    }
+
+    /// 接続先と一致するキャッシュ済みのモデル一覧
+    pub fn cached_models(&self, backend: &BackendConfig) -> Option<&ModelCache> {
+        self.model_cache.as_ref().filter(|cache| {
+            cache.backend == backend.kind.name() && cache.base_url == backend.endpoint()
+        })
+    }
+
+    pub fn set_model_cache(&mut self, backend: &BackendConfig, models: Vec<String>) {
+        self.model_cache = Some(ModelCache {
+            backend: backend.kind.name().to_string(),
+            base_url: backend.endpoint().to_string(),
+            models,
+            fetched_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        });
+    }
+
+    pub fn set_favorite_models(&mut self, models: Vec<String>) {
+        self.favorite_models = models;
+    }
+
+    pub fn set_speaker_style(&mut self, style_id: u32) {
+        self.speaker_style = Some(style_id);
+    }
+
+    /// 既定値に戻したスタイルは設定ファイルから消す
+    pub fn set_voice_params(&mut self, style_id: u32, params: VoiceParams) {
+        if params.is_empty() {
+            self.voice_params.remove(&style_id);
+        } else {
+            self.voice_params.insert(style_id, params);
+        }
+    }
+
     pub fn get_all_settings(&self) -> HashMap<String, String> {
         let mut settings = HashMap::new();
 
@@ -89,15 +274,162 @@ impl AppConfig {
             settings.insert("Last Used Model".to_string(), format!("{} [config]", model));
         }
 
-        // Environment variables
-        if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
-            let masked_key = format!("{}...{}", &api_key[..8], &api_key[api_key.len() - 8..]);
+        // Backend settings
+        let backend = self.get_backend(std::env::var("CHAT_BACKEND").ok().as_deref());
+        let source = if std::env::var("CHAT_BACKEND").is_ok() {
+            "env"
+        } else if self.backend.is_some() {
+            "config"
+        } else {
+            "default"
+        };
+        settings.insert(
+            "Chat Backend".to_string(),
+            format!("{} [{}]", backend.name(), source),
+        );
+
+        let backend_config = self.backend_config();
+        let env_or_config = |env_key: &str| {
+            if std::env::var(env_key).is_ok() {
+                "env"
+            } else {
+                "config"
+            }
+        };
+
+        if let Some(api_key) = &backend_config.api_key {
             settings.insert(
-                "OpenAI API Key".to_string(),
-                format!("{} [env]", masked_key),
+                "API Key".to_string(),
+                format!(
+                    "{} [{}]",
+                    mask_secret(api_key),
+                    env_or_config(Self::api_key_env_key(backend_config.kind))
+                ),
             );
+        } else {
+            settings.insert("API Key".to_string(), "Not set".to_string());
         }
 
+        if let Some(base_url) = &backend_config.base_url {
+            settings.insert(
+                "API Base URL".to_string(),
+                format!(
+                    "{} [{}]",
+                    base_url,
+                    env_or_config(Self::base_url_env_key(backend_config.kind))
+                ),
+            );
+        } else {
+            settings.insert(
+                "API Base URL".to_string(),
+                format!("{} [default]", backend_config.kind.default_base_url()),
+            );
+        }
+
+        if !backend_config.options.is_empty() {
+            settings.insert(
+                "Ollama Options".to_string(),
+                serde_json::Value::Object(backend_config.options.clone()).to_string(),
+            );
+        }
+
+        if !backend_config.extra_headers.is_empty() {
+            let mut header_names: Vec<&str> = backend_config
+                .extra_headers
+                .keys()
+                .map(|name| name.as_str())
+                .collect();
+            header_names.sort();
+            settings.insert("Extra Headers".to_string(), header_names.join(", "));
+        }
+
+        if self.structured_output.enabled {
+            let mut styles: Vec<String> = self
+                .structured_output
+                .styles
+                .iter()
+                .map(|(name, id)| format!("{}={}", name, id))
+                .collect();
+            styles.sort();
+            settings.insert(
+                "Structured Output".to_string(),
+                if styles.is_empty() {
+                    "Enabled [config]".to_string()
+                } else {
+                    format!("Enabled [config] ({})", styles.join(", "))
+                },
+            );
+        }
+
+        // Voice settings
+        settings.insert(
+            "Voice Style".to_string(),
+            match self.speaker_style {
+                Some(style_id) => format!("{} [config]", style_id),
+                None => format!("{} [default]", DEFAULT_SPEAKER),
+            },
+        );
+        if !self.voice_params.is_empty() {
+            let mut voice_params: Vec<String> = self
+                .voice_params
+                .iter()
+                .map(|(style_id, params)| format!("#{} {}", style_id, params.summary()))
+                .collect();
+            voice_params.sort();
+            settings.insert("Voice Parameters".to_string(), voice_params.join("; "));
+        }
+
+        // Tool settings
+        if !self.tools.enabled {
+            settings.insert("Tools".to_string(), "Disabled [config]".to_string());
+        } else {
+            let mut permissions: Vec<String> = self
+                .tools
+                .permissions
+                .iter()
+                .map(|(name, permission)| format!("{}={:?}", name, permission).to_lowercase())
+                .collect();
+            permissions.sort();
+            settings.insert(
+                "Tool Permissions".to_string(),
+                if permissions.is_empty() {
+                    "ask for every tool [default]".to_string()
+                } else {
+                    permissions.join(", ")
+                },
+            );
+        }
+
+        if !self.mcp_servers.is_empty() {
+            let mut server_names: Vec<&str> =
+                self.mcp_servers.keys().map(|name| name.as_str()).collect();
+            server_names.sort();
+            settings.insert("MCP Servers".to_string(), server_names.join(", "));
+        }
+
+        // Usage settings
+        settings.insert(
+            "Daily Spending Cap".to_string(),
+            match self.daily_spending_cap {
+                Some(cap) => format!("${:.2} [config]", cap),
+                None => "Not set".to_string(),
+            },
+        );
+        if !self.prices.is_empty() {
+            let mut prices: Vec<String> = self
+                .prices
+                .iter()
+                .map(|(model, price)| format!("{} ${}/${}", model, price.input, price.output))
+                .collect();
+            prices.sort();
+            settings.insert(
+                "Model Prices (per 1M in/out)".to_string(),
+                prices.join(", "),
+            );
+        }
+
+        // Environment variables
+
         if let Ok(env_model) = std::env::var("OPENAI_MODEL") {
             settings.insert(
                 "OpenAI Model (env)".to_string(),
@@ -148,10 +480,22 @@ impl AppConfig {
         }
 
         // Available models
-        settings.insert(
-            "Available Models".to_string(),
-            "gpt-5, gpt-5-mini, gpt-5-nano".to_string(),
-        );
+        let backend_config = self.backend_config();
+        let available_models = match self.cached_models(&backend_config) {
+            Some(cache) => format!(
+                "{} models [cached {}]",
+                cache.models.len(),
+                cache.fetched_at
+            ),
+            None => format!("{} [default]", backend.fallback_models().join(", ")),
+        };
+        settings.insert("Available Models".to_string(), available_models);
+        if !self.favorite_models.is_empty() {
+            settings.insert(
+                "Favorite Models".to_string(),
+                self.favorite_models.join(", "),
+            );
+        }
 
         settings
     }
@@ -161,3 +505,15 @@ impl AppConfig {
             .map(|project_dirs| project_dirs.config_dir().join("config.json"))
     }
 }
+
+/// APIキーなどの秘密情報を先頭と末尾だけ残して伏せる
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 16 {
+        return "*".repeat(chars.len());
+    }
+
+    let head: String = chars[..8].iter().collect();
+    let tail: String = chars[chars.len() - 8..].iter().collect();
+    format!("{}...{}", head, tail)
+}
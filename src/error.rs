@@ -0,0 +1,342 @@
+use std::fmt;
+use std::time::Duration;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// UIでの案内文を選ぶためのエラー分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    InvalidApiKey,
+    PermissionDenied,
+    RateLimited,
+    QuotaExceeded,
+    NotFound,
+    BadRequest,
+    ServerError,
+    Network,
+    Timeout,
+    StreamParse,
+    InvalidResponse,
+    EngineUnreachable,
+    Tts,
+    AudioDevice,
+    Tool,
+    Mcp,
+    Attachment,
+    Config,
+}
+
+impl ErrorCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorCategory::InvalidApiKey => "invalid API key",
+            ErrorCategory::PermissionDenied => "permission denied",
+            ErrorCategory::RateLimited => "rate limited",
+            ErrorCategory::QuotaExceeded => "quota exceeded",
+            ErrorCategory::NotFound => "not found",
+            ErrorCategory::BadRequest => "bad request",
+            ErrorCategory::ServerError => "server error",
+            ErrorCategory::Network => "network error",
+            ErrorCategory::Timeout => "timed out",
+            ErrorCategory::StreamParse => "stream parse error",
+            ErrorCategory::InvalidResponse => "invalid response",
+            ErrorCategory::EngineUnreachable => "engine unreachable",
+            ErrorCategory::Tts => "voice synthesis failed",
+            ErrorCategory::AudioDevice => "audio device error",
+            ErrorCategory::Tool => "tool failed",
+            ErrorCategory::Mcp => "MCP server error",
+            ErrorCategory::Attachment => "attachment failed",
+            ErrorCategory::Config => "configuration error",
+        }
+    }
+
+    /// ユーザーが次に何をすればよいかの案内
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ErrorCategory::InvalidApiKey => "Check OPENAI_API_KEY or api_key in the config file.",
+            ErrorCategory::PermissionDenied => {
+                "The API key has no access to this model or endpoint."
+            }
+            ErrorCategory::RateLimited => "Wait a moment and send the message again.",
+            ErrorCategory::QuotaExceeded => "Check the plan and billing of the provider.",
+            ErrorCategory::NotFound => "Check the model name (m) and the API base URL.",
+            ErrorCategory::BadRequest => "The provider rejected the request.",
+            ErrorCategory::ServerError => "The provider is having trouble. Try again later.",
+            ErrorCategory::Network => "Check the network connection and the API base URL.",
+            ErrorCategory::Timeout => "The server stopped responding. Try again later.",
+            ErrorCategory::StreamParse | ErrorCategory::InvalidResponse => {
+                "The server returned an unexpected response."
+            }
+            ErrorCategory::EngineUnreachable => {
+                "Check VOICEVOX_ENGINE_URL and that the VOICEVOX engine is running."
+            }
+            ErrorCategory::Tts => "The VOICEVOX engine could not synthesize the reply.",
+            ErrorCategory::AudioDevice => "Check the audio output device.",
+            ErrorCategory::Tool => "A tool called by the assistant failed.",
+            ErrorCategory::Mcp => "Check mcp_servers in the config file and that the server runs.",
+            ErrorCategory::Attachment => "Attach a readable PNG or JPEG file (up to 20 MB).",
+            ErrorCategory::Config => "Check the environment variables and the config file.",
+        }
+    }
+}
+
+/// クレート共通のエラー
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// エラーステータスが返ったが、本文からAPIエラーを読み取れなかった
+    HttpStatus {
+        status: u16,
+        body: String,
+        retry_after: Option<Duration>,
+    },
+    /// APIがエラーオブジェクトを返した
+    Api {
+        status: Option<u16>,
+        message: String,
+        error_type: Option<String>,
+        code: Option<String>,
+        retry_after: Option<Duration>,
+    },
+    /// リクエストの送受信に失敗した
+    Network(String),
+    /// 応答が一定時間途絶えた
+    Timeout(String),
+    /// ストリーミングレスポンスを解析できなかった
+    SseParse(String),
+    /// レスポンスが想定した形式ではなかった
+    InvalidResponse(String),
+    /// VOICEVOX Engineに接続できない
+    EngineUnreachable(String),
+    /// VOICEVOX Engineが合成に失敗した
+    Tts(String),
+    /// 音声出力デバイスの初期化・再生に失敗した
+    AudioDevice(String),
+    /// ツールの実行に失敗した
+    Tool(String),
+    /// MCPサーバーの起動・通信に失敗した
+    Mcp(String),
+    /// 添付する画像を読み込めなかった
+    Attachment(String),
+    /// 設定が不足・不正
+    Config(String),
+}
+
+impl Error {
+    /// エラーステータスのレスポンスを読み取り、APIエラーとして解釈する
+    pub async fn from_response(resp: reqwest::Response) -> Self {
+        let status = resp.status().as_u16();
+        let retry_after = parse_retry_after(resp.headers());
+        let body = resp.text().await.unwrap_or_default();
+        Self::from_status_body(status, &body).with_retry_after(retry_after)
+    }
+
+    /// ステータスコードとレスポンス本文からエラーを組み立てる
+    pub fn from_status_body(status: u16, body: &str) -> Self {
+        match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(json) => match Self::from_error_json(&json) {
+                Some(Error::Api {
+                    message,
+                    error_type,
+                    code,
+                    ..
+                }) => Error::Api {
+                    status: Some(status),
+                    message,
+                    error_type,
+                    code,
+                    retry_after: None,
+                },
+                _ => Error::HttpStatus {
+                    status,
+                    body: body.to_string(),
+                    retry_after: None,
+                },
+            },
+            Err(_) => Error::HttpStatus {
+                status,
+                body: body.to_string(),
+                retry_after: None,
+            },
+        }
+    }
+
+    /// `Retry-After`で指定された待機時間を設定する
+    pub fn with_retry_after(mut self, delay: Option<Duration>) -> Self {
+        match &mut self {
+            Error::HttpStatus { retry_after, .. } | Error::Api { retry_after, .. } => {
+                *retry_after = delay;
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// サーバーから指示された再試行までの待機時間
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::HttpStatus { retry_after, .. } | Error::Api { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// HTTPステータスコード（判明している場合）
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::HttpStatus { status, .. } => Some(*status),
+            Error::Api { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// `{"error": {...}}`または`{"error": "..."}`形式のエラーオブジェクトを読み取る
+    pub fn from_error_json(json: &serde_json::Value) -> Option<Self> {
+        let error = json.get("error")?;
+        if let Some(message) = error.as_str() {
+            return Some(Error::Api {
+                status: None,
+                message: message.to_string(),
+                error_type: None,
+                code: None,
+                retry_after: None,
+            });
+        }
+
+        let as_string = |value: &serde_json::Value| match value {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        };
+
+        Some(Error::Api {
+            status: None,
+            message: error
+                .get("message")
+                .and_then(as_string)
+                .unwrap_or_else(|| error.to_string()),
+            error_type: error.get("type").and_then(as_string),
+            code: error.get("code").and_then(as_string),
+            retry_after: None,
+        })
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::HttpStatus { status, .. } => Self::category_for_status(*status),
+            Error::Api {
+                status,
+                error_type,
+                code,
+                ..
+            } => {
+                let code = code.as_deref().or(error_type.as_deref());
+                match code {
+                    Some("invalid_api_key") => ErrorCategory::InvalidApiKey,
+                    Some("insufficient_quota") => ErrorCategory::QuotaExceeded,
+                    Some("rate_limit_exceeded") => ErrorCategory::RateLimited,
+                    Some("model_not_found") => ErrorCategory::NotFound,
+                    _ => status
+                        .map(Self::category_for_status)
+                        .unwrap_or(ErrorCategory::BadRequest),
+                }
+            }
+            Error::Network(_) => ErrorCategory::Network,
+            Error::Timeout(_) => ErrorCategory::Timeout,
+            Error::SseParse(_) => ErrorCategory::StreamParse,
+            Error::InvalidResponse(_) => ErrorCategory::InvalidResponse,
+            Error::EngineUnreachable(_) => ErrorCategory::EngineUnreachable,
+            Error::Tts(_) => ErrorCategory::Tts,
+            Error::AudioDevice(_) => ErrorCategory::AudioDevice,
+            Error::Tool(_) => ErrorCategory::Tool,
+            Error::Mcp(_) => ErrorCategory::Mcp,
+            Error::Attachment(_) => ErrorCategory::Attachment,
+            Error::Config(_) => ErrorCategory::Config,
+        }
+    }
+
+    fn category_for_status(status: u16) -> ErrorCategory {
+        match status {
+            401 => ErrorCategory::InvalidApiKey,
+            403 => ErrorCategory::PermissionDenied,
+            404 => ErrorCategory::NotFound,
+            429 => ErrorCategory::RateLimited,
+            500..=599 => ErrorCategory::ServerError,
+            _ => ErrorCategory::BadRequest,
+        }
+    }
+
+    /// UIに表示する案内付きのメッセージ
+    pub fn user_message(&self) -> String {
+        let category = self.category();
+        format!("[{}] {} ({})", category.label(), category.hint(), self)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::HttpStatus { status, body, .. } => {
+                if body.is_empty() {
+                    write!(f, "HTTP {}", status)
+                } else {
+                    write!(f, "HTTP {}: {}", status, body)
+                }
+            }
+            Error::Api {
+                status, message, ..
+            } => match status {
+                Some(status) => write!(f, "API error {}: {}", status, message),
+                None => write!(f, "API error: {}", message),
+            },
+            Error::Network(message) => write!(f, "network error: {}", message),
+            Error::Timeout(message) => write!(f, "timed out: {}", message),
+            Error::SseParse(message) => write!(f, "failed to parse stream: {}", message),
+            Error::InvalidResponse(message) => write!(f, "invalid response: {}", message),
+            Error::EngineUnreachable(message) => {
+                write!(f, "VOICEVOX engine unreachable: {}", message)
+            }
+            Error::Tts(message) => write!(f, "voice synthesis failed: {}", message),
+            Error::AudioDevice(message) => write!(f, "audio device error: {}", message),
+            Error::Tool(message) => write!(f, "tool failed: {}", message),
+            Error::Mcp(message) => write!(f, "MCP error: {}", message),
+            Error::Attachment(message) => write!(f, "attachment failed: {}", message),
+            Error::Config(message) => write!(f, "configuration error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Error::HttpStatus {
+                status: status.as_u16(),
+                body: String::new(),
+                retry_after: None,
+            },
+            None if e.is_timeout() => Error::Timeout(e.to_string()),
+            None if e.is_decode() => Error::InvalidResponse(e.to_string()),
+            None => Error::Network(e.to_string()),
+        }
+    }
+}
+
+/// `Retry-After`（秒）または`retry-after-ms`ヘッダーを読み取る
+///
+/// 数値でない値は無視し、`Duration`に収まらない長さは最大値として扱う（再試行時に上限で打ち切る）
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header_secs = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+    };
+    let to_duration =
+        |secs: f64| Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX);
+
+    if let Some(millis) = header_secs("retry-after-ms") {
+        return Some(to_duration(millis / 1000.0));
+    }
+    header_secs("retry-after").map(to_duration)
+}
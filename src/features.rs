@@ -1,5 +1,10 @@
+pub mod branch_select;
 pub mod chat;
+pub mod generation;
 pub mod model_select;
 pub mod settings;
 pub mod shared;
+pub mod speaker_select;
+pub mod tool_permission;
 pub mod voice;
+pub mod voice_settings;
@@ -0,0 +1,63 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use super::props::BranchSelectProps;
+
+/// 一覧に表示する本文の最大文字数
+const PREVIEW_CHARS: usize = 60;
+
+pub fn render_branch_select_screen(frame: &mut Frame, props: &BranchSelectProps) {
+    let area = frame.area();
+
+    let popup_area = ratatui::layout::Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+
+    let mut items = Vec::new();
+    for id in props.path {
+        let node = props.tree.node(*id);
+        let message = &node.message;
+        let style = props.theme.get_message_style(&message.role);
+
+        let first_line = message.content.lines().next().unwrap_or_default();
+        let mut preview: String = first_line.chars().take(PREVIEW_CHARS).collect();
+        if first_line.chars().count() > PREVIEW_CHARS || message.content.lines().count() > 1 {
+            preview.push('…');
+        }
+
+        // 兄弟のあるメッセージは左右で切り替えられることを示す
+        let branch = match message.branch {
+            Some((index, count)) => format!("◀ {}/{} ▶ ", index + 1, count),
+            None => "      ".to_string(),
+        };
+
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(branch, Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{}: ", message.role.display_name()), style),
+            Span::raw(preview),
+        ])));
+    }
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Branches (h/l:Switch Enter:Fork here Esc:Back)"),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray));
+
+    let mut list_state = ListState::default();
+    if !props.path.is_empty() {
+        list_state.select(Some(props.selected_index));
+    }
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_stateful_widget(list, popup_area, &mut list_state);
+}
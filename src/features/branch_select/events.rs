@@ -0,0 +1,33 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use tokio::sync::mpsc;
+
+use crate::features::chat::events::send_command;
+use crate::features::chat::state::{AppState, InputMode};
+use crate::features::chat::worker::WorkerCommand;
+
+pub fn handle_branch_select_mode(
+    key: KeyEvent,
+    state: &mut AppState,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) -> bool {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            state.input_mode = InputMode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => state.move_branch_selection_up(),
+        KeyCode::Down | KeyCode::Char('j') => state.move_branch_selection_down(),
+        KeyCode::Left | KeyCode::Char('h') if state.switch_selected_branch(-1) => {
+            send_command(command_tx, WorkerCommand::LoadHistory(state.tree.history()));
+        }
+        KeyCode::Right | KeyCode::Char('l') if state.switch_selected_branch(1) => {
+            send_command(command_tx, WorkerCommand::LoadHistory(state.tree.history()));
+        }
+        // 選択したメッセージの続きを入力して新しい枝を作る
+        KeyCode::Enter if state.fork_at_selected() => {
+            send_command(command_tx, WorkerCommand::LoadHistory(state.tree.history()));
+            state.input_mode = InputMode::Insert;
+        }
+        _ => {}
+    }
+    false
+}
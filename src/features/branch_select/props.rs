@@ -0,0 +1,11 @@
+use crate::features::chat::theme::ChatTheme;
+use crate::features::chat::tree::{MessageTree, NodeId};
+
+#[derive(Debug)]
+pub struct BranchSelectProps<'a> {
+    pub tree: &'a MessageTree,
+    /// 表示中の経路
+    pub path: &'a [NodeId],
+    pub selected_index: usize,
+    pub theme: &'a ChatTheme,
+}
@@ -3,4 +3,5 @@ pub mod events;
 pub mod props;
 pub mod state;
 pub mod theme;
+pub mod tree;
 pub mod worker;
@@ -11,6 +11,7 @@ use super::state::{AppState, InputMode, MessageRole};
 use crate::features::shared::text_utils::{
     calculate_input_height, calculate_multiline_cursor_position, wrap_text,
 };
+use crate::history::ContextUsage;
 
 pub fn render_ui(frame: &mut Frame, state: &AppState) {
     match state.input_mode {
@@ -18,9 +19,53 @@ pub fn render_ui(frame: &mut Frame, state: &AppState) {
             crate::features::model_select::component::render_model_select_screen(
                 frame,
                 &crate::features::model_select::props::ModelSelectProps {
-                    available_models: &state.available_models,
+                    visible_models: &state.visible_models(),
+                    favorite_models: &state.favorite_models,
                     current_model: &state.current_model,
                     selected_index: state.model_select_index,
+                    filter: &state.model_filter,
+                    filter_editing: state.model_filter_editing,
+                    source: &state.model_list_source,
+                    offline: state.model_list_offline,
+                    theme: &state.theme,
+                },
+            );
+        }
+        InputMode::Generation => {
+            crate::features::generation::component::render_generation_panel(
+                frame,
+                &crate::features::generation::props::GenerationPanelProps {
+                    params: &state.generation_params,
+                    backend: state.backend_kind,
+                    current_model: &state.current_model,
+                    selected_index: state.generation_index,
+                    editing: state.generation_input.as_deref(),
+                    error: state.generation_error.as_deref(),
+                    theme: &state.theme,
+                },
+            );
+        }
+        InputMode::SpeakerSelect => {
+            crate::features::speaker_select::component::render_speaker_select_screen(
+                frame,
+                &crate::features::speaker_select::props::SpeakerSelectProps {
+                    styles: &state.speaker_styles(),
+                    current_style: state.speaker_style,
+                    selected_index: state.speaker_select_index,
+                    status: &state.speaker_list_status,
+                    theme: &state.theme,
+                },
+            );
+        }
+        InputMode::VoiceSettings => {
+            crate::features::voice_settings::component::render_voice_settings_panel(
+                frame,
+                &crate::features::voice_settings::props::VoiceSettingsProps {
+                    params: &state.current_voice_params(),
+                    speaker_label: &state.speaker_label(state.speaker_style),
+                    selected_index: state.voice_index,
+                    editing: state.voice_input.as_deref(),
+                    error: state.voice_error.as_deref(),
                     theme: &state.theme,
                 },
             );
@@ -48,10 +93,14 @@ pub fn render_ui(frame: &mut Frame, state: &AppState) {
             render_message_list(
                 frame,
                 &ChatScreenProps {
-                    messages: &state.messages,
+                    messages: &state.messages(),
                     theme: &state.theme,
                     scroll_offset: state.scroll_offset,
                     auto_scroll_enabled: state.auto_scroll_enabled,
+                    show_reasoning: state.show_reasoning,
+                    status_message: state.status_message.as_deref(),
+                    context_usage: state.context_usage,
+                    usage_status: state.usage.status_line(),
                 },
                 main_layout[0],
             );
@@ -66,6 +115,31 @@ pub fn render_ui(frame: &mut Frame, state: &AppState) {
                 },
                 main_layout[1],
             );
+
+            if let (InputMode::ToolPermission, Some(request)) =
+                (state.input_mode, &state.pending_permission)
+            {
+                crate::features::tool_permission::component::render_tool_permission_dialog(
+                    frame,
+                    &crate::features::tool_permission::props::ToolPermissionProps {
+                        tool_name: &request.tool_name,
+                        arguments: &request.arguments,
+                        theme: &state.theme,
+                    },
+                );
+            }
+
+            if state.input_mode == InputMode::BranchSelect {
+                crate::features::branch_select::component::render_branch_select_screen(
+                    frame,
+                    &crate::features::branch_select::props::BranchSelectProps {
+                        tree: &state.tree,
+                        path: &state.tree.path(),
+                        selected_index: state.branch_select_index,
+                        theme: &state.theme,
+                    },
+                );
+            }
         }
     }
 }
@@ -76,6 +150,7 @@ fn render_message_list(frame: &mut Frame, props: &ChatScreenProps, area: ratatui
         MessageRole::User.display_name(),
         MessageRole::Assistant.display_name(),
         MessageRole::System.display_name(),
+        MessageRole::Tool.display_name(),
     ]
     .iter()
     .map(|s| s.len())
@@ -92,27 +167,55 @@ fn render_message_list(frame: &mut Frame, props: &ChatScreenProps, area: ratatui
         let style = props.theme.get_message_style(&msg.role);
         let prefix = msg.role.formatted_prefix(max_prefix_width);
 
+        // 思考過程を本文の上に暗く表示し、その後に本文を続ける
+        let reasoning_lines = msg
+            .reasoning_block(props.show_reasoning)
+            .map(|block| wrap_text(&block, text_width))
+            .unwrap_or_default();
+        let reasoning_style = props.theme.get_reasoning_style();
+
         // メッセージ内容を指定幅で折り返し
-        let wrapped_lines = wrap_text(&msg.content, text_width);
+        let wrapped_lines = wrap_text(&msg.display_content(), text_width);
 
-        for (i, line_content) in wrapped_lines.iter().enumerate() {
-            let line_text = if i == 0 {
+        let lines = reasoning_lines
+            .iter()
+            .map(|line| (line, reasoning_style))
+            .chain(wrapped_lines.iter().map(|line| (line, style)));
+        for (i, (line_content, line_style)) in lines.enumerate() {
+            if i == 0 {
                 // 最初の行にはプレフィックスを付ける
-                format!("{}{}", prefix, line_content)
+                all_lines.push(ListItem::new(Line::from(vec![
+                    Span::styled(prefix.clone(), style),
+                    Span::styled(line_content.clone(), line_style),
+                ])));
             } else {
                 // 2行目以降は適切なインデントを追加
-                format!("{}{}", " ".repeat(max_prefix_width + 2), line_content)
-            };
-
-            all_lines.push(ListItem::new(Line::from(Span::styled(line_text, style))));
+                all_lines.push(ListItem::new(Line::from(Span::styled(
+                    format!("{}{}", " ".repeat(max_prefix_width + 2), line_content),
+                    line_style,
+                ))));
+            }
         }
     }
 
     let mut list_state = ListState::default();
     let total_lines = all_lines.len();
 
+    let title = match props.status_message {
+        Some(status) => format!("Chat History - {}", status),
+        None => "Chat History".to_string(),
+    };
+
+    let mut block = Block::default().borders(Borders::ALL).title(title);
+    if let Some(usage_status) = &props.usage_status {
+        block = block.title_bottom(Line::from(usage_status.as_str()).left_aligned());
+    }
+    if let Some(usage) = props.context_usage {
+        block = block.title_bottom(Line::from(format_context_usage(&usage)).right_aligned());
+    }
+
     let messages_list = List::new(all_lines)
-        .block(Block::default().borders(Borders::ALL).title("Chat History"))
+        .block(block)
         .highlight_style(props.theme.get_highlight_style());
 
     if total_lines > 0 {
@@ -124,18 +227,62 @@ fn render_message_list(frame: &mut Frame, props: &ChatScreenProps, area: ratatui
     frame.render_stateful_widget(messages_list, area, &mut list_state);
 }
 
+/// コンテキスト残量の表示文字列（例: "context 12% used, 350k tokens left"）
+fn format_context_usage(usage: &ContextUsage) -> String {
+    let percent = (usage.used_tokens * 100)
+        .checked_div(usage.budget_tokens)
+        .unwrap_or(100)
+        .min(100);
+    format!(
+        " context {}% used, {} tokens left ",
+        percent,
+        format_token_count(usage.remaining_tokens())
+    )
+}
+
+fn format_token_count(tokens: usize) -> String {
+    if tokens >= 1_000 {
+        format!("{:.1}k", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
 fn render_input_area(frame: &mut Frame, props: &InputAreaProps, area: ratatui::layout::Rect) {
     let (mode_text, help_text) = match props.input_mode {
         InputMode::Normal => (
             "-- NORMAL --",
-            "i:Insert m:Model s:Settings q:Quit j/k:Scroll g/G:Top/Bottom",
+            "i:Insert r:Regenerate e:Edit a:Alternative b:Branches t:Thinking m:Model v:Voice V:VoiceParams p:Params s:Settings q:Quit j/k:Scroll g/G:Top/Bottom Esc:Cancel",
         ),
         InputMode::Insert => (
             "-- INSERT --",
-            "Esc:Normal Enter:Send /model:ModelSelect Ctrl+N:NewLine",
+            "Esc:Normal Enter:Send /model:ModelSelect Ctrl+N:NewLine Ctrl+C:Cancel",
+        ),
+        InputMode::ModelSelect => (
+            "-- MODEL SELECT --",
+            "j/k:Navigate Enter:Select /:Filter p:Pin Esc:Cancel",
         ),
-        InputMode::ModelSelect => ("-- MODEL SELECT --", "j/k:Navigate Enter:Select Esc:Cancel"),
         InputMode::Settings => ("-- SETTINGS --", "j/k:Scroll Esc:Back q:Quit"),
+        InputMode::BranchSelect => (
+            "-- BRANCHES --",
+            "j/k:Navigate h/l:Switch Enter:Fork Esc:Back",
+        ),
+        InputMode::Generation => (
+            "-- PARAMETERS --",
+            "j/k:Navigate Enter:Edit d:Default Esc:Back",
+        ),
+        InputMode::SpeakerSelect => (
+            "-- VOICE SELECT --",
+            "j/k:Navigate Space:Preview Enter:Select r:Reload Esc:Back",
+        ),
+        InputMode::VoiceSettings => (
+            "-- VOICE PARAMETERS --",
+            "j/k:Navigate h/l:Adjust Enter:Edit d:Default Space:Preview Esc:Back",
+        ),
+        InputMode::ToolPermission => (
+            "-- TOOL PERMISSION --",
+            "y:Allow a:Always Allow n:Deny d:Always Deny",
+        ),
     };
 
     let border_color = props
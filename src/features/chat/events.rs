@@ -1,7 +1,14 @@
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use tokio::sync::mpsc;
 
-use super::state::{AppState, Content, ErrorMessage, InputMode, MessageId, MessageRole};
+use super::state::{AppState, Content, InputMode, MessageId, MessageRole, ModelListSource};
+use super::worker::WorkerCommand;
+use crate::error::Error;
+use crate::generation::GenerationParam;
+use crate::history::{ContextUsage, Message};
+use crate::structured::StructuredReply;
+use crate::tools::PermissionRequest;
+use crate::usage::TokenUsage;
 
 #[derive(Debug, Clone)]
 pub enum ScrollAction {
@@ -16,8 +23,52 @@ pub enum ChatEvent {
     StreamingStart(MessageId),
     StreamingChunk(MessageId, Content),
     StreamingComplete(MessageId),
-    Error(ErrorMessage),
+    /// 推論モデルの思考過程のチャンク
+    ReasoningChunk(MessageId, Content),
+    /// 構造化出力の応答を読み取った（`StreamingComplete`の直前に届く）
+    StructuredReply(StructuredReply),
+    /// ユーザーのキャンセルで生成が中断された
+    StreamingCancelled(MessageId),
+    /// 一時的な失敗のため再試行を待機している
+    Retrying {
+        attempt: u32,
+        max_retries: u32,
+    },
+    /// 古い会話を要約している
+    Summarizing,
+    /// アシスタントがツールを呼び出した
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    /// ツールを実行してよいかユーザーに確認する
+    ToolPermission(PermissionRequest),
+    /// ツールの実行が終わった
+    ToolResult {
+        id: String,
+        result: String,
+    },
+    Error(Error),
+    /// ChatWorkerがモデルを切り替えた
     ModelChanged(String),
+    /// ChatWorkerがシステムプロンプトを置き換えた
+    SystemPromptChanged,
+    /// ChatWorkerが会話履歴を破棄した
+    HistoryReset,
+    /// 応答の生成が終わり、ChatWorkerが履歴に加えたメッセージ
+    ReplyRecorded(Vec<Message>),
+    /// プロバイダーから取得したモデル一覧
+    ModelsLoaded(Vec<String>),
+    /// モデル一覧を取得できなかった（キャッシュか組み込みの一覧を使い続ける）
+    ModelsUnavailable(Error),
+    /// 履歴が使用しているコンテキストの概算
+    ContextUsage(ContextUsage),
+    /// 1ターン分のトークン数（ツール呼び出しの往復を含む）
+    Usage {
+        model: String,
+        usage: TokenUsage,
+    },
 }
 
 pub fn handle_chat_event(app_state: &mut AppState, event: ChatEvent) {
@@ -29,27 +80,111 @@ pub fn handle_chat_event(app_state: &mut AppState, event: ChatEvent) {
             // message_idとactual_idの対応を内部で管理する必要がある場合は追加実装
         }
         ChatEvent::StreamingChunk(_message_id, content) => {
+            app_state.clear_status();
             // 最後に追加されたストリーミングメッセージにcontentを追加
-            if let Some(last_message) = app_state.messages.last_mut() {
+            if let Some(last_message) = app_state.tree.head_message_mut() {
                 if last_message.is_streaming {
                     last_message.content.push_str(&content);
                 }
             }
         }
+        ChatEvent::ReasoningChunk(_message_id, reasoning) => {
+            app_state.clear_status();
+            if let Some(last_message) = app_state.tree.head_message_mut() {
+                if last_message.is_streaming {
+                    last_message.reasoning.push_str(&reasoning);
+                }
+            }
+        }
         ChatEvent::StreamingComplete(_message_id) => {
+            app_state.clear_status();
             // 最後に追加されたストリーミングメッセージを完了状態にする
-            if let Some(last_message) = app_state.messages.last_mut() {
+            if let Some(last_message) = app_state.tree.head_message_mut() {
                 if last_message.is_streaming {
                     last_message.is_streaming = false;
                 }
             }
         }
-        ChatEvent::Error(error_msg) => {
-            app_state.add_message(MessageRole::System, format!("Error: {}", error_msg));
+        ChatEvent::StructuredReply(reply) => {
+            app_state.apply_structured_reply(reply);
+        }
+        ChatEvent::StreamingCancelled(_message_id) => {
+            app_state.clear_status();
+            app_state.dismiss_tool_permission();
+            if app_state.truncate_streaming_message() {
+                app_state.add_message(MessageRole::System, "Response cancelled".to_string());
+            }
+        }
+        ChatEvent::Retrying {
+            attempt,
+            max_retries,
+        } => {
+            app_state.set_status(format!("retrying ({}/{})…", attempt, max_retries));
+        }
+        ChatEvent::Summarizing => {
+            app_state.set_status("summarizing earlier conversation…".to_string());
+        }
+        ChatEvent::ToolCall {
+            id,
+            name,
+            arguments,
+        } => {
+            app_state.start_tool_message(id, &name, &arguments);
+        }
+        ChatEvent::ToolPermission(request) => {
+            app_state.request_tool_permission(request);
+        }
+        ChatEvent::ToolResult { id, result } => {
+            app_state.finish_tool_message(&id, &result);
+        }
+        ChatEvent::Error(error) => {
+            app_state.clear_status();
+            // 生成途中で失敗した場合はストリーミング表示を終了させる
+            if let Some(last_message) = app_state.tree.head_message_mut() {
+                if last_message.is_streaming {
+                    last_message.is_streaming = false;
+                }
+            }
+            app_state.add_message(
+                MessageRole::System,
+                format!("Error: {}", error.user_message()),
+            );
+        }
+        ChatEvent::ContextUsage(usage) => {
+            app_state.context_usage = Some(usage);
+        }
+        ChatEvent::Usage { model, usage } => {
+            app_state.record_usage(&model, usage);
         }
         ChatEvent::ModelChanged(model) => {
             app_state.set_current_model(model.clone());
             app_state.add_message(MessageRole::System, format!("Model changed to: {}", model));
+            if let Some(note) = app_state.unsupported_generation_note() {
+                app_state.add_message(MessageRole::System, note);
+            }
+        }
+        ChatEvent::SystemPromptChanged => {
+            app_state.add_message(MessageRole::System, "System prompt updated".to_string());
+        }
+        ChatEvent::HistoryReset => {
+            app_state.context_usage = None;
+            // 破棄した会話は分岐の一覧から辿れるよう、新しい会話を別の枝として始める
+            app_state.tree.fork_at(None);
+            app_state.add_message(
+                MessageRole::System,
+                "Conversation history cleared".to_string(),
+            );
+        }
+        ChatEvent::ReplyRecorded(history) => {
+            app_state.record_reply_history(history);
+        }
+        ChatEvent::ModelsLoaded(models) => {
+            app_state.model_list_offline = false;
+            app_state.set_available_models(models, ModelListSource::Provider);
+        }
+        ChatEvent::ModelsUnavailable(error) => {
+            log::warn!("Using offline model list: {}", error);
+            app_state.model_list_offline = true;
         }
     }
 }
@@ -57,33 +192,76 @@ pub fn handle_chat_event(app_state: &mut AppState, event: ChatEvent) {
 pub fn handle_key_event(
     key: KeyEvent,
     state: &mut AppState,
-    user_input_tx: Option<&mpsc::Sender<String>>,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
 ) -> (bool, Option<ScrollAction>) {
     if key.kind != KeyEventKind::Press {
         return (false, None);
     }
 
+    // Ctrl+Cはどのチャット系モードからでも生成と読み上げを中止する
+    if key.code == KeyCode::Char('c')
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && matches!(state.input_mode, InputMode::Normal | InputMode::Insert)
+    {
+        state.request_cancel();
+        return (false, None);
+    }
+
     match state.input_mode {
-        InputMode::Normal => handle_normal_mode(key, state),
-        InputMode::Insert => handle_insert_mode(key, state, user_input_tx),
+        InputMode::Normal => handle_normal_mode(key, state, command_tx),
+        InputMode::Insert => handle_insert_mode(key, state, command_tx),
         InputMode::ModelSelect => {
-            let should_quit =
-                crate::features::model_select::events::handle_model_select_mode(key, state);
+            let should_quit = crate::features::model_select::events::handle_model_select_mode(
+                key, state, command_tx,
+            );
             (should_quit, None)
         }
         InputMode::Settings => {
             let should_quit = crate::features::settings::events::handle_settings_mode(key, state);
             (should_quit, None)
         }
+        InputMode::Generation => {
+            let should_quit =
+                crate::features::generation::events::handle_generation_mode(key, state, command_tx);
+            (should_quit, None)
+        }
+        InputMode::BranchSelect => {
+            let should_quit = crate::features::branch_select::events::handle_branch_select_mode(
+                key, state, command_tx,
+            );
+            (should_quit, Some(ScrollAction::ToBottom))
+        }
+        InputMode::SpeakerSelect => {
+            let should_quit =
+                crate::features::speaker_select::events::handle_speaker_select_mode(key, state);
+            (should_quit, None)
+        }
+        InputMode::VoiceSettings => {
+            let should_quit =
+                crate::features::voice_settings::events::handle_voice_settings_mode(key, state);
+            (should_quit, None)
+        }
+        InputMode::ToolPermission => {
+            crate::features::tool_permission::events::handle_tool_permission_mode(key, state);
+            (false, None)
+        }
     }
 }
 
-fn handle_normal_mode(key: KeyEvent, state: &mut AppState) -> (bool, Option<ScrollAction>) {
+fn handle_normal_mode(
+    key: KeyEvent,
+    state: &mut AppState,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) -> (bool, Option<ScrollAction>) {
     match key.code {
         KeyCode::Char('q') => {
             state.should_quit = true;
             (true, None)
         }
+        KeyCode::Esc => {
+            state.request_cancel();
+            (false, None)
+        }
         KeyCode::Up | KeyCode::Char('k') => (false, Some(ScrollAction::Up)),
         KeyCode::Down | KeyCode::Char('j') => (false, Some(ScrollAction::Down)),
         KeyCode::Char('g') => (false, Some(ScrollAction::ToTop)),
@@ -92,6 +270,10 @@ fn handle_normal_mode(key: KeyEvent, state: &mut AppState) -> (bool, Option<Scro
             state.input_mode = InputMode::Insert;
             (false, None)
         }
+        KeyCode::Char('t') => {
+            state.show_reasoning = !state.show_reasoning;
+            (false, None)
+        }
         KeyCode::Char('m') => {
             state.input_mode = InputMode::ModelSelect;
             (false, None)
@@ -100,6 +282,41 @@ fn handle_normal_mode(key: KeyEvent, state: &mut AppState) -> (bool, Option<Scro
             state.input_mode = InputMode::Settings;
             (false, None)
         }
+        KeyCode::Char('v') => {
+            state.open_speaker_select();
+            (false, None)
+        }
+        KeyCode::Char('V') => {
+            state.input_mode = InputMode::VoiceSettings;
+            (false, None)
+        }
+        KeyCode::Char('p') => {
+            state.input_mode = InputMode::Generation;
+            (false, None)
+        }
+        KeyCode::Char('r') => {
+            if check_spending_cap(state) && state.start_regenerate() {
+                send_command(command_tx, WorkerCommand::LoadHistory(state.tree.history()));
+                send_command(command_tx, WorkerCommand::Respond);
+                state.enable_auto_scroll();
+            }
+            (false, Some(ScrollAction::ToBottom))
+        }
+        KeyCode::Char('e') => {
+            state.start_edit_last_message();
+            (false, None)
+        }
+        KeyCode::Char('a') => {
+            // 生成済みの応答を切り替えるだけでリクエストはしないので、上限額に達していても使える
+            if state.cycle_alternative() {
+                send_command(command_tx, WorkerCommand::LoadHistory(state.tree.history()));
+            }
+            (false, Some(ScrollAction::ToBottom))
+        }
+        KeyCode::Char('b') => {
+            state.open_branch_select();
+            (false, None)
+        }
         _ => (false, None),
     }
 }
@@ -107,15 +324,17 @@ fn handle_normal_mode(key: KeyEvent, state: &mut AppState) -> (bool, Option<Scro
 fn handle_insert_mode(
     key: KeyEvent,
     state: &mut AppState,
-    user_input_tx: Option<&mpsc::Sender<String>>,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
 ) -> (bool, Option<ScrollAction>) {
     match key.code {
         KeyCode::Esc => {
+            state.cancel_edit_last_message();
             state.input_mode = InputMode::Normal;
             (false, None)
         }
         KeyCode::Enter => {
-            if !state.current_input.trim().is_empty() {
+            // 画像だけを添付した発言も送れる
+            if !state.current_input.trim().is_empty() || !state.pending_attachments.is_empty() {
                 // Check for slash commands
                 if state.current_input.trim() == "/model" {
                     state.input_mode = InputMode::ModelSelect;
@@ -127,24 +346,89 @@ fn handle_insert_mode(
                     state.clear_input();
                     return (false, None);
                 }
+                if state.current_input.trim() == "/branches" {
+                    state.clear_input();
+                    state.open_branch_select();
+                    return (false, None);
+                }
+                if state.current_input.trim() == "/speakers" {
+                    state.clear_input();
+                    state.open_speaker_select();
+                    return (false, None);
+                }
+                if state.current_input.trim() == "/voice" {
+                    state.input_mode = InputMode::VoiceSettings;
+                    state.clear_input();
+                    return (false, None);
+                }
+                if state.current_input.trim() == "/params" {
+                    state.input_mode = InputMode::Generation;
+                    state.clear_input();
+                    return (false, None);
+                }
+                if let Some(args) = state.current_input.trim().strip_prefix("/set") {
+                    if args.is_empty() || args.starts_with(' ') {
+                        let args = args.trim().to_string();
+                        set_generation_param(state, &args, command_tx);
+                        state.clear_input();
+                        state.input_mode = InputMode::Normal;
+                        return (false, Some(ScrollAction::ToBottom));
+                    }
+                }
+                if let Some(path) = state.current_input.trim().strip_prefix("/attach ") {
+                    let path = path.to_string();
+                    state.attach_image(&path);
+                    state.clear_input();
+                    return (false, Some(ScrollAction::ToBottom));
+                }
+                if state.current_input.trim() == "/reset" {
+                    send_command(command_tx, WorkerCommand::ResetHistory);
+                    state.clear_input();
+                    state.input_mode = InputMode::Normal;
+                    return (false, Some(ScrollAction::ToBottom));
+                }
+                if let Some(prompt) = state.current_input.trim().strip_prefix("/system ") {
+                    send_command(
+                        command_tx,
+                        WorkerCommand::SetSystemPrompt(prompt.trim().to_string()),
+                    );
+                    state.clear_input();
+                    state.input_mode = InputMode::Normal;
+                    return (false, Some(ScrollAction::ToBottom));
+                }
 
-                // Enterならメッセージ送信
-                let _user_id = state.add_message(MessageRole::User, state.current_input.clone());
+                // 生成中の応答の後ろに発言を加えると会話の木が壊れるので、完了を待ってもらう
+                if state.is_busy() {
+                    state.set_status("wait for the reply to finish (Esc to cancel)".to_string());
+                    return (false, None);
+                }
+
+                if !check_spending_cap(state) {
+                    return (false, Some(ScrollAction::ToBottom));
+                }
 
                 // 新しいメッセージ送信時に自動スクロールを再有効化
                 state.enable_auto_scroll();
 
-                // ChatWorkerに入力を送信
-                if let Some(tx) = user_input_tx {
-                    let input = state.current_input.clone();
-                    let tx_clone = tx.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = tx_clone.send(input).await {
-                            eprintln!("Failed to send user input to ChatWorker: {}", e);
-                        }
-                    });
+                // 編集した発言は元の発言の兄弟として分岐させる
+                if let Some(history) = state.fork_for_edit() {
+                    send_command(command_tx, WorkerCommand::LoadHistory(history));
                 }
 
+                // Enterならメッセージ送信
+                let input = state.current_input.clone();
+                let images = std::mem::take(&mut state.pending_attachments);
+                state.push_user_message(input.clone(), images.clone());
+
+                // ChatWorkerに入力を送信
+                send_command(
+                    command_tx,
+                    WorkerCommand::UserInput {
+                        text: input,
+                        images,
+                    },
+                );
+
                 state.clear_input();
                 // Normalモードに戻る
                 state.input_mode = InputMode::Normal;
@@ -179,3 +463,73 @@ fn handle_insert_mode(
         _ => (false, None),
     }
 }
+
+/// `/set <パラメータ> [値]`を処理する（値を省略すると既定値に戻す）
+fn set_generation_param(
+    state: &mut AppState,
+    args: &str,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) {
+    let (name, value) = args.split_once(' ').unwrap_or((args, ""));
+    let Some(param) = GenerationParam::from_name(name) else {
+        let names: Vec<&str> = GenerationParam::ALL.iter().map(|p| p.name()).collect();
+        state.add_message(
+            MessageRole::System,
+            format!("Usage: /set <{}> [value]", names.join("|")),
+        );
+        return;
+    };
+
+    if let Err(e) = state.generation_params.set(param, value) {
+        handle_chat_event(state, ChatEvent::Error(e));
+        return;
+    }
+    send_command(
+        command_tx,
+        WorkerCommand::SetGenerationParams(state.generation_params.clone()),
+    );
+
+    let value = state
+        .generation_params
+        .value(param)
+        .unwrap_or_else(|| "default".to_string());
+    state.add_message(
+        MessageRole::System,
+        format!("{} set to {}", param.name(), value),
+    );
+    if let Some(note) = state.unsupported_generation_note() {
+        state.add_message(MessageRole::System, note);
+    }
+}
+
+/// 1日の上限額に達していないか確かめる（達していれば通知して`false`）
+///
+/// 応答の生成につながる操作はすべて、ChatWorkerに指示を送る前にこれを呼ぶ
+fn check_spending_cap(state: &mut AppState) -> bool {
+    if !state.usage.cap_reached() {
+        return true;
+    }
+    let cap = state.usage.daily_cap().unwrap_or_default();
+    state.add_message(
+        MessageRole::System,
+        format!(
+            "Daily spending cap of ${:.2} reached. Raise daily_spending_cap in the config file to continue.",
+            cap
+        ),
+    );
+    false
+}
+
+/// ChatWorkerに指示を送る
+///
+/// UIスレッドから同期的に送るので、続けて送った指示（`LoadHistory`の後の`Respond`など）は送った順に届く
+pub fn send_command(
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+    command: WorkerCommand,
+) {
+    if let Some(tx) = command_tx {
+        if let Err(e) = tx.send(command) {
+            log::error!("Failed to send command to ChatWorker: {}", e);
+        }
+    }
+}
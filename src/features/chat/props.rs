@@ -1,12 +1,19 @@
 use crate::features::chat::state::{ChatMessage, InputMode};
 use crate::features::chat::theme::ChatTheme;
+use crate::history::ContextUsage;
 
 #[derive(Debug)]
 pub struct ChatScreenProps<'a> {
-    pub messages: &'a [ChatMessage],
+    pub messages: &'a [&'a ChatMessage],
     pub theme: &'a ChatTheme,
     pub scroll_offset: usize,
     pub auto_scroll_enabled: bool,
+    /// 思考過程を展開して表示するか
+    pub show_reasoning: bool,
+    pub status_message: Option<&'a str>,
+    pub context_usage: Option<ContextUsage>,
+    /// トークン数と料金のステータス行
+    pub usage_status: Option<String>,
 }
 
 #[derive(Debug)]
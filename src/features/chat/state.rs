@@ -1,11 +1,21 @@
 use uuid::Uuid;
 
 use super::theme::{ChatTheme, ThemePreset};
+use super::tree::{MessageTree, NodeId};
+use crate::attachment::ImageAttachment;
+use crate::audio::{Speaker, SpeakerStyle};
+use crate::backend::BackendKind;
+use crate::features::voice::DEFAULT_SPEAKER;
+use crate::generation::{GenerationParam, GenerationParams};
+use crate::history::{ContextUsage, Message};
+use crate::structured::{StructuredOutputConfig, StructuredReply};
+use crate::tools::{PermissionDecision, PermissionRequest, ToolPermission};
+use crate::usage::{self, TokenUsage, UsageTracker};
+use crate::voice_params::{VoiceParam, VoiceParams};
 use std::collections::HashMap;
 
 pub type MessageId = String;
 pub type Content = String;
-pub type ErrorMessage = String;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
@@ -13,6 +23,38 @@ pub enum InputMode {
     Insert,
     ModelSelect,
     Settings,
+    /// 生成パラメータの設定パネル
+    Generation,
+    /// 会話の分岐の一覧
+    BranchSelect,
+    /// ツール実行の確認ダイアログ
+    ToolPermission,
+    /// 読み上げの声の選択
+    SpeakerSelect,
+    /// 読み上げパラメータの設定パネル
+    VoiceSettings,
+}
+
+/// モデル選択画面に表示している一覧の取得元
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelListSource {
+    /// 組み込みの一覧
+    Fallback,
+    /// 前回取得した一覧（取得日時）
+    Cache(String),
+    /// 起動後にプロバイダーから取得した一覧
+    Provider,
+}
+
+/// 話者選択画面の一覧の状態
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpeakerListStatus {
+    /// まだ取得していない
+    NotLoaded,
+    Loading,
+    Loaded,
+    /// 取得に失敗した（エラーの内容）
+    Failed(String),
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +63,20 @@ pub struct ChatMessage {
     pub role: MessageRole,
     pub content: Content,
     pub is_streaming: bool,
+    /// ユーザーのキャンセルで生成が途中終了したか
+    pub is_truncated: bool,
+    /// この応答の生成に使ったトークン数
+    pub usage: Option<TokenUsage>,
+    /// この応答の料金（USD、料金表にあるモデルのみ）
+    pub cost: Option<f64>,
+    /// 兄弟のメッセージがある場合の分岐の番号と数
+    pub branch: Option<(usize, usize)>,
+    /// 読み上げに使うVOICEVOXのスタイルID（構造化出力で選ばれた場合）
+    pub voice_style: Option<u32>,
+    /// ユーザー発言に添付した画像
+    pub attachments: Vec<ImageAttachment>,
+    /// 推論モデルの思考過程（表示のみで、読み上げにも履歴にも使わない）
+    pub reasoning: String,
 }
 
 impl ChatMessage {
@@ -30,6 +86,13 @@ impl ChatMessage {
             role,
             content,
             is_streaming: false,
+            is_truncated: false,
+            usage: None,
+            cost: None,
+            branch: None,
+            voice_style: None,
+            attachments: Vec::new(),
+            reasoning: String::new(),
         }
     }
 
@@ -39,8 +102,49 @@ impl ChatMessage {
             role,
             content: initial_content,
             is_streaming: true,
+            is_truncated: false,
+            usage: None,
+            cost: None,
+            branch: None,
+            voice_style: None,
+            attachments: Vec::new(),
+            reasoning: String::new(),
+        }
+    }
+
+    /// 本文の上に表示する思考過程（折りたたみ時は見出しと文字数だけ）
+    pub fn reasoning_block(&self, expanded: bool) -> Option<String> {
+        if self.reasoning.is_empty() {
+            return None;
+        }
+        if expanded {
+            Some(format!("▾ Thinking\n{}", self.reasoning.trim()))
+        } else {
+            Some(format!(
+                "▸ Thinking ({} chars, t to expand)",
+                self.reasoning.chars().count()
+            ))
         }
     }
+
+    /// 表示用の本文（途中終了したメッセージと分岐したメッセージには印を付ける）
+    ///
+    /// 添付画像は本文の前にファイル名とサイズの行として表示する
+    pub fn display_content(&self) -> String {
+        let mut content: String = self
+            .attachments
+            .iter()
+            .map(|image| image.placeholder() + "\n")
+            .collect();
+        content.push_str(&self.content);
+        if self.is_truncated {
+            content.push_str(" [truncated]");
+        }
+        if let Some((current, count)) = self.branch {
+            content.push_str(&format!(" [{}/{}]", current + 1, count));
+        }
+        content
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -48,6 +152,8 @@ pub enum MessageRole {
     User,
     Assistant,
     System,
+    /// アシスタントによるツール呼び出し
+    Tool,
 }
 
 impl MessageRole {
@@ -56,6 +162,7 @@ impl MessageRole {
             MessageRole::User => "You",
             MessageRole::Assistant => "AI",
             MessageRole::System => "System",
+            MessageRole::Tool => "Tool",
         }
     }
 
@@ -66,19 +173,80 @@ impl MessageRole {
 
 #[derive(Debug, Clone)]
 pub struct AppState {
-    pub messages: Vec<ChatMessage>,
+    /// 会話の木（表示とLLMへの送信には選択中の経路を使う）
+    pub tree: MessageTree,
     pub current_input: String,
     pub cursor_position: usize,
     pub should_quit: bool,
+    pub cancel_requested: bool,
+    /// チャット画面に表示する一時的な状態（再試行中など）
+    pub status_message: Option<String>,
+    pub context_usage: Option<ContextUsage>,
+    /// トークン数と料金の集計
+    pub usage: UsageTracker,
     pub scroll_offset: usize,
     pub input_mode: InputMode,
     pub theme: ChatTheme,
     pub auto_scroll_enabled: bool,
     pub current_model: String,
+    pub backend_kind: BackendKind,
     pub available_models: Vec<String>,
+    pub model_list_source: ModelListSource,
+    /// プロバイダーからモデル一覧を取得できなかった
+    pub model_list_offline: bool,
+    pub favorite_models: Vec<String>,
+    /// モデル一覧の絞り込み（部分一致、大文字小文字を区別しない）
+    pub model_filter: String,
+    /// 絞り込み文字列を入力中か
+    pub model_filter_editing: bool,
+    /// `visible_models()`内の選択位置
     pub model_select_index: usize,
+    favorites_changed: bool,
     pub current_settings: HashMap<String, String>,
     pub settings_scroll_index: usize,
+    /// このセッションの生成パラメータ
+    pub generation_params: GenerationParams,
+    pub generation_index: usize,
+    /// 設定パネルで編集中の値
+    pub generation_input: Option<String>,
+    /// 設定パネルで入力した値の検証エラー
+    pub generation_error: Option<String>,
+    /// 思考過程を展開して表示するか
+    pub show_reasoning: bool,
+    /// 構造化出力の設定（応答の感情から読み上げのスタイルを選ぶ）
+    pub structured_output: StructuredOutputConfig,
+    /// 読み上げに使うVOICEVOXのスタイルID
+    pub speaker_style: u32,
+    /// エンジンから取得したキャラクターの一覧
+    pub speakers: Vec<Speaker>,
+    pub speaker_list_status: SpeakerListStatus,
+    /// `speaker_styles()`内の選択位置
+    pub speaker_select_index: usize,
+    speakers_requested: bool,
+    speaker_preview: Option<u32>,
+    speaker_changed: bool,
+    /// スタイルIDごとの読み上げパラメータ
+    pub voice_params: HashMap<u32, VoiceParams>,
+    pub voice_index: usize,
+    /// 読み上げパラメータのパネルで編集中の値
+    pub voice_input: Option<String>,
+    /// 読み上げパラメータのパネルで入力した値の検証エラー
+    pub voice_error: Option<String>,
+    voice_params_changed: bool,
+    /// 応答を待っているユーザー発言（応答の履歴をこの次のメッセージに記録する）
+    pub reply_parent: Option<NodeId>,
+    /// 入力欄で編集中のユーザー発言
+    pub editing_message: Option<NodeId>,
+    /// 次の発言に添付する画像
+    pub pending_attachments: Vec<ImageAttachment>,
+    /// 分岐の一覧で選択中の経路上の位置
+    pub branch_select_index: usize,
+    /// 回答待ちのツール実行確認
+    pub pending_permission: Option<PermissionRequest>,
+    /// 確認ダイアログを閉じた後に戻る入力モード
+    permission_return_mode: InputMode,
+    /// 設定ファイルに保存すべきツールの許可設定
+    permission_updates: Vec<(String, ToolPermission)>,
 }
 
 impl Default for AppState {
@@ -90,32 +258,100 @@ impl Default for AppState {
 impl AppState {
     pub fn new() -> Self {
         Self {
-            messages: Vec::new(),
+            tree: MessageTree::new(),
             current_input: String::new(),
             cursor_position: 0,
             should_quit: false,
+            cancel_requested: false,
+            status_message: None,
+            context_usage: None,
+            usage: UsageTracker::default(),
             scroll_offset: 0,
             input_mode: InputMode::Normal,
             theme: ChatTheme::from_preset(ThemePreset::Default),
             auto_scroll_enabled: true,
-            current_model: "gpt-5-nano".to_string(),
-            available_models: vec![
-                "gpt-5".to_string(),
-                "gpt-5-mini".to_string(),
-                "gpt-5-nano".to_string(),
-            ],
-            model_select_index: 2, // Default to gpt-5-nano
+            current_model: BackendKind::default().default_model().to_string(),
+            backend_kind: BackendKind::default(),
+            available_models: BackendKind::default().fallback_models(),
+            model_list_source: ModelListSource::Fallback,
+            model_list_offline: false,
+            favorite_models: Vec::new(),
+            model_filter: String::new(),
+            model_filter_editing: false,
+            model_select_index: 0,
+            favorites_changed: false,
             current_settings: HashMap::new(),
             settings_scroll_index: 0,
+            generation_params: GenerationParams::default(),
+            generation_index: 0,
+            generation_input: None,
+            generation_error: None,
+            show_reasoning: false,
+            structured_output: StructuredOutputConfig::default(),
+            speaker_style: DEFAULT_SPEAKER,
+            speakers: Vec::new(),
+            speaker_list_status: SpeakerListStatus::NotLoaded,
+            speaker_select_index: 0,
+            speakers_requested: false,
+            speaker_preview: None,
+            speaker_changed: false,
+            voice_params: HashMap::new(),
+            voice_index: 0,
+            voice_input: None,
+            voice_error: None,
+            voice_params_changed: false,
+            reply_parent: None,
+            editing_message: None,
+            pending_attachments: Vec::new(),
+            branch_select_index: 0,
+            pending_permission: None,
+            permission_return_mode: InputMode::Normal,
+            permission_updates: Vec::new(),
         }
     }
 
     pub fn set_current_model(&mut self, model: String) {
-        self.current_model = model.clone();
-        // Update selected index to match current model
-        if let Some(index) = self.available_models.iter().position(|m| m == &model) {
-            self.model_select_index = index;
+        self.current_model = model;
+        self.select_current_model();
+    }
+
+    /// モデル一覧を置き換える（現在のモデルが含まれていなければ追加する）
+    pub fn set_available_models(&mut self, mut models: Vec<String>, source: ModelListSource) {
+        if !models.contains(&self.current_model) {
+            models.push(self.current_model.clone());
+            models.sort();
         }
+        self.available_models = models;
+        self.model_list_source = source;
+        self.select_current_model();
+    }
+
+    /// 絞り込みを反映した一覧（お気に入りを先頭に並べる）
+    pub fn visible_models(&self) -> Vec<&String> {
+        let filter = self.model_filter.to_lowercase();
+        let (mut favorites, others): (Vec<&String>, Vec<&String>) = self
+            .available_models
+            .iter()
+            .filter(|model| model.to_lowercase().contains(&filter))
+            .partition(|model| self.is_favorite_model(model));
+        favorites.extend(others);
+        favorites
+    }
+
+    pub fn is_favorite_model(&self, model: &str) -> bool {
+        self.favorite_models.iter().any(|m| m == model)
+    }
+
+    fn select_current_model(&mut self) {
+        self.select_model(&self.current_model.clone());
+    }
+
+    fn select_model(&mut self, model: &str) {
+        self.model_select_index = self
+            .visible_models()
+            .iter()
+            .position(|m| m.as_str() == model)
+            .unwrap_or(0);
     }
 
     pub fn move_model_selection_up(&mut self) {
@@ -125,17 +361,504 @@ impl AppState {
     }
 
     pub fn move_model_selection_down(&mut self) {
-        if self.model_select_index < self.available_models.len().saturating_sub(1) {
+        if self.model_select_index < self.visible_models().len().saturating_sub(1) {
             self.model_select_index += 1;
         }
     }
 
     pub fn get_selected_model(&self) -> Option<&String> {
-        self.available_models.get(self.model_select_index)
+        self.visible_models().get(self.model_select_index).copied()
+    }
+
+    pub fn push_model_filter(&mut self, c: char) {
+        self.model_filter.push(c);
+        self.model_select_index = 0;
+    }
+
+    pub fn pop_model_filter(&mut self) {
+        self.model_filter.pop();
+        self.model_select_index = 0;
+    }
+
+    pub fn clear_model_filter(&mut self) {
+        self.model_filter.clear();
+        self.model_filter_editing = false;
+        self.select_current_model();
+    }
+
+    /// 選択中のモデルをお気に入りに追加/解除する
+    pub fn toggle_favorite_model(&mut self) {
+        let Some(model) = self.get_selected_model().cloned() else {
+            return;
+        };
+        if self.is_favorite_model(&model) {
+            self.favorite_models.retain(|m| m != &model);
+        } else {
+            self.favorite_models.push(model.clone());
+        }
+        self.favorites_changed = true;
+        // 並び順が変わっても同じモデルを選択したままにする
+        self.select_model(&model);
+    }
+
+    /// 設定ファイルに保存すべきお気に入りを取り出す
+    pub fn take_favorite_models_update(&mut self) -> Option<Vec<String>> {
+        std::mem::take(&mut self.favorites_changed).then(|| self.favorite_models.clone())
+    }
+
+    /// 話者選択画面を開く（一覧がなければ取得を要求する）
+    pub fn open_speaker_select(&mut self) {
+        if matches!(
+            self.speaker_list_status,
+            SpeakerListStatus::NotLoaded | SpeakerListStatus::Failed(_)
+        ) {
+            self.reload_speakers();
+        }
+        self.select_current_speaker();
+        self.input_mode = InputMode::SpeakerSelect;
+    }
+
+    /// エンジンを起動し直した後などに一覧を取り直す
+    pub fn reload_speakers(&mut self) {
+        if self.speaker_list_status != SpeakerListStatus::Loading {
+            self.speaker_list_status = SpeakerListStatus::Loading;
+            self.speakers_requested = true;
+        }
+    }
+
+    /// 取得したキャラクターの一覧を反映する
+    pub fn set_speakers(&mut self, speakers: Vec<Speaker>) {
+        self.speakers = speakers;
+        self.speaker_list_status = SpeakerListStatus::Loaded;
+        self.select_current_speaker();
+    }
+
+    pub fn set_speakers_failed(&mut self, error: String) {
+        self.speaker_list_status = SpeakerListStatus::Failed(error);
+    }
+
+    /// すべてのキャラクターのスタイルを一列に並べた一覧
+    pub fn speaker_styles(&self) -> Vec<(&Speaker, &SpeakerStyle)> {
+        self.speakers
+            .iter()
+            .flat_map(|speaker| speaker.styles.iter().map(move |style| (speaker, style)))
+            .collect()
+    }
+
+    fn select_current_speaker(&mut self) {
+        self.speaker_select_index = self
+            .speaker_styles()
+            .iter()
+            .position(|(_, style)| style.id == self.speaker_style)
+            .unwrap_or(0);
+    }
+
+    pub fn move_speaker_selection_up(&mut self) {
+        self.speaker_select_index = self.speaker_select_index.saturating_sub(1);
+    }
+
+    pub fn move_speaker_selection_down(&mut self) {
+        if self.speaker_select_index < self.speaker_styles().len().saturating_sub(1) {
+            self.speaker_select_index += 1;
+        }
+    }
+
+    fn selected_speaker_style(&self) -> Option<u32> {
+        self.speaker_styles()
+            .get(self.speaker_select_index)
+            .map(|(_, style)| style.id)
+    }
+
+    /// 選択中のスタイルで見本の文を読み上げる
+    pub fn preview_selected_speaker(&mut self) {
+        if let Some(style_id) = self.selected_speaker_style() {
+            self.speaker_preview = Some(style_id);
+        }
+    }
+
+    /// 選択中のスタイルを読み上げの声にする
+    pub fn select_speaker(&mut self) -> bool {
+        let Some(style_id) = self.selected_speaker_style() else {
+            return false;
+        };
+        self.speaker_style = style_id;
+        self.speaker_changed = true;
+        true
+    }
+
+    /// キャラクターの一覧の取得が要求されたか
+    pub fn take_speakers_request(&mut self) -> bool {
+        std::mem::take(&mut self.speakers_requested)
+    }
+
+    /// 見本を読み上げるスタイル
+    pub fn take_speaker_preview(&mut self) -> Option<u32> {
+        self.speaker_preview.take()
+    }
+
+    /// 設定ファイルに保存すべき読み上げのスタイル
+    pub fn take_speaker_update(&mut self) -> Option<u32> {
+        std::mem::take(&mut self.speaker_changed).then_some(self.speaker_style)
+    }
+
+    /// キャラクターとスタイルの名前（一覧を取得していなければスタイルID）
+    pub fn speaker_label(&self, style_id: u32) -> String {
+        self.speaker_styles()
+            .iter()
+            .find(|(_, style)| style.id == style_id)
+            .map(|(speaker, style)| format!("{}（{}）", speaker.name, style.name))
+            .unwrap_or_else(|| format!("style #{}", style_id))
+    }
+
+    /// 現在の声の読み上げパラメータ
+    pub fn current_voice_params(&self) -> VoiceParams {
+        self.voice_params
+            .get(&self.speaker_style)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn selected_voice_param(&self) -> VoiceParam {
+        VoiceParam::ALL[self.voice_index]
+    }
+
+    pub fn move_voice_selection_up(&mut self) {
+        self.voice_index = self.voice_index.saturating_sub(1);
+        self.voice_error = None;
+    }
+
+    pub fn move_voice_selection_down(&mut self) {
+        if self.voice_index < VoiceParam::ALL.len() - 1 {
+            self.voice_index += 1;
+        }
+        self.voice_error = None;
+    }
+
+    /// 選択中のパラメータの値の入力を始める（現在の値を入力欄に入れる）
+    pub fn start_voice_input(&mut self) {
+        let param = self.selected_voice_param();
+        self.voice_input = Some(self.current_voice_params().value(param).unwrap_or_default());
+        self.voice_error = None;
+    }
+
+    /// 入力した値を検証して設定する（不正な値なら入力を残す）
+    pub fn apply_voice_input(&mut self) {
+        let Some(input) = self.voice_input.clone() else {
+            return;
+        };
+        let param = self.selected_voice_param();
+        let mut params = self.current_voice_params();
+        match params.set(param, &input) {
+            Ok(()) => {
+                self.voice_input = None;
+                self.voice_error = None;
+                self.update_voice_params(params);
+            }
+            Err(e) => self.voice_error = Some(e.to_string()),
+        }
+    }
+
+    /// 選択中のパラメータを`steps`刻みだけ増減する
+    pub fn adjust_voice_param(&mut self, steps: i32) {
+        let mut params = self.current_voice_params();
+        params.adjust(self.selected_voice_param(), steps);
+        self.voice_error = None;
+        self.update_voice_params(params);
+    }
+
+    /// 選択中のパラメータをエンジンの既定値に戻す
+    pub fn reset_voice_param(&mut self) {
+        let mut params = self.current_voice_params();
+        params.unset(self.selected_voice_param());
+        self.voice_error = None;
+        self.update_voice_params(params);
+    }
+
+    /// 変更を反映し、保存と見本の読み上げを要求する
+    fn update_voice_params(&mut self, params: VoiceParams) {
+        if params.is_empty() {
+            self.voice_params.remove(&self.speaker_style);
+        } else {
+            self.voice_params.insert(self.speaker_style, params);
+        }
+        self.voice_params_changed = true;
+        self.speaker_preview = Some(self.speaker_style);
+    }
+
+    /// 現在の声で見本の文を読み上げる
+    pub fn preview_current_voice(&mut self) {
+        self.speaker_preview = Some(self.speaker_style);
+    }
+
+    /// 設定ファイルに保存すべき読み上げパラメータ
+    pub fn take_voice_params_update(&mut self) -> Option<(u32, VoiceParams)> {
+        std::mem::take(&mut self.voice_params_changed)
+            .then(|| (self.speaker_style, self.current_voice_params()))
+    }
+
+    pub fn selected_generation_param(&self) -> GenerationParam {
+        GenerationParam::ALL[self.generation_index]
+    }
+
+    pub fn move_generation_selection_up(&mut self) {
+        self.generation_index = self.generation_index.saturating_sub(1);
+        self.generation_error = None;
+    }
+
+    pub fn move_generation_selection_down(&mut self) {
+        if self.generation_index < GenerationParam::ALL.len() - 1 {
+            self.generation_index += 1;
+        }
+        self.generation_error = None;
+    }
+
+    /// 選択中のパラメータの現在値を初期値として編集を始める
+    pub fn start_generation_input(&mut self) {
+        let param = self.selected_generation_param();
+        self.generation_input = Some(self.generation_params.value(param).unwrap_or_default());
+    }
+
+    /// 現在のモデルが受け付けないため送信されないパラメータの案内
+    pub fn unsupported_generation_note(&self) -> Option<String> {
+        let unsupported = self
+            .generation_params
+            .unsupported(self.backend_kind, &self.current_model);
+        if unsupported.is_empty() {
+            return None;
+        }
+        let names: Vec<&str> = unsupported.iter().map(|param| param.name()).collect();
+        Some(format!(
+            "{} is not supported by {} and will not be sent",
+            names.join(", "),
+            self.current_model
+        ))
+    }
+
+    /// 表示中の経路のメッセージ
+    pub fn messages(&self) -> Vec<&ChatMessage> {
+        self.tree.messages()
+    }
+
+    /// 応答の生成中か（完了して履歴が届くまで分岐を切り替えない）
+    pub fn is_busy(&self) -> bool {
+        self.reply_parent.is_some() || self.is_streaming()
+    }
+
+    fn last_user_node(&self) -> Option<NodeId> {
+        self.tree.rfind_on_path(|msg| msg.role == MessageRole::User)
+    }
+
+    /// ユーザー発言を追加し、応答を待つ発言として記録する
+    ///
+    /// 生成中（`is_busy()`）に呼ぶと応答の途中に発言が挟まるので、呼び出し側で確認する
+    pub fn push_user_message(&mut self, content: Content, images: Vec<ImageAttachment>) -> NodeId {
+        let history = vec![Message::with_images("user", &content, &images)];
+        let message = ChatMessage {
+            attachments: images,
+            ..ChatMessage::new(MessageRole::User, content)
+        };
+        let id = self.tree.push(message, history);
+        self.reply_parent = Some(id);
+        id
+    }
+
+    /// ChatWorkerが履歴に加えた応答を、発言の次のメッセージに記録する
+    pub fn record_reply_history(&mut self, history: Vec<Message>) {
+        let Some(parent) = self.reply_parent.take() else {
+            return;
+        };
+        let path = self.tree.path();
+        let reply = path
+            .iter()
+            .position(|id| *id == parent)
+            .and_then(|index| path.get(index + 1));
+        if let Some(reply) = reply {
+            self.tree.node_mut(*reply).history = history;
+        }
+    }
+
+    /// 最後の発言への応答を別の枝として生成し直す（生成中や発言がなければ`false`）
+    pub fn start_regenerate(&mut self) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        let Some(user_node) = self.last_user_node() else {
+            return false;
+        };
+        self.tree.fork_at(Some(user_node));
+        self.reply_parent = Some(user_node);
+        true
+    }
+
+    /// 最後の発言への応答を次の枝に切り替える（切り替えたら`true`）
+    pub fn cycle_alternative(&mut self) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        let path = self.tree.path();
+        let reply = self
+            .last_user_node()
+            .and_then(|user_node| path.iter().position(|id| *id == user_node))
+            .and_then(|index| path.get(index + 1));
+        let Some(next) = reply.and_then(|reply| self.tree.sibling(*reply, 1)) else {
+            return false;
+        };
+        self.tree.switch_to(next);
+        true
+    }
+
+    /// 最後のユーザー発言を入力欄に読み込んで編集を始める
+    pub fn start_edit_last_message(&mut self) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        let Some(user_node) = self.last_user_node() else {
+            return false;
+        };
+        let message = &self.tree.node(user_node).message;
+        self.current_input = message.content.clone();
+        self.pending_attachments = message.attachments.clone();
+        self.cursor_position = self.current_input.len();
+        self.editing_message = Some(user_node);
+        self.input_mode = InputMode::Insert;
+        true
+    }
+
+    /// 編集を取りやめる
+    pub fn cancel_edit_last_message(&mut self) {
+        if self.editing_message.take().is_some() {
+            self.clear_input();
+            self.pending_attachments.clear();
+        }
+    }
+
+    /// 編集中の発言の直前から分岐し、発言までの履歴を返す
+    pub fn fork_for_edit(&mut self) -> Option<Vec<Message>> {
+        let user_node = self.editing_message.take()?;
+        self.tree.fork_at(self.tree.node(user_node).parent);
+        Some(self.tree.history())
+    }
+
+    /// 分岐の一覧を開く（最後のメッセージを選択する）
+    pub fn open_branch_select(&mut self) {
+        self.branch_select_index = self.tree.path().len().saturating_sub(1);
+        self.input_mode = InputMode::BranchSelect;
+    }
+
+    pub fn move_branch_selection_up(&mut self) {
+        self.branch_select_index = self.branch_select_index.saturating_sub(1);
+    }
+
+    pub fn move_branch_selection_down(&mut self) {
+        if self.branch_select_index + 1 < self.tree.path().len() {
+            self.branch_select_index += 1;
+        }
+    }
+
+    fn selected_branch_node(&self) -> Option<NodeId> {
+        self.tree.path().get(self.branch_select_index).copied()
+    }
+
+    /// 選択中のメッセージを兄弟の枝に切り替える（切り替えたら`true`）
+    pub fn switch_selected_branch(&mut self, offset: isize) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        let Some(sibling) = self
+            .selected_branch_node()
+            .and_then(|id| self.tree.sibling(id, offset))
+        else {
+            return false;
+        };
+        self.tree.switch_to(sibling);
+        true
+    }
+
+    /// 選択中のメッセージまでを表示し、続きをそこから分岐させる（分岐したら`true`）
+    pub fn fork_at_selected(&mut self) -> bool {
+        if self.is_busy() {
+            return false;
+        }
+        let Some(id) = self.selected_branch_node() else {
+            return false;
+        };
+        self.tree.fork_at(Some(id));
+        true
+    }
+
+    /// 画像を読み込み、次の発言に添付する
+    pub fn attach_image(&mut self, path: &str) {
+        let path = path.trim().trim_matches(|c| c == '"' || c == '\'');
+        match ImageAttachment::load(std::path::Path::new(path)) {
+            Ok(image) => {
+                self.add_message(
+                    MessageRole::System,
+                    format!("Attached {} to your next message", image.placeholder()),
+                );
+                self.pending_attachments.push(image);
+            }
+            Err(e) => {
+                self.add_message(MessageRole::System, format!("Error: {}", e.user_message()));
+            }
+        }
+    }
+
+    /// JSONで受け取った応答を直近のアシスタントの応答に反映する
+    ///
+    /// 表示は`text`だけにし、感情と`speaker_style`から読み上げのスタイルを選ぶ
+    pub fn apply_structured_reply(&mut self, reply: StructuredReply) {
+        let voice_style = self.structured_output.style_id(&reply);
+        if let Some(message) = self
+            .tree
+            .head_message_mut()
+            .filter(|message| message.role == MessageRole::Assistant)
+        {
+            message.content = reply.text;
+            message.voice_style = voice_style;
+        }
     }
 
     pub fn update_settings(&mut self, settings: HashMap<String, String>) {
         self.current_settings = settings;
+        self.refresh_usage_settings();
+    }
+
+    /// 1ターン分の利用を集計し、直近のアシスタントの応答に記録する
+    pub fn record_usage(&mut self, model: &str, usage: TokenUsage) {
+        let cost = self.usage.record(model, usage);
+        if let Some(id) = self
+            .tree
+            .rfind_on_path(|msg| msg.role == MessageRole::Assistant)
+        {
+            let message = &mut self.tree.node_mut(id).message;
+            message.usage = Some(usage);
+            message.cost = cost;
+        }
+        self.refresh_usage_settings();
+    }
+
+    /// 設定画面に表示する利用状況を更新する
+    fn refresh_usage_settings(&mut self) {
+        let session_cost = if self.usage.session_cost > 0.0 {
+            format!(" ${:.4}", self.usage.session_cost)
+        } else {
+            String::new()
+        };
+        self.current_settings.insert(
+            "Session Usage".to_string(),
+            format!(
+                "{}{}",
+                usage::format_usage(&self.usage.session),
+                session_cost
+            ),
+        );
+
+        let daily = match self.usage.daily_cap() {
+            Some(cap) => format!("${:.4} / ${:.2}", self.usage.daily_cost(), cap),
+            None => format!("${:.4}", self.usage.daily_cost()),
+        };
+        self.current_settings
+            .insert("Today's Spending".to_string(), daily);
     }
 
     pub fn move_settings_selection_up(&mut self) {
@@ -153,7 +876,7 @@ impl AppState {
     pub fn add_message(&mut self, role: MessageRole, content: Content) -> MessageId {
         let message = ChatMessage::new(role, content);
         let id = message.id.clone();
-        self.messages.push(message);
+        self.tree.push(message, Vec::new());
         // 自動スクロールは呼び出し側で適切なdisplay_widthと共に呼び出す
         id
     }
@@ -165,11 +888,78 @@ impl AppState {
     ) -> MessageId {
         let message = ChatMessage::new_streaming(role, initial_content);
         let id = message.id.clone();
-        self.messages.push(message);
+        self.tree.push(message, Vec::new());
         // 自動スクロールは呼び出し側で適切なdisplay_widthと共に呼び出す
         id
     }
 
+    /// ツール呼び出しを実行中のメッセージとして表示する
+    ///
+    /// 本文のないストリーミング中の応答はツール呼び出しのみなので取り除く
+    pub fn start_tool_message(&mut self, tool_call_id: MessageId, name: &str, arguments: &str) {
+        if let Some(last_message) = self.tree.head_message_mut() {
+            if last_message.is_streaming {
+                last_message.is_streaming = false;
+                // ツールを呼ぶ前の思考過程は残す
+                if last_message.content.is_empty() && last_message.reasoning.is_empty() {
+                    self.tree.remove_head();
+                }
+            }
+        }
+
+        let mut message = ChatMessage::new_streaming(
+            MessageRole::Tool,
+            format!("{}({})", name, arguments.trim()),
+        );
+        message.id = tool_call_id;
+        self.tree.push(message, Vec::new());
+    }
+
+    /// ツール呼び出しのメッセージに結果を追記する
+    pub fn finish_tool_message(&mut self, tool_call_id: &MessageId, result: &str) {
+        if let Some(message) = self.find_message_mut(tool_call_id) {
+            message.is_streaming = false;
+            message.content.push_str(" -> ");
+            message.content.push_str(&summarize_tool_result(result));
+        }
+    }
+
+    /// ツール実行の確認ダイアログを開く
+    pub fn request_tool_permission(&mut self, request: PermissionRequest) {
+        if self.input_mode != InputMode::ToolPermission {
+            self.permission_return_mode = self.input_mode;
+        }
+        // 前の確認が残っていれば拒否として閉じる
+        if let Some(previous) = self.pending_permission.replace(request) {
+            previous.respond(PermissionDecision::DenyOnce);
+        }
+        self.input_mode = InputMode::ToolPermission;
+    }
+
+    /// 確認ダイアログへの回答を送り、元の入力モードに戻る
+    pub fn answer_tool_permission(&mut self, decision: PermissionDecision) {
+        if let Some(request) = self.pending_permission.take() {
+            if let Some(permission) = decision.persistent_permission() {
+                self.permission_updates
+                    .push((request.tool_name.clone(), permission));
+            }
+            request.respond(decision);
+        }
+        self.input_mode = self.permission_return_mode;
+    }
+
+    /// 応答がキャンセルされたときに、開いている確認ダイアログを拒否として閉じる
+    pub fn dismiss_tool_permission(&mut self) {
+        if self.pending_permission.is_some() {
+            self.answer_tool_permission(PermissionDecision::DenyOnce);
+        }
+    }
+
+    /// 設定ファイルに保存すべき許可設定を取り出す
+    pub fn take_permission_updates(&mut self) -> Vec<(String, ToolPermission)> {
+        std::mem::take(&mut self.permission_updates)
+    }
+
     /// 自動スクロール（最下部へ） - 有効時のみ実行
     pub fn auto_scroll_to_bottom(&mut self, display_width: usize) {
         if self.auto_scroll_enabled {
@@ -208,8 +998,43 @@ impl AppState {
         }
     }
 
+    pub fn set_status(&mut self, message: String) {
+        self.status_message = Some(message);
+    }
+
+    pub fn clear_status(&mut self) {
+        self.status_message = None;
+    }
+
+    /// 応答をストリーミング受信中か
+    pub fn is_streaming(&self) -> bool {
+        self.tree.head_message().is_some_and(|msg| msg.is_streaming)
+    }
+
+    /// 生成中の応答と読み上げの中止を要求する
+    pub fn request_cancel(&mut self) {
+        self.cancel_requested = true;
+    }
+
+    /// 中止要求を取り出す（呼び出し後は要求がクリアされる）
+    pub fn take_cancel_request(&mut self) -> bool {
+        std::mem::take(&mut self.cancel_requested)
+    }
+
+    /// ストリーミング中のメッセージを途中終了として確定する
+    pub fn truncate_streaming_message(&mut self) -> bool {
+        if let Some(last_message) = self.tree.head_message_mut() {
+            if last_message.is_streaming {
+                last_message.is_streaming = false;
+                last_message.is_truncated = true;
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn find_message_mut(&mut self, id: &MessageId) -> Option<&mut ChatMessage> {
-        self.messages.iter_mut().find(|msg| msg.id == *id)
+        self.tree.find_message_mut(id)
     }
 
     pub fn scroll_up(&mut self, _display_width: usize) {
@@ -238,8 +1063,11 @@ impl AppState {
 
     pub fn get_visible_messages(&self, visible_height: usize) -> Vec<&ChatMessage> {
         let start = self.scroll_offset;
-        let end = (start + visible_height).min(self.messages.len());
-        self.messages[start..end].iter().collect()
+        self.messages()
+            .into_iter()
+            .skip(start)
+            .take(visible_height)
+            .collect()
     }
 
     pub fn max_prefix_width(&self) -> usize {
@@ -303,9 +1131,15 @@ impl AppState {
         let max_prefix_width = self.max_prefix_width();
         let text_width = display_width.saturating_sub(max_prefix_width + 2);
 
-        self.messages
-            .iter()
-            .map(|msg| self.calculate_wrapped_lines(&msg.content, text_width))
+        self.messages()
+            .into_iter()
+            .map(|msg| {
+                let reasoning_lines = msg
+                    .reasoning_block(self.show_reasoning)
+                    .map(|block| self.calculate_wrapped_lines(&block, text_width))
+                    .unwrap_or(0);
+                reasoning_lines + self.calculate_wrapped_lines(&msg.display_content(), text_width)
+            })
             .sum()
     }
 
@@ -368,3 +1202,16 @@ impl AppState {
         }
     }
 }
+
+/// ツール結果の表示用の要約（長い結果は先頭のみ）
+fn summarize_tool_result(result: &str) -> String {
+    const MAX_CHARS: usize = 200;
+
+    let single_line = result.split_whitespace().collect::<Vec<_>>().join(" ");
+    if single_line.chars().count() > MAX_CHARS {
+        let head: String = single_line.chars().take(MAX_CHARS).collect();
+        format!("{}…", head)
+    } else {
+        single_line
+    }
+}
@@ -7,6 +7,7 @@ pub struct ChatTheme {
     pub user_color: Color,
     pub assistant_color: Color,
     pub system_color: Color,
+    pub tool_color: Color,
     pub highlight_bg: Color,
     pub normal_border: Color,
     pub insert_border: Color,
@@ -26,6 +27,7 @@ impl ChatTheme {
                 user_color: Color::Cyan,
                 assistant_color: Color::Green,
                 system_color: Color::Yellow,
+                tool_color: Color::Magenta,
                 highlight_bg: Color::Rgb(40, 40, 40),
                 normal_border: Color::Blue,
                 insert_border: Color::Green,
@@ -34,6 +36,7 @@ impl ChatTheme {
                 user_color: Color::LightBlue,
                 assistant_color: Color::LightGreen,
                 system_color: Color::LightYellow,
+                tool_color: Color::LightMagenta,
                 highlight_bg: Color::Rgb(60, 60, 60),
                 normal_border: Color::Blue,
                 insert_border: Color::Green,
@@ -42,6 +45,7 @@ impl ChatTheme {
                 user_color: Color::Blue,
                 assistant_color: Color::Rgb(0, 150, 0),
                 system_color: Color::Red,
+                tool_color: Color::Rgb(150, 0, 150),
                 highlight_bg: Color::Rgb(240, 240, 220),
                 normal_border: Color::Rgb(0, 0, 150),
                 insert_border: Color::Rgb(0, 120, 0),
@@ -54,10 +58,16 @@ impl ChatTheme {
             MessageRole::User => self.user_color,
             MessageRole::Assistant => self.assistant_color,
             MessageRole::System => self.system_color,
+            MessageRole::Tool => self.tool_color,
         };
         Style::default().fg(fg_color)
     }
 
+    /// 思考過程は本文より目立たないよう暗く表示する
+    pub fn get_reasoning_style(&self) -> Style {
+        Style::default().fg(Color::DarkGray)
+    }
+
     pub fn get_highlight_style(&self) -> Style {
         Style::default().bg(self.highlight_bg)
     }
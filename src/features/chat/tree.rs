@@ -0,0 +1,330 @@
+use super::state::{ChatMessage, MessageId};
+use crate::history::Message;
+
+/// `MessageTree`内のメッセージの位置
+pub type NodeId = usize;
+
+#[derive(Debug, Clone)]
+pub struct MessageNode {
+    pub message: ChatMessage,
+    /// このメッセージがLLMの履歴に加えるメッセージ（通知などは空）
+    pub history: Vec<Message>,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    /// 最後に選択していた子（枝を切り替えたときに辿る）
+    active_child: Option<NodeId>,
+}
+
+/// 会話を木として保持し、先頭から`head`までの経路を表示・送信する
+///
+/// 途中のメッセージから新しい子を追加することで会話を分岐させる
+#[derive(Debug, Clone, Default)]
+pub struct MessageTree {
+    nodes: Vec<MessageNode>,
+    roots: Vec<NodeId>,
+    /// 表示中の経路の末尾（新しいメッセージはこの子になる）
+    head: Option<NodeId>,
+}
+
+impl MessageTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node(&self, id: NodeId) -> &MessageNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut MessageNode {
+        &mut self.nodes[id]
+    }
+
+    pub fn head(&self) -> Option<NodeId> {
+        self.head
+    }
+
+    pub fn head_message(&self) -> Option<&ChatMessage> {
+        self.head.map(|id| &self.nodes[id].message)
+    }
+
+    pub fn head_message_mut(&mut self) -> Option<&mut ChatMessage> {
+        self.head.map(|id| &mut self.nodes[id].message)
+    }
+
+    /// `head`の子としてメッセージを追加し、新しい`head`にする
+    pub fn push(&mut self, message: ChatMessage, history: Vec<Message>) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(MessageNode {
+            message,
+            history,
+            parent: self.head,
+            children: Vec::new(),
+            active_child: None,
+        });
+        match self.head {
+            Some(parent) => {
+                self.nodes[parent].children.push(id);
+                self.nodes[parent].active_child = Some(id);
+            }
+            None => self.roots.push(id),
+        }
+        self.head = Some(id);
+        self.update_branch_labels(self.nodes[id].parent);
+        id
+    }
+
+    /// `head`のメッセージを木から取り除く（子を持たない場合のみ）
+    pub fn remove_head(&mut self) -> Option<ChatMessage> {
+        let id = self.head?;
+        if !self.nodes[id].children.is_empty() {
+            return None;
+        }
+        let parent = self.nodes[id].parent;
+        let siblings = self.children_mut(parent);
+        siblings.retain(|child| *child != id);
+        let last_sibling = siblings.last().copied();
+        if let Some(parent) = parent {
+            self.nodes[parent].active_child = last_sibling;
+        }
+        self.head = parent;
+        self.update_branch_labels(parent);
+        Some(self.nodes[id].message.clone())
+    }
+
+    /// 先頭から`head`までの経路
+    pub fn path(&self) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut current = self.head;
+        while let Some(id) = current {
+            path.push(id);
+            current = self.nodes[id].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// 表示中の経路のメッセージ
+    pub fn messages(&self) -> Vec<&ChatMessage> {
+        self.path()
+            .into_iter()
+            .map(|id| &self.nodes[id].message)
+            .collect()
+    }
+
+    /// 表示中の経路をLLMに送る履歴にする
+    pub fn history(&self) -> Vec<Message> {
+        self.path()
+            .into_iter()
+            .flat_map(|id| self.nodes[id].history.iter().cloned())
+            .collect()
+    }
+
+    pub fn find_message_mut(&mut self, id: &MessageId) -> Option<&mut ChatMessage> {
+        self.nodes
+            .iter_mut()
+            .map(|node| &mut node.message)
+            .find(|message| message.id == *id)
+    }
+
+    /// 表示中の経路を末尾から辿り、条件に合う最初のメッセージを返す
+    pub fn rfind_on_path(&self, predicate: impl Fn(&ChatMessage) -> bool) -> Option<NodeId> {
+        self.path()
+            .into_iter()
+            .rev()
+            .find(|id| predicate(&self.nodes[*id].message))
+    }
+
+    /// 同じ親を持つメッセージ（自身を含む）
+    pub fn siblings(&self, id: NodeId) -> &[NodeId] {
+        match self.nodes[id].parent {
+            Some(parent) => &self.nodes[parent].children,
+            None => &self.roots,
+        }
+    }
+
+    /// `offset`個隣の兄弟（端では反対側に回り込む）
+    pub fn sibling(&self, id: NodeId, offset: isize) -> Option<NodeId> {
+        let siblings = self.siblings(id);
+        if siblings.len() < 2 {
+            return None;
+        }
+        let index = siblings.iter().position(|sibling| *sibling == id)? as isize;
+        let len = siblings.len() as isize;
+        Some(siblings[(index + offset).rem_euclid(len) as usize])
+    }
+
+    /// `id`までを表示中の経路にする（`None`なら空の経路）
+    ///
+    /// 以降に追加するメッセージは`id`の新しい子として分岐する
+    pub fn fork_at(&mut self, id: Option<NodeId>) {
+        if let Some(id) = id {
+            self.activate_ancestors(id);
+        }
+        self.head = id;
+    }
+
+    /// `id`を含む枝に切り替え、その枝で最後に選択していたメッセージまでを表示する
+    pub fn switch_to(&mut self, id: NodeId) {
+        self.activate_ancestors(id);
+        let mut current = id;
+        while let Some(child) = self.nodes[current].active_child {
+            current = child;
+        }
+        self.head = Some(current);
+    }
+
+    /// 先頭から`id`までの各メッセージで、`id`に向かう子を選択中にする
+    fn activate_ancestors(&mut self, id: NodeId) {
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            self.nodes[parent].active_child = Some(current);
+            current = parent;
+        }
+    }
+
+    fn children_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(parent) => &mut self.nodes[parent].children,
+            None => &mut self.roots,
+        }
+    }
+
+    /// 兄弟がいるメッセージに分岐の番号（例: 2/3）を付ける
+    fn update_branch_labels(&mut self, parent: Option<NodeId>) {
+        let children = match parent {
+            Some(parent) => self.nodes[parent].children.clone(),
+            None => self.roots.clone(),
+        };
+        let count = children.len();
+        for (index, child) in children.into_iter().enumerate() {
+            self.nodes[child].message.branch = (count > 1).then_some((index, count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::chat::state::MessageRole;
+
+    fn push(tree: &mut MessageTree, role: MessageRole, content: &str) -> NodeId {
+        let history = vec![Message::new(role_name(&role), content)];
+        tree.push(ChatMessage::new(role, content.to_string()), history)
+    }
+
+    fn role_name(role: &MessageRole) -> &'static str {
+        match role {
+            MessageRole::User => "user",
+            _ => "assistant",
+        }
+    }
+
+    fn contents(tree: &MessageTree) -> Vec<&str> {
+        tree.messages()
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect()
+    }
+
+    /// user → assistant → user → assistant の一本道
+    fn conversation() -> (MessageTree, Vec<NodeId>) {
+        let mut tree = MessageTree::new();
+        let ids = vec![
+            push(&mut tree, MessageRole::User, "q1"),
+            push(&mut tree, MessageRole::Assistant, "a1"),
+            push(&mut tree, MessageRole::User, "q2"),
+            push(&mut tree, MessageRole::Assistant, "a2"),
+        ];
+        (tree, ids)
+    }
+
+    #[test]
+    fn path_follows_pushed_messages() {
+        let (tree, ids) = conversation();
+
+        assert_eq!(tree.path(), ids);
+        assert_eq!(tree.head(), Some(ids[3]));
+        assert_eq!(contents(&tree), vec!["q1", "a1", "q2", "a2"]);
+        let history: Vec<String> = tree
+            .history()
+            .iter()
+            .map(|message| message.content.text())
+            .collect();
+        assert_eq!(history, vec!["q1", "a1", "q2", "a2"]);
+        assert!(tree
+            .messages()
+            .iter()
+            .all(|message| message.branch.is_none()));
+    }
+
+    #[test]
+    fn forking_adds_a_sibling_branch() {
+        let (mut tree, ids) = conversation();
+
+        // 2つ目の発言への応答をやり直す
+        tree.fork_at(Some(ids[2]));
+        let retry = push(&mut tree, MessageRole::Assistant, "a2'");
+
+        assert_eq!(contents(&tree), vec!["q1", "a1", "q2", "a2'"]);
+        assert_eq!(tree.siblings(retry), &[ids[3], retry]);
+        assert_eq!(tree.node(ids[3]).message.branch, Some((0, 2)));
+        assert_eq!(tree.node(retry).message.branch, Some((1, 2)));
+
+        // 最初の発言を編集して送り直す（根の兄弟になる）
+        tree.fork_at(None);
+        let edited = push(&mut tree, MessageRole::User, "q1'");
+        assert_eq!(tree.path(), vec![edited]);
+        assert_eq!(tree.siblings(edited), &[ids[0], edited]);
+    }
+
+    #[test]
+    fn switching_siblings_restores_the_last_visited_branch() {
+        let (mut tree, ids) = conversation();
+        tree.fork_at(Some(ids[0]));
+        let other_reply = push(&mut tree, MessageRole::Assistant, "b1");
+        push(&mut tree, MessageRole::User, "r2");
+
+        // 隣の兄弟へは端で回り込む
+        assert_eq!(tree.sibling(other_reply, 1), Some(ids[1]));
+        assert_eq!(tree.sibling(other_reply, -1), Some(ids[1]));
+        assert_eq!(tree.sibling(ids[2], 1), None);
+
+        // 元の枝に戻ると、その枝の末尾まで表示する
+        tree.switch_to(ids[1]);
+        assert_eq!(contents(&tree), vec!["q1", "a1", "q2", "a2"]);
+
+        tree.switch_to(other_reply);
+        assert_eq!(contents(&tree), vec!["q1", "b1", "r2"]);
+    }
+
+    #[test]
+    fn fork_in_the_middle_keeps_later_messages_on_their_branch() {
+        let (mut tree, ids) = conversation();
+
+        tree.fork_at(Some(ids[1]));
+        assert_eq!(tree.path(), ids[..2].to_vec());
+        let new_question = push(&mut tree, MessageRole::User, "q2'");
+        assert_eq!(contents(&tree), vec!["q1", "a1", "q2'"]);
+
+        tree.switch_to(ids[2]);
+        assert_eq!(tree.head(), Some(ids[3]));
+        tree.switch_to(new_question);
+        assert_eq!(tree.head(), Some(new_question));
+    }
+
+    #[test]
+    fn removing_head_selects_the_remaining_sibling() {
+        let (mut tree, ids) = conversation();
+        tree.fork_at(Some(ids[2]));
+        push(&mut tree, MessageRole::Assistant, "a2'");
+
+        let removed = tree.remove_head().unwrap();
+        assert_eq!(removed.content, "a2'");
+        assert_eq!(tree.head(), Some(ids[2]));
+        assert_eq!(tree.node(ids[3]).message.branch, None);
+
+        // 子を持つメッセージは取り除かない
+        tree.fork_at(Some(ids[0]));
+        assert!(tree.remove_head().is_none());
+    }
+}
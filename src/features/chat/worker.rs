@@ -1,44 +1,215 @@
 use super::events::ChatEvent;
-use crate::openai::ChatCompletion;
-use reqwest::Client;
+use crate::attachment::ImageAttachment;
+use crate::backend::{create_backend, BackendConfig, ChatBackend, StreamEvent};
+use crate::cassette::HttpClient;
+use crate::error::Error;
+use crate::generation::GenerationParams;
+use crate::history::{Message, ToolCall};
+use crate::structured::{StructuredReply, TextFieldExtractor};
+use crate::tools::{PermissionRequest, ToolPermission, ToolRegistry};
+use crate::usage::TokenUsage;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// 1回の発言に対してツール呼び出しを繰り返す上限
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// UIからChatWorkerへの指示
+#[derive(Debug, Clone)]
+pub enum WorkerCommand {
+    /// ユーザーの発言に応答する
+    UserInput {
+        text: String,
+        images: Vec<ImageAttachment>,
+    },
+    /// 以降の応答に使うモデルを切り替える
+    SetModel(String),
+    /// システムプロンプトを置き換える
+    SetSystemPrompt(String),
+    /// 以降の応答に使う生成パラメータを置き換える
+    SetGenerationParams(GenerationParams),
+    /// 現在の履歴に対する応答を生成する（応答の再生成に使う）
+    Respond,
+    /// 会話履歴を置き換える（UIで会話の分岐を切り替えたとき）
+    LoadHistory(Vec<Message>),
+    /// 会話履歴を破棄する
+    ResetHistory,
+    /// 生成中の応答を中止する
+    Cancel,
+}
+
 pub struct ChatWorkerConfig {
-    pub api_key: String,
+    pub backend: BackendConfig,
     pub model: String,
     pub system_prompt: String,
+    pub tools: ToolRegistry,
+    /// ツールごとの許可設定（未指定は実行前に確認する）
+    pub tool_permissions: HashMap<String, ToolPermission>,
 }
 
 pub struct ChatWorker {
-    chat_completion: ChatCompletion,
-    user_input_rx: mpsc::Receiver<String>,
+    backend: Box<dyn ChatBackend>,
+    tools: ToolRegistry,
+    tool_permissions: HashMap<String, ToolPermission>,
+    command_rx: mpsc::UnboundedReceiver<WorkerCommand>,
+    /// 生成中に届き、後で処理する指示
+    deferred_commands: VecDeque<WorkerCommand>,
     chat_event_tx: mpsc::Sender<ChatEvent>,
+    /// 応答をJSONで受け取り、`text`だけを表示する
+    structured_output: bool,
 }
 
 impl ChatWorker {
     pub fn new(
         config: ChatWorkerConfig,
-        client: Arc<Client>,
-        user_input_rx: mpsc::Receiver<String>,
+        client: Arc<HttpClient>,
+        command_rx: mpsc::UnboundedReceiver<WorkerCommand>,
         chat_event_tx: mpsc::Sender<ChatEvent>,
     ) -> Self {
-        let mut chat_completion = ChatCompletion::new(config.api_key.clone(), client);
-        chat_completion.push_system_message(&config.system_prompt);
+        let mut backend = create_backend(&config.backend, client);
+        let structured_output = config.backend.response_schema.is_some();
+        backend.set_model(&config.model);
+        backend.push_system_message(&config.system_prompt);
+        backend.set_tools(config.tools.definitions());
+        // 要約などストリーミング以外のリクエストの再試行もステータス行に表示する
+        let retry_tx = chat_event_tx.clone();
+        backend.set_retry_notifier(Arc::new(move |status| {
+            let _ = retry_tx.try_send(ChatEvent::Retrying {
+                attempt: status.attempt,
+                max_retries: status.max_retries,
+            });
+        }));
 
         Self {
-            chat_completion,
-            user_input_rx,
+            backend,
+            tools: config.tools,
+            tool_permissions: config.tool_permissions,
+            command_rx,
+            deferred_commands: VecDeque::new(),
             chat_event_tx,
+            structured_output,
+        }
+    }
+
+    /// 現在のコンテキスト使用量をUIに通知する
+    async fn send_context_usage(&self) {
+        let usage = self.backend.context_usage();
+        if let Err(e) = self
+            .chat_event_tx
+            .send(ChatEvent::ContextUsage(usage))
+            .await
+        {
+            log::error!("Failed to send ContextUsage event: {}", e);
         }
     }
 
     pub async fn run(mut self) {
-        while let Some(user_input) = self.user_input_rx.recv().await {
-            // ユーザー入力をChatCompletionに追加
-            self.chat_completion.push_user_message(&user_input);
+        self.send_context_usage().await;
+
+        while let Some(command) = self.next_command().await {
+            let sent = match command {
+                WorkerCommand::UserInput { text, images } => {
+                    self.handle_user_input(&text, &images).await
+                }
+                WorkerCommand::Respond => {
+                    // 読み込み直した履歴から外れたターンも、応答の前に要約へ取り込む
+                    self.summarize_dropped_turns().await;
+                    self.respond_and_report_usage().await
+                }
+                WorkerCommand::LoadHistory(messages) => {
+                    self.backend.load_history(messages);
+                    true
+                }
+                WorkerCommand::SetModel(model) => {
+                    self.backend.set_model(&model);
+                    self.send_event(ChatEvent::ModelChanged(model)).await
+                }
+                WorkerCommand::SetSystemPrompt(prompt) => {
+                    self.backend.set_system_prompt(&prompt);
+                    self.send_event(ChatEvent::SystemPromptChanged).await
+                }
+                WorkerCommand::SetGenerationParams(params) => {
+                    self.backend.set_generation_params(params);
+                    continue;
+                }
+                WorkerCommand::ResetHistory => {
+                    self.backend.reset_history();
+                    self.send_event(ChatEvent::HistoryReset).await
+                }
+                // 前の応答に対して遅れて届いたキャンセル要求は捨てる
+                WorkerCommand::Cancel => continue,
+            };
+            if !sent {
+                break;
+            }
+            self.send_context_usage().await;
+        }
+    }
 
+    /// 生成中に届いた指示を優先して、次の指示を取り出す
+    async fn next_command(&mut self) -> Option<WorkerCommand> {
+        match self.deferred_commands.pop_front() {
+            Some(command) => Some(command),
+            None => self.command_rx.recv().await,
+        }
+    }
+
+    async fn send_event(&self, event: ChatEvent) -> bool {
+        if let Err(e) = self.chat_event_tx.send(event).await {
+            log::error!("Failed to send ChatEvent: {}", e);
+            return false;
+        }
+        true
+    }
+
+    /// ユーザーの発言を履歴に追加して応答する。UIへの送信に失敗した場合は`false`を返す
+    async fn handle_user_input(&mut self, user_input: &str, images: &[ImageAttachment]) -> bool {
+        // ユーザー入力をバックエンドの履歴に追加
+        self.backend.push_user_message(user_input, images);
+
+        self.summarize_dropped_turns().await;
+        self.respond_and_report_usage().await
+    }
+
+    /// 履歴から外れたターンがあれば、応答の前に要約へ取り込む
+    async fn summarize_dropped_turns(&mut self) {
+        if !self.backend.needs_summary() {
+            return;
+        }
+        let _ = self.chat_event_tx.send(ChatEvent::Summarizing).await;
+        if let Err(e) = self.backend.summarize_history().await {
+            // 要約できなかったターンは次の応答時に再度要約する
+            log::warn!("Failed to summarize conversation: {}", e);
+            let _ = self.chat_event_tx.send(ChatEvent::Error(e)).await;
+        }
+    }
+
+    /// 応答を生成し、使用したトークン数と履歴に加えた応答をUIに通知する
+    async fn respond_and_report_usage(&mut self) -> bool {
+        let mut usage = TokenUsage::default();
+        let sent = self.respond(&mut usage).await;
+        if !usage.is_empty() {
+            let _ = self
+                .chat_event_tx
+                .send(ChatEvent::Usage {
+                    model: self.backend.current_model().to_string(),
+                    usage,
+                })
+                .await;
+        }
+        // UIが会話の枝を切り替えたときに復元できるよう、応答の履歴を渡す
+        sent && self
+            .send_event(ChatEvent::ReplyRecorded(self.backend.last_reply()))
+            .await
+    }
+
+    /// 最終的なテキスト応答が得られるまで、ツール呼び出しと応答の生成を繰り返す
+    ///
+    /// 各リクエストのトークン数を`usage`に合算する。UIへの送信に失敗した場合は`false`を返す
+    async fn respond(&mut self, usage: &mut TokenUsage) -> bool {
+        for _ in 0..MAX_TOOL_ROUNDS {
             // ストリーミングレスポンス開始を通知
             let message_id = Uuid::new_v4().to_string();
             if let Err(e) = self
@@ -46,68 +217,293 @@ impl ChatWorker {
                 .send(ChatEvent::StreamingStart(message_id.clone()))
                 .await
             {
-                eprintln!("Failed to send StreamingStart event: {}", e);
-                break;
+                log::error!("Failed to send StreamingStart event: {}", e);
+                return false;
             }
 
-            // OpenAI APIからストリーミングレスポンスを取得
+            // バックエンドからストリーミングレスポンスを取得
             let event_tx = self.chat_event_tx.clone();
             let msg_id = message_id.clone();
+            let mut partial_response = String::new();
+            // 構造化出力ではJSONの`text`フィールドだけを逐次表示する
+            let mut extractor = self.structured_output.then(TextFieldExtractor::new);
 
-            let result = self
-                .chat_completion
-                .completion_stream(move |chunk| {
-                    let tx = event_tx.clone();
-                    let id = msg_id.clone();
-                    let chunk_str = chunk.to_string();
-
+            let result = {
+                let mut on_event = |event: StreamEvent<'_>| {
                     // ブロッキング送信を使用してSendエラーを回避
-                    let _ = tx.try_send(ChatEvent::StreamingChunk(id, chunk_str));
-                })
-                .await;
-
-            match result {
-                Ok(full_response) => {
-                    // レスポンス完了を通知
-                    if let Err(e) = self
-                        .chat_event_tx
-                        .send(ChatEvent::StreamingComplete(message_id))
-                        .await
-                    {
-                        eprintln!("Failed to send StreamingComplete event: {}", e);
-                        break;
+                    match event {
+                        StreamEvent::Content(chunk) => {
+                            partial_response.push_str(chunk);
+                            let text = match extractor.as_mut() {
+                                Some(extractor) => extractor.push(chunk),
+                                None => chunk.to_string(),
+                            };
+                            if !text.is_empty() {
+                                let _ = event_tx
+                                    .try_send(ChatEvent::StreamingChunk(msg_id.clone(), text));
+                            }
+                        }
+                        StreamEvent::Reasoning(chunk) => {
+                            let _ = event_tx.try_send(ChatEvent::ReasoningChunk(
+                                msg_id.clone(),
+                                chunk.to_string(),
+                            ));
+                        }
+                        StreamEvent::Retrying(status) => {
+                            let _ = event_tx.try_send(ChatEvent::Retrying {
+                                attempt: status.attempt,
+                                max_retries: status.max_retries,
+                            });
+                        }
                     }
+                };
 
-                    // ChatCompletionの履歴にレスポンスを追加
-                    self.chat_completion.push_assistant_message(&full_response);
+                tokio::select! {
+                    result = self.backend.completion_stream(&mut on_event) => Some(result),
+                    _ = wait_for_cancel(&mut self.command_rx, &mut self.deferred_commands) => None,
+                }
+            };
+
+            let Some(result) = result else {
+                // キャンセルされた場合は途中までの応答を途中終了として履歴に残す
+                self.backend
+                    .push_truncated_assistant_message(&partial_response);
+                return self.send_cancelled(message_id).await;
+            };
+
+            let completion = match result {
+                Ok(completion) => {
+                    if let Some(completion_usage) = completion.usage {
+                        *usage += completion_usage;
+                    }
+                    completion
                 }
                 Err(e) => {
                     // エラーを通知
-                    let error_msg = e.to_string();
-                    if let Err(send_err) =
-                        self.chat_event_tx.send(ChatEvent::Error(error_msg)).await
-                    {
-                        eprintln!("Failed to send Error event: {}", send_err);
-                        break;
+                    if let Err(send_err) = self.chat_event_tx.send(ChatEvent::Error(e)).await {
+                        log::error!("Failed to send Error event: {}", send_err);
+                        return false;
+                    }
+                    return true;
+                }
+            };
+
+            if completion.tool_calls.is_empty() {
+                if self.structured_output {
+                    match StructuredReply::parse(&completion.content) {
+                        Some(reply) => {
+                            let _ = self
+                                .chat_event_tx
+                                .send(ChatEvent::StructuredReply(reply))
+                                .await;
+                        }
+                        None => log::warn!("Reply did not match the response schema"),
+                    }
+                }
+
+                // レスポンス完了を通知
+                if let Err(e) = self
+                    .chat_event_tx
+                    .send(ChatEvent::StreamingComplete(message_id))
+                    .await
+                {
+                    log::error!("Failed to send StreamingComplete event: {}", e);
+                    return false;
+                }
+
+                // バックエンドの履歴にレスポンスを追加
+                self.backend.push_assistant_message(&completion.content);
+                return true;
+            }
+
+            // ツールを順に実行し、結果を履歴に追加してから続きを生成させる
+            let tool_calls = completion.tool_calls.clone();
+            self.backend
+                .push_tool_calls(&completion.content, completion.tool_calls);
+
+            for (index, call) in tool_calls.iter().enumerate() {
+                let _ = self
+                    .chat_event_tx
+                    .send(ChatEvent::ToolCall {
+                        id: call.id.clone(),
+                        name: call.function.name.clone(),
+                        arguments: call.function.arguments.clone(),
+                    })
+                    .await;
+
+                let allowed = match self.check_permission(call).await {
+                    Some(PermissionCheck::Allowed) => true,
+                    Some(PermissionCheck::Denied) => false,
+                    // 確認中のキャンセルは拒否として扱い、残りの呼び出しも実行しない
+                    Some(PermissionCheck::Cancelled) => {
+                        for call in &tool_calls[index..] {
+                            self.backend
+                                .push_tool_result(&call.id, "Cancelled by the user.");
+                        }
+                        return self.send_cancelled(message_id).await;
                     }
+                    None => return false,
+                };
+                if !allowed {
+                    let result = "The user denied permission to run this tool.".to_string();
+                    self.backend.push_tool_result(&call.id, &result);
+                    let _ = self
+                        .chat_event_tx
+                        .send(ChatEvent::ToolResult {
+                            id: call.id.clone(),
+                            result,
+                        })
+                        .await;
+                    continue;
                 }
+
+                let result = tokio::select! {
+                    result = self.tools.call(call) => result,
+                    _ = wait_for_cancel(&mut self.command_rx, &mut self.deferred_commands) => {
+                        // 未実行の呼び出しにも結果を返さないと次のリクエストが不正になる
+                        for call in &tool_calls[index..] {
+                            self.backend.push_tool_result(&call.id, "Cancelled by the user.");
+                        }
+                        return self.send_cancelled(message_id).await;
+                    }
+                };
+
+                self.backend.push_tool_result(&call.id, &result);
+                let _ = self
+                    .chat_event_tx
+                    .send(ChatEvent::ToolResult {
+                        id: call.id.clone(),
+                        result,
+                    })
+                    .await;
             }
+            self.send_context_usage().await;
+        }
+
+        let error = Error::Tool(format!(
+            "gave up after {} rounds of tool calls",
+            MAX_TOOL_ROUNDS
+        ));
+        if let Err(e) = self.chat_event_tx.send(ChatEvent::Error(error)).await {
+            log::error!("Failed to send Error event: {}", e);
+            return false;
+        }
+        true
+    }
+
+    /// ツールを実行してよいか確認する
+    ///
+    /// 設定で決まっていなければUIに確認を求めて回答を待つ。UIへ送れなかった場合は`None`
+    async fn check_permission(&mut self, call: &ToolCall) -> Option<PermissionCheck> {
+        let name = &call.function.name;
+        match self.tool_permissions.get(name).copied().unwrap_or_default() {
+            ToolPermission::Allow => return Some(PermissionCheck::Allowed),
+            ToolPermission::Deny => return Some(PermissionCheck::Denied),
+            ToolPermission::Ask => {}
+        }
+
+        let (request, response_rx) = PermissionRequest::new(name, &call.function.arguments);
+        if let Err(e) = self
+            .chat_event_tx
+            .send(ChatEvent::ToolPermission(request))
+            .await
+        {
+            log::error!("Failed to send ToolPermission event: {}", e);
+            return None;
+        }
+
+        let response = tokio::select! {
+            response = response_rx => response,
+            _ = wait_for_cancel(&mut self.command_rx, &mut self.deferred_commands) => {
+                return Some(PermissionCheck::Cancelled);
+            }
+        };
+        // 回答がないままダイアログが破棄された場合は拒否とみなす
+        let Ok(decision) = response else {
+            return Some(PermissionCheck::Denied);
+        };
+        if let Some(permission) = decision.persistent_permission() {
+            self.tool_permissions.insert(name.clone(), permission);
+        }
+        Some(if decision.is_allowed() {
+            PermissionCheck::Allowed
+        } else {
+            PermissionCheck::Denied
+        })
+    }
+
+    async fn send_cancelled(&self, message_id: String) -> bool {
+        if let Err(e) = self
+            .chat_event_tx
+            .send(ChatEvent::StreamingCancelled(message_id))
+            .await
+        {
+            log::error!("Failed to send StreamingCancelled event: {}", e);
+            return false;
+        }
+        true
+    }
+}
+
+/// ツールの実行を確認した結果
+enum PermissionCheck {
+    Allowed,
+    Denied,
+    /// 回答を待っている間に応答がキャンセルされた（拒否として扱う）
+    Cancelled,
+}
+
+/// 生成中にキャンセルの指示が届くまで待つ
+///
+/// それ以外の指示は生成が終わってから処理するよう`deferred`に積む。
+/// UIが終了してチャネルが閉じた場合もキャンセルとみなす
+async fn wait_for_cancel(
+    command_rx: &mut mpsc::UnboundedReceiver<WorkerCommand>,
+    deferred: &mut VecDeque<WorkerCommand>,
+) {
+    while let Some(command) = command_rx.recv().await {
+        match command {
+            WorkerCommand::Cancel => return,
+            command => deferred.push_back(command),
         }
     }
 }
 
 pub fn create_chat_worker(
     config: ChatWorkerConfig,
-    client: Arc<Client>,
-) -> (mpsc::Sender<String>, mpsc::Receiver<ChatEvent>) {
-    let (user_input_tx, user_input_rx) = mpsc::channel::<String>(32);
+    client: Arc<HttpClient>,
+) -> (
+    mpsc::UnboundedSender<WorkerCommand>,
+    mpsc::Receiver<ChatEvent>,
+) {
+    let (command_tx, command_rx) = mpsc::unbounded_channel::<WorkerCommand>();
     let (chat_event_tx, chat_event_rx) = mpsc::channel::<ChatEvent>(32);
 
-    let worker = ChatWorker::new(config, client, user_input_rx, chat_event_tx);
+    // モデル一覧の取得は時間がかかることがあるため、会話とは別に行う
+    tokio::spawn(discover_models(
+        create_backend(&config.backend, client.clone()),
+        chat_event_tx.clone(),
+    ));
+
+    let worker = ChatWorker::new(config, client, command_rx, chat_event_tx);
 
     tokio::spawn(async move {
         worker.run().await;
     });
 
-    (user_input_tx, chat_event_rx)
+    (command_tx, chat_event_rx)
+}
+
+/// プロバイダーからモデル一覧を取得してUIに通知する
+async fn discover_models(backend: Box<dyn ChatBackend>, chat_event_tx: mpsc::Sender<ChatEvent>) {
+    let event = match backend.list_models().await {
+        Ok(models) if !models.is_empty() => ChatEvent::ModelsLoaded(models),
+        Ok(_) => ChatEvent::ModelsUnavailable(Error::InvalidResponse(
+            "the provider returned no models".to_string(),
+        )),
+        Err(e) => ChatEvent::ModelsUnavailable(e),
+    };
+    if let Err(e) = chat_event_tx.send(event).await {
+        log::error!("Failed to send model list: {}", e);
+    }
 }
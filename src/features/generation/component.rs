@@ -0,0 +1,86 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+use super::props::GenerationPanelProps;
+use crate::generation::GenerationParam;
+
+pub fn render_generation_panel(frame: &mut Frame, props: &GenerationPanelProps) {
+    let area = frame.area();
+
+    let panel_area = ratatui::layout::Rect {
+        x: area.width / 8,
+        y: area.height / 4,
+        width: area.width * 3 / 4,
+        height: area.height / 2,
+    };
+
+    frame.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Generation Parameters [{}]", props.current_model)),
+        panel_area,
+    );
+
+    let inner_area = ratatui::layout::Rect {
+        x: panel_area.x + 1,
+        y: panel_area.y + 1,
+        width: panel_area.width.saturating_sub(2),
+        height: panel_area.height.saturating_sub(2),
+    };
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    let mut items = Vec::new();
+    for (i, param) in GenerationParam::ALL.iter().enumerate() {
+        let value = match (i == props.selected_index, props.editing) {
+            (true, Some(input)) => format!("{}█", input),
+            _ => props
+                .params
+                .value(*param)
+                .unwrap_or_else(|| "default".to_string()),
+        };
+        let mut line = format!("{:.<26} {}", param.name(), value);
+
+        // モデルが受け付けないパラメータは送信しない
+        let supported = param.is_supported(props.backend, props.current_model);
+        if !supported {
+            line.push_str("  (not sent to this model)");
+        }
+
+        let mut style = if supported {
+            Style::default()
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        if i == props.selected_index {
+            style = style.bg(Color::DarkGray);
+        }
+
+        items.push(ListItem::new(line).style(style));
+    }
+
+    frame.render_widget(
+        List::new(items).block(Block::default().borders(Borders::NONE)),
+        layout[0],
+    );
+
+    // 入力例またはエラーを最下行に表示する
+    let (footer, style) = match props.error {
+        Some(error) => (error.to_string(), Style::default().fg(Color::Red)),
+        None => {
+            let hint = GenerationParam::ALL
+                .get(props.selected_index)
+                .map(|param| param.hint())
+                .unwrap_or_default();
+            (hint.to_string(), Style::default().fg(Color::DarkGray))
+        }
+    };
+    frame.render_widget(Paragraph::new(footer).style(style), layout[1]);
+}
@@ -0,0 +1,76 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use tokio::sync::mpsc;
+
+use crate::features::chat::events::send_command;
+use crate::features::chat::state::{AppState, InputMode};
+use crate::features::chat::worker::WorkerCommand;
+
+pub fn handle_generation_mode(
+    key: KeyEvent,
+    state: &mut AppState,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) -> bool {
+    if state.generation_input.is_some() {
+        handle_value_input(key, state, command_tx);
+        return false;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            state.generation_error = None;
+            state.input_mode = InputMode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => state.move_generation_selection_up(),
+        KeyCode::Down | KeyCode::Char('j') => state.move_generation_selection_down(),
+        KeyCode::Enter => state.start_generation_input(),
+        KeyCode::Char('d') | KeyCode::Delete => {
+            let param = state.selected_generation_param();
+            state.generation_params.unset(param);
+            state.generation_error = None;
+            send_command(
+                command_tx,
+                WorkerCommand::SetGenerationParams(state.generation_params.clone()),
+            );
+        }
+        _ => {}
+    }
+    false
+}
+
+/// 選択中のパラメータの値の入力
+fn handle_value_input(
+    key: KeyEvent,
+    state: &mut AppState,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) {
+    let Some(input) = state.generation_input.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            state.generation_input = None;
+            state.generation_error = None;
+        }
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Enter => {
+            let value = input.clone();
+            let param = state.selected_generation_param();
+            match state.generation_params.set(param, &value) {
+                Ok(()) => {
+                    state.generation_input = None;
+                    state.generation_error = None;
+                    send_command(
+                        command_tx,
+                        WorkerCommand::SetGenerationParams(state.generation_params.clone()),
+                    );
+                }
+                // 入力を残したまま修正できるようにする
+                Err(e) => state.generation_error = Some(e.to_string()),
+            }
+        }
+        _ => {}
+    }
+}
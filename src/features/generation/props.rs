@@ -0,0 +1,15 @@
+use crate::backend::BackendKind;
+use crate::features::chat::theme::ChatTheme;
+use crate::generation::GenerationParams;
+
+#[derive(Debug)]
+pub struct GenerationPanelProps<'a> {
+    pub params: &'a GenerationParams,
+    pub backend: BackendKind,
+    pub current_model: &'a str,
+    pub selected_index: usize,
+    /// 編集中の値（編集していなければ`None`）
+    pub editing: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub theme: &'a ChatTheme,
+}
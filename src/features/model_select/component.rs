@@ -1,10 +1,12 @@
 use ratatui::{
+    layout::{Constraint, Direction, Layout},
     style::Style,
-    widgets::{Block, Borders, List, ListItem},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     Frame,
 };
 
 use super::props::ModelSelectProps;
+use crate::features::chat::state::ModelListSource;
 
 pub fn render_model_select_screen(frame: &mut Frame, props: &ModelSelectProps) {
     let area = frame.area();
@@ -19,36 +21,77 @@ pub fn render_model_select_screen(frame: &mut Frame, props: &ModelSelectProps) {
 
     // Clear the background
     frame.render_widget(
-        Block::default().borders(Borders::ALL).title("Select Model"),
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Select Model [{}]", source_label(props))),
         popup_area,
     );
 
     let inner_area = ratatui::layout::Rect {
         x: popup_area.x + 1,
         y: popup_area.y + 1,
-        width: popup_area.width - 2,
-        height: popup_area.height - 2,
+        width: popup_area.width.saturating_sub(2),
+        height: popup_area.height.saturating_sub(2),
     };
 
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner_area);
+
+    // 絞り込みの入力欄
+    let filter_text = if props.filter_editing {
+        format!("/{}█", props.filter)
+    } else if props.filter.is_empty() {
+        "/ to filter".to_string()
+    } else {
+        format!("/{}", props.filter)
+    };
+    frame.render_widget(
+        Paragraph::new(filter_text).style(Style::default().fg(ratatui::style::Color::DarkGray)),
+        layout[0],
+    );
+
     let mut items = Vec::new();
-    for (i, model) in props.available_models.iter().enumerate() {
-        let mut line = model.clone();
-        if model == props.current_model {
-            line = format!("● {}", line);
+    for model in props.visible_models {
+        let marker = if model.as_str() == props.current_model {
+            "●"
         } else {
-            line = format!("  {}", line);
-        }
-
-        let style = if i == props.selected_index {
-            Style::default().bg(ratatui::style::Color::DarkGray)
+            " "
+        };
+        let favorite = if props.favorite_models.contains(*model) {
+            "★"
         } else {
-            Style::default()
+            " "
         };
+        items.push(ListItem::new(format!("{}{} {}", marker, favorite, model)));
+    }
+    if items.is_empty() {
+        items.push(ListItem::new("  (no matching models)"));
+    }
+
+    let model_list = List::new(items)
+        .block(Block::default().borders(Borders::NONE))
+        .highlight_style(Style::default().bg(ratatui::style::Color::DarkGray));
 
-        items.push(ListItem::new(line).style(style));
+    // 一覧が長い場合も選択中のモデルが見えるようにスクロールする
+    let mut list_state = ListState::default();
+    if !props.visible_models.is_empty() {
+        list_state.select(Some(props.selected_index));
     }
 
-    let model_list = List::new(items).block(Block::default().borders(Borders::NONE));
+    frame.render_stateful_widget(model_list, layout[1], &mut list_state);
+}
 
-    frame.render_widget(model_list, inner_area);
+fn source_label(props: &ModelSelectProps) -> String {
+    let label = match props.source {
+        ModelListSource::Fallback => "default list".to_string(),
+        ModelListSource::Cache(fetched_at) => format!("cached {}", fetched_at),
+        ModelListSource::Provider => "provider".to_string(),
+    };
+    if props.offline {
+        format!("{}, offline", label)
+    } else {
+        label
+    }
 }
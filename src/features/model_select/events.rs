@@ -1,10 +1,22 @@
 use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use tokio::sync::mpsc;
 
-use crate::features::chat::state::{AppState, InputMode, MessageRole};
+use crate::features::chat::events::send_command;
+use crate::features::chat::state::{AppState, InputMode};
+use crate::features::chat::worker::WorkerCommand;
+
+pub fn handle_model_select_mode(
+    key: KeyEvent,
+    state: &mut AppState,
+    command_tx: Option<&mpsc::UnboundedSender<WorkerCommand>>,
+) -> bool {
+    if state.model_filter_editing {
+        return handle_filter_input(key, state);
+    }
 
-pub fn handle_model_select_mode(key: KeyEvent, state: &mut AppState) -> bool {
     match key.code {
         KeyCode::Esc => {
+            state.clear_model_filter();
             state.input_mode = InputMode::Normal;
             false
         }
@@ -16,18 +28,35 @@ pub fn handle_model_select_mode(key: KeyEvent, state: &mut AppState) -> bool {
             state.move_model_selection_down();
             false
         }
+        KeyCode::Char('/') => {
+            state.model_filter_editing = true;
+            false
+        }
+        KeyCode::Char('p') => {
+            state.toggle_favorite_model();
+            false
+        }
         KeyCode::Enter => {
             if let Some(selected_model) = state.get_selected_model().cloned() {
-                state.set_current_model(selected_model.clone());
-                state.add_message(
-                    MessageRole::System,
-                    format!("Model changed to: {}", selected_model),
-                );
+                state.clear_model_filter();
                 state.input_mode = InputMode::Normal;
-                // TODO: Send model change event to worker
+                // 表示の切り替えはChatWorkerからのModelChangedで行う
+                send_command(command_tx, WorkerCommand::SetModel(selected_model));
             }
             false
         }
         _ => false,
     }
 }
+
+/// 絞り込み文字列の入力
+fn handle_filter_input(key: KeyEvent, state: &mut AppState) -> bool {
+    match key.code {
+        KeyCode::Esc => state.clear_model_filter(),
+        KeyCode::Enter | KeyCode::Down | KeyCode::Up => state.model_filter_editing = false,
+        KeyCode::Backspace => state.pop_model_filter(),
+        KeyCode::Char(c) => state.push_model_filter(c),
+        _ => {}
+    }
+    false
+}
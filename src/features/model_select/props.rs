@@ -1,9 +1,15 @@
+use crate::features::chat::state::ModelListSource;
 use crate::features::chat::theme::ChatTheme;
 
 #[derive(Debug)]
 pub struct ModelSelectProps<'a> {
-    pub available_models: &'a [String],
+    pub visible_models: &'a [&'a String],
+    pub favorite_models: &'a [String],
     pub current_model: &'a str,
     pub selected_index: usize,
+    pub filter: &'a str,
+    pub filter_editing: bool,
+    pub source: &'a ModelListSource,
+    pub offline: bool,
     pub theme: &'a ChatTheme,
 }
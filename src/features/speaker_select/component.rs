@@ -0,0 +1,83 @@
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use super::props::SpeakerSelectProps;
+use crate::features::chat::state::SpeakerListStatus;
+
+pub fn render_speaker_select_screen(frame: &mut Frame, props: &SpeakerSelectProps) {
+    let area = frame.area();
+
+    // Create a centered popup
+    let popup_area = Rect {
+        x: area.width / 4,
+        y: area.height / 4,
+        width: area.width / 2,
+        height: area.height / 2,
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Select Voice [{}]", status_label(props)));
+
+    // 一覧を表示できない間は状態だけを表示する
+    let message = match props.status {
+        SpeakerListStatus::NotLoaded | SpeakerListStatus::Loading => {
+            Some("Loading speakers from the VOICEVOX Engine…".to_string())
+        }
+        SpeakerListStatus::Failed(error) => Some(format!(
+            "Could not load speakers: {}\n\nPress r to retry.",
+            error
+        )),
+        SpeakerListStatus::Loaded if props.styles.is_empty() => {
+            Some("The engine has no speakers.".to_string())
+        }
+        SpeakerListStatus::Loaded => None,
+    };
+    if let Some(message) = message {
+        frame.render_widget(
+            Paragraph::new(message)
+                .wrap(Wrap { trim: true })
+                .block(block),
+            popup_area,
+        );
+        return;
+    }
+
+    let items: Vec<ListItem> = props
+        .styles
+        .iter()
+        .map(|(speaker, style)| {
+            let marker = if style.id == props.current_style {
+                "●"
+            } else {
+                " "
+            };
+            ListItem::new(format!(
+                "{} {}（{}） #{}",
+                marker, speaker.name, style.name, style.id
+            ))
+        })
+        .collect();
+
+    let speaker_list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(ratatui::style::Color::DarkGray));
+
+    // 一覧が長い場合も選択中のスタイルが見えるようにスクロールする
+    let mut list_state = ListState::default();
+    list_state.select(Some(props.selected_index));
+
+    frame.render_stateful_widget(speaker_list, popup_area, &mut list_state);
+}
+
+fn status_label(props: &SpeakerSelectProps) -> String {
+    match props.status {
+        SpeakerListStatus::NotLoaded | SpeakerListStatus::Loading => "loading".to_string(),
+        SpeakerListStatus::Loaded => format!("{} styles", props.styles.len()),
+        SpeakerListStatus::Failed(_) => "unavailable".to_string(),
+    }
+}
@@ -0,0 +1,35 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use crate::features::chat::state::{AppState, InputMode};
+
+pub fn handle_speaker_select_mode(key: KeyEvent, state: &mut AppState) -> bool {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            state.input_mode = InputMode::Normal;
+            false
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            state.move_speaker_selection_up();
+            false
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            state.move_speaker_selection_down();
+            false
+        }
+        KeyCode::Char(' ') | KeyCode::Char('p') => {
+            state.preview_selected_speaker();
+            false
+        }
+        KeyCode::Char('r') => {
+            state.reload_speakers();
+            false
+        }
+        KeyCode::Enter => {
+            if state.select_speaker() {
+                state.input_mode = InputMode::Normal;
+            }
+            false
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,13 @@
+use crate::audio::{Speaker, SpeakerStyle};
+use crate::features::chat::state::SpeakerListStatus;
+use crate::features::chat::theme::ChatTheme;
+
+#[derive(Debug)]
+pub struct SpeakerSelectProps<'a> {
+    /// キャラクターとスタイルの組（スタイルごとに1行）
+    pub styles: &'a [(&'a Speaker, &'a SpeakerStyle)],
+    pub current_style: u32,
+    pub selected_index: usize,
+    pub status: &'a SpeakerListStatus,
+    pub theme: &'a ChatTheme,
+}
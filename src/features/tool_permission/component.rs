@@ -0,0 +1,43 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use super::props::ToolPermissionProps;
+use crate::features::chat::state::MessageRole;
+
+pub fn render_tool_permission_dialog(frame: &mut Frame, props: &ToolPermissionProps) {
+    let area = frame.area();
+
+    // Create a centered popup
+    let height = (area.height / 3).max(7).min(area.height);
+    let popup_area = ratatui::layout::Rect {
+        x: area.width / 6,
+        y: (area.height - height) / 2,
+        width: area.width * 2 / 3,
+        height,
+    };
+
+    let tool_style = props.theme.get_message_style(&MessageRole::Tool);
+    let lines = vec![
+        Line::from(vec![
+            Span::raw("The assistant wants to run "),
+            Span::styled(props.tool_name, tool_style.add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(Span::styled(props.arguments, Style::default())),
+        Line::from(""),
+        Line::from("[y] Allow once  [a] Always allow  [n] Deny  [d] Always deny"),
+    ];
+
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Tool Permission"),
+        ),
+        popup_area,
+    );
+}
@@ -0,0 +1,15 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use crate::features::chat::state::AppState;
+use crate::tools::PermissionDecision;
+
+pub fn handle_tool_permission_mode(key: KeyEvent, state: &mut AppState) {
+    let decision = match key.code {
+        KeyCode::Char('y') | KeyCode::Enter => PermissionDecision::AllowOnce,
+        KeyCode::Char('a') => PermissionDecision::AlwaysAllow,
+        KeyCode::Char('n') | KeyCode::Esc => PermissionDecision::DenyOnce,
+        KeyCode::Char('d') => PermissionDecision::AlwaysDeny,
+        _ => return,
+    };
+    state.answer_tool_permission(decision);
+}
@@ -0,0 +1,8 @@
+use crate::features::chat::theme::ChatTheme;
+
+#[derive(Debug)]
+pub struct ToolPermissionProps<'a> {
+    pub tool_name: &'a str,
+    pub arguments: &'a str,
+    pub theme: &'a ChatTheme,
+}
@@ -1,18 +1,62 @@
+pub mod segmenter;
+
+use self::segmenter::SentenceSegmenter;
 use super::chat::events::ChatEvent;
 use super::chat::state::{AppState, MessageRole};
 use crate::audio;
+use crate::cassette::HttpClient;
+use crate::error::{Error, Result};
+use crate::retry::RetryPolicy;
+use crate::sound::AudioCommand;
+use crate::voice_params::VoiceParams;
 use log::{debug, error, info, warn};
-use reqwest::Client;
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// 読み上げに使う既定のスタイルID
+pub const DEFAULT_SPEAKER: u32 = audio::Speakers::Zundamon as u32;
+
+/// 話者選択画面で読み上げる見本の文
+const PREVIEW_TEXT: &str = "こんにちは。この声で読み上げます。";
+
+/// 音声合成タスクが使う接続先と通知先
+#[derive(Clone)]
+pub struct SynthesisContext {
+    pub client: Arc<HttpClient>,
+    pub audio_tx: std::sync::mpsc::Sender<AudioCommand>,
+    /// 合成に失敗したときのエラーの送り先
+    pub error_tx: mpsc::UnboundedSender<Error>,
+    /// 再試行中などの状況をステータス行に表示するための送り先
+    pub status_tx: mpsc::UnboundedSender<String>,
+    pub retry_policy: RetryPolicy,
+}
 
+/// `speaker`のスタイルと`params`の速さや高さで読み上げる
 pub async fn speak_text(
-    client: Arc<Client>,
+    context: &SynthesisContext,
     text: &str,
-    audio_tx: &std::sync::mpsc::Sender<Vec<u8>>,
-) -> Result<(), String> {
+    speaker: u32,
+    params: &VoiceParams,
+) -> Result<()> {
     debug!("Starting voice synthesis for text: {}", text);
 
-    let wav_data = match audio::generate_wav(client, text, audio::Speakers::Zundamon).await {
+    let wav_data = match audio::generate_wav(
+        context.client.clone(),
+        text,
+        speaker,
+        params,
+        &context.retry_policy,
+        |status| {
+            let _ = context.status_tx.send(format!(
+                "speech: retrying ({}/{})…",
+                status.attempt, status.max_retries
+            ));
+        },
+    )
+    .await
+    {
         Ok(data) => {
             info!("Successfully generated WAV data ({} bytes)", data.len());
             data
@@ -24,46 +68,185 @@ pub async fn speak_text(
     };
 
     debug!("Sending audio data to playback system");
-    match audio_tx.send(wav_data) {
+    match context.audio_tx.send(AudioCommand::Play(wav_data)) {
         Ok(_) => {
             info!("Audio data sent successfully");
             Ok(())
         }
         Err(_) => {
             error!("Failed to send audio data - channel closed");
-            Err("Audio channel closed".to_string())
+            Err(Error::AudioDevice("Audio channel closed".to_string()))
         }
     }
 }
 
-pub fn handle_voice_event(
-    chat_event: &ChatEvent,
-    app_state: &AppState,
-    client: Arc<Client>,
-    audio_tx: std::sync::mpsc::Sender<Vec<u8>>,
-) {
-    if let ChatEvent::StreamingComplete(_) = chat_event {
-        if let Some(last_message) = app_state.messages.last() {
-            if last_message.role == MessageRole::Assistant {
-                let text = last_message.content.clone();
-                info!("Triggering voice synthesis for assistant message");
-                debug!("Message content (length: {}): {}", text.len(), text);
-
-                tokio::spawn(async move {
-                    match speak_text(client, &text, &audio_tx).await {
-                        Ok(_) => {
-                            info!("Voice synthesis completed successfully");
-                        }
-                        Err(e) => {
-                            error!("Voice synthesis failed: {}", e);
-                        }
-                    }
-                });
-            } else {
-                debug!("Skipping voice synthesis for non-assistant message");
+/// 読み上げる1つの文
+#[derive(Debug, Clone)]
+struct Utterance {
+    text: String,
+    speaker: u32,
+    params: VoiceParams,
+}
+
+/// 読み上げの順番待ち
+///
+/// 追加した順に1つずつ音声合成して再生キューに送るので、
+/// 前の文を再生している間に次の文を合成しつつ、順番どおりに再生される
+pub struct SpeechQueue {
+    context: SynthesisContext,
+    utterance_tx: mpsc::UnboundedSender<Utterance>,
+    task: JoinHandle<()>,
+    /// 応答の読み上げに使うスタイルID
+    speaker: u32,
+    /// スタイルIDごとの読み上げパラメータ
+    voice_params: HashMap<u32, VoiceParams>,
+    /// 文ごとに読み上げている生成中の応答
+    segmenter: Option<SentenceSegmenter>,
+}
+
+impl SpeechQueue {
+    /// 合成タスクを起動する（合成に失敗した場合は`context.error_tx`にエラーを送る）
+    pub fn new(context: SynthesisContext) -> Self {
+        let (utterance_tx, task) = spawn_synthesis(context.clone());
+        Self {
+            context,
+            utterance_tx,
+            task,
+            speaker: DEFAULT_SPEAKER,
+            voice_params: HashMap::new(),
+            segmenter: None,
+        }
+    }
+
+    /// `speaker`のスタイルで読み上げる（先に追加した文の後に再生される）
+    pub fn speak(&self, text: String, speaker: u32) {
+        debug!("Queueing speech (speaker {}): {}", speaker, text);
+        let params = self.voice_params.get(&speaker).cloned().unwrap_or_default();
+        let _ = self.utterance_tx.send(Utterance {
+            text,
+            speaker,
+            params,
+        });
+    }
+
+    /// スタイルごとの読み上げパラメータを置き換える（次に追加する文から反映する）
+    pub fn set_voice_params(&mut self, voice_params: HashMap<u32, VoiceParams>) {
+        self.voice_params = voice_params;
+    }
+
+    /// これから読み上げる応答の声を切り替える
+    pub fn set_speaker(&mut self, speaker: u32) {
+        self.speaker = speaker;
+    }
+
+    /// 読み上げ中の音声を止めて、`speaker`の声で見本の文を読み上げる
+    pub fn preview(&mut self, speaker: u32) {
+        self.stop();
+        self.speak(PREVIEW_TEXT.to_string(), speaker);
+    }
+
+    /// 合成待ち・再生中の音声をすべて破棄する
+    pub fn stop(&mut self) {
+        self.task.abort();
+        self.segmenter = None;
+        (self.utterance_tx, self.task) = spawn_synthesis(self.context.clone());
+        let _ = self.context.audio_tx.send(AudioCommand::Stop);
+    }
+
+    /// 応答の生成中は、文が揃うたびに読み上げる
+    ///
+    /// 構造化出力では話者のスタイルが応答の最後に決まるので、完了時に全文を読み上げる
+    pub fn handle_chat_event(&mut self, chat_event: &ChatEvent, app_state: &AppState) {
+        match chat_event {
+            ChatEvent::StreamingStart(_) => {
+                // ツール呼び出しを挟んだ前の応答の残りを先に読み上げる
+                self.flush();
+                if !app_state.structured_output.enabled {
+                    self.segmenter = Some(SentenceSegmenter::new());
+                }
+            }
+            ChatEvent::StreamingChunk(_, content) => {
+                let sentences = match self.segmenter.as_mut() {
+                    Some(segmenter) => segmenter.push(content),
+                    None => return,
+                };
+                for sentence in sentences {
+                    self.speak(sentence, self.speaker);
+                }
             }
-        } else {
+            ChatEvent::StreamingComplete(_) => {
+                if self.segmenter.is_some() {
+                    self.flush();
+                } else {
+                    self.speak_last_reply(app_state);
+                }
+            }
+            // 中断された応答の残りは読み上げない
+            ChatEvent::StreamingCancelled(_) | ChatEvent::Error(_) => {
+                self.segmenter = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// 生成中の応答の最後の文を読み上げる
+    fn flush(&mut self) {
+        if let Some(sentence) = self
+            .segmenter
+            .take()
+            .and_then(|mut segmenter| segmenter.finish())
+        {
+            self.speak(sentence, self.speaker);
+        }
+    }
+
+    /// 完了したアシスタントの応答を全文読み上げる
+    fn speak_last_reply(&self, app_state: &AppState) {
+        let Some(last_message) = app_state.tree.head_message() else {
             warn!("No messages found when trying to synthesize voice");
+            return;
+        };
+        if last_message.role != MessageRole::Assistant || last_message.is_truncated {
+            debug!("Skipping voice synthesis for non-assistant message");
+            return;
         }
+
+        // 構造化出力で選ばれたスタイルがあればその声で読み上げる
+        let speaker = last_message.voice_style.unwrap_or(self.speaker);
+        info!(
+            "Triggering voice synthesis for assistant message (speaker {})",
+            speaker
+        );
+        self.speak(last_message.content.clone(), speaker);
     }
 }
+
+/// 届いた文を順に合成するタスクを起動する
+fn spawn_synthesis(
+    context: SynthesisContext,
+) -> (mpsc::UnboundedSender<Utterance>, JoinHandle<()>) {
+    let (utterance_tx, mut utterance_rx) = mpsc::unbounded_channel::<Utterance>();
+    let task = tokio::spawn(async move {
+        while let Some(utterance) = utterance_rx.recv().await {
+            match speak_text(
+                &context,
+                &utterance.text,
+                utterance.speaker,
+                &utterance.params,
+            )
+            .await
+            {
+                Ok(_) => {
+                    info!("Voice synthesis completed successfully");
+                }
+                Err(e) => {
+                    error!("Voice synthesis failed: {}", e);
+                    let _ = context.error_tx.send(e);
+                    // 同じ原因で続けて失敗しないよう、待っている文は読み上げない
+                    while utterance_rx.try_recv().is_ok() {}
+                }
+            }
+        }
+    });
+    (utterance_tx, task)
+}
@@ -0,0 +1,206 @@
+/// 文の終わりとみなす文字
+const TERMINATORS: [char; 5] = ['。', '！', '？', '!', '?'];
+
+/// 開き括弧と対応する閉じ括弧（内側では文を区切らない）
+const BRACKETS: [(char, char); 7] = [
+    ('「', '」'),
+    ('『', '』'),
+    ('（', '）'),
+    ('(', ')'),
+    ('【', '】'),
+    ('“', '”'),
+    ('"', '"'),
+];
+
+/// 開き括弧としては使わない閉じ括弧か（`"`は開き括弧にもなるので含めない）
+fn is_closing_only(c: char) -> bool {
+    BRACKETS.iter().any(|(_, closer)| *closer == c)
+        && !BRACKETS.iter().any(|(opener, _)| *opener == c)
+}
+
+/// ストリーミング中の応答を、読み上げられる文に区切る
+///
+/// `。！？!?`と改行で区切るが、括弧や引用符の内側では区切らない。
+/// 「本当！？」のように文末の記号が続く場合や、文末の記号の直後に閉じ括弧が続く場合はまとめて1文にする。
+/// 「ありがとう。」のように文末の記号の直後で括弧がすべて閉じた場合は、そこで文が終わる
+#[derive(Debug, Clone, Default)]
+pub struct SentenceSegmenter {
+    buffer: String,
+    /// 閉じられていない括弧に対応する閉じ括弧
+    closers: Vec<char>,
+    /// 文末の記号を読んだ（次の文字が文末の記号か閉じ括弧でなければ区切る）
+    at_terminator: bool,
+    /// 括弧の内側を含め、直前が文末の記号（とそれに続く閉じ括弧）だった
+    after_terminator: bool,
+}
+
+impl SentenceSegmenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// チャンクを追加し、区切りが確定した文を返す
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        let mut sentences = Vec::new();
+        for c in chunk.chars() {
+            if self.at_terminator && !TERMINATORS.contains(&c) && !is_closing_only(c) {
+                self.at_terminator = false;
+                sentences.extend(self.take_sentence());
+            }
+
+            if c == '\n' {
+                // 段落が変わったら閉じられていない括弧は諦める
+                self.closers.clear();
+                self.at_terminator = false;
+                self.after_terminator = false;
+                sentences.extend(self.take_sentence());
+                continue;
+            }
+
+            self.buffer.push(c);
+            if self.closers.last() == Some(&c) {
+                self.closers.pop();
+                // 「…。」のように文末で括弧がすべて閉じたら、括弧の外の文末と同じく扱う
+                if self.after_terminator && self.closers.is_empty() {
+                    self.at_terminator = true;
+                }
+            } else if let Some(&(_, closer)) = BRACKETS.iter().find(|(opener, _)| *opener == c) {
+                self.closers.push(closer);
+                self.after_terminator = false;
+            } else if TERMINATORS.contains(&c) {
+                self.after_terminator = true;
+                if self.closers.is_empty() {
+                    self.at_terminator = true;
+                }
+            } else {
+                self.after_terminator = false;
+            }
+        }
+        sentences
+    }
+
+    /// ストリームの終わりで、残っている文を返す
+    pub fn finish(&mut self) -> Option<String> {
+        self.closers.clear();
+        self.at_terminator = false;
+        self.after_terminator = false;
+        self.take_sentence()
+    }
+
+    /// 溜めた文を取り出す（空白や記号だけなら読み上げないので捨てる）
+    fn take_sentence(&mut self) -> Option<String> {
+        let sentence = std::mem::take(&mut self.buffer);
+        let sentence = sentence.trim();
+        if sentence.chars().any(char::is_alphanumeric) {
+            Some(sentence.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// チャンクを順に渡し、最後に残りを取り出した全文を返す
+    fn segment(chunks: &[&str]) -> Vec<String> {
+        let mut segmenter = SentenceSegmenter::new();
+        let mut sentences: Vec<String> = chunks
+            .iter()
+            .flat_map(|chunk| segmenter.push(chunk))
+            .collect();
+        sentences.extend(segmenter.finish());
+        sentences
+    }
+
+    #[test]
+    fn splits_at_japanese_terminators() {
+        assert_eq!(
+            segment(&["こんにちは。元気です！あなたは？"]),
+            vec!["こんにちは。", "元気です！", "あなたは？"]
+        );
+    }
+
+    #[test]
+    fn sentence_is_emitted_once_the_next_character_arrives() {
+        let mut segmenter = SentenceSegmenter::new();
+        // 次の文字が文末の記号の続きかどうか分かるまで待つ
+        assert!(segmenter.push("はい。").is_empty());
+        assert_eq!(segmenter.push("次"), vec!["はい。"]);
+        assert_eq!(segmenter.finish().as_deref(), Some("次"));
+    }
+
+    #[test]
+    fn consecutive_terminators_stay_in_one_sentence() {
+        assert_eq!(
+            segment(&["本当！", "？うそ!?", "ほんと。"]),
+            vec!["本当！？", "うそ!?", "ほんと。"]
+        );
+    }
+
+    #[test]
+    fn terminators_inside_brackets_do_not_split() {
+        assert_eq!(
+            segment(&["彼は「はい。", "わかりました」と言った。次へ"]),
+            vec!["彼は「はい。わかりました」と言った。", "次へ"]
+        );
+    }
+
+    #[test]
+    fn closing_brackets_after_a_terminator_stay_with_the_sentence() {
+        assert_eq!(
+            segment(&["「ありがとう。」", "（そうだ。）", "終わり"]),
+            vec!["「ありがとう。」", "（そうだ。）", "終わり"]
+        );
+        assert_eq!(
+            segment(&["『「本当！？」』", "うん。"]),
+            vec!["『「本当！？」』", "うん。"]
+        );
+        assert_eq!(
+            segment(&["それでいい。）", "次の文。"]),
+            vec!["それでいい。）", "次の文。"]
+        );
+    }
+
+    #[test]
+    fn quoted_lines_are_spoken_while_streaming() {
+        let mut segmenter = SentenceSegmenter::new();
+        assert!(segmenter.push("「おはよう。」").is_empty());
+        assert_eq!(segmenter.push("「"), vec!["「おはよう。」"]);
+        assert!(segmenter.push("元気？」").is_empty());
+        assert_eq!(segmenter.finish().as_deref(), Some("「元気？」"));
+    }
+
+    #[test]
+    fn newlines_split_and_reset_open_brackets() {
+        assert_eq!(
+            segment(&["見出し\n「閉じない括弧\n", "本文です。"]),
+            vec!["見出し", "「閉じない括弧", "本文です。"]
+        );
+    }
+
+    #[test]
+    fn ascii_periods_do_not_split_numbers() {
+        assert_eq!(
+            segment(&["円周率は3.", "14です。バージョン1.2.3を使う"]),
+            vec!["円周率は3.14です。", "バージョン1.2.3を使う"]
+        );
+    }
+
+    #[test]
+    fn finish_flushes_the_trailing_fragment() {
+        let mut segmenter = SentenceSegmenter::new();
+        assert!(segmenter.push("途中で終わる文").is_empty());
+        assert_eq!(segmenter.finish().as_deref(), Some("途中で終わる文"));
+        assert_eq!(segmenter.finish(), None);
+    }
+
+    #[test]
+    fn fragments_without_words_are_dropped() {
+        assert_eq!(
+            segment(&["こんにちは。", "  ！\n", "……"]),
+            vec!["こんにちは。"]
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+use super::props::VoiceSettingsProps;
+use crate::voice_params::VoiceParam;
+
+pub fn render_voice_settings_panel(frame: &mut Frame, props: &VoiceSettingsProps) {
+    let area = frame.area();
+
+    let panel_area = ratatui::layout::Rect {
+        x: area.width / 8,
+        y: area.height / 4,
+        width: area.width * 3 / 4,
+        height: area.height / 2,
+    };
+
+    frame.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Voice Parameters [{}]", props.speaker_label)),
+        panel_area,
+    );
+
+    let inner_area = ratatui::layout::Rect {
+        x: panel_area.x + 1,
+        y: panel_area.y + 1,
+        width: panel_area.width.saturating_sub(2),
+        height: panel_area.height.saturating_sub(2),
+    };
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner_area);
+
+    let mut items = Vec::new();
+    for (i, param) in VoiceParam::ALL.iter().enumerate() {
+        let value = match (i == props.selected_index, props.editing) {
+            (true, Some(input)) => format!("{}█", input),
+            _ => props
+                .params
+                .value(*param)
+                .unwrap_or_else(|| "default".to_string()),
+        };
+        let line = format!("{:.<26} {}", param.name(), value);
+
+        let style = if i == props.selected_index {
+            Style::default().bg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+        items.push(ListItem::new(line).style(style));
+    }
+
+    frame.render_widget(
+        List::new(items).block(Block::default().borders(Borders::NONE)),
+        layout[0],
+    );
+
+    // 入力できる範囲またはエラーを最下行に表示する
+    let (footer, style) = match props.error {
+        Some(error) => (error.to_string(), Style::default().fg(Color::Red)),
+        None => {
+            let hint = VoiceParam::ALL
+                .get(props.selected_index)
+                .map(|param| param.hint())
+                .unwrap_or_default();
+            (hint, Style::default().fg(Color::DarkGray))
+        }
+    };
+    frame.render_widget(Paragraph::new(footer).style(style), layout[1]);
+}
@@ -0,0 +1,45 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+use crate::features::chat::state::{AppState, InputMode};
+
+pub fn handle_voice_settings_mode(key: KeyEvent, state: &mut AppState) -> bool {
+    if state.voice_input.is_some() {
+        handle_value_input(key, state);
+        return false;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            state.voice_error = None;
+            state.input_mode = InputMode::Normal;
+        }
+        KeyCode::Up | KeyCode::Char('k') => state.move_voice_selection_up(),
+        KeyCode::Down | KeyCode::Char('j') => state.move_voice_selection_down(),
+        KeyCode::Left | KeyCode::Char('h') => state.adjust_voice_param(-1),
+        KeyCode::Right | KeyCode::Char('l') => state.adjust_voice_param(1),
+        KeyCode::Enter => state.start_voice_input(),
+        KeyCode::Char(' ') => state.preview_current_voice(),
+        KeyCode::Char('d') | KeyCode::Delete => state.reset_voice_param(),
+        _ => {}
+    }
+    false
+}
+
+/// 選択中のパラメータの値の入力
+fn handle_value_input(key: KeyEvent, state: &mut AppState) {
+    let Some(input) = state.voice_input.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => {
+            state.voice_input = None;
+            state.voice_error = None;
+        }
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Enter => state.apply_voice_input(),
+        _ => {}
+    }
+}
@@ -0,0 +1,14 @@
+use crate::features::chat::theme::ChatTheme;
+use crate::voice_params::VoiceParams;
+
+#[derive(Debug)]
+pub struct VoiceSettingsProps<'a> {
+    pub params: &'a VoiceParams,
+    /// 調整している声（キャラクターとスタイルの名前）
+    pub speaker_label: &'a str,
+    pub selected_index: usize,
+    /// 編集中の値（編集していなければ`None`）
+    pub editing: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub theme: &'a ChatTheme,
+}
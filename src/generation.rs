@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use crate::backend::BackendKind;
+use crate::error::{Error, Result};
+
+/// 停止シーケンスの上限（Chat Completions APIの制限）
+const MAX_STOP_SEQUENCES: usize = 4;
+
+/// 推論モデルの推論の深さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "minimal" => Some(ReasoningEffort::Minimal),
+            "low" => Some(ReasoningEffort::Low),
+            "medium" => Some(ReasoningEffort::Medium),
+            "high" => Some(ReasoningEffort::High),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+}
+
+/// 実行中に変更できる生成パラメータの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationParam {
+    Temperature,
+    TopP,
+    MaxCompletionTokens,
+    ReasoningEffort,
+    Seed,
+    Stop,
+}
+
+impl GenerationParam {
+    pub const ALL: [GenerationParam; 6] = [
+        GenerationParam::Temperature,
+        GenerationParam::TopP,
+        GenerationParam::MaxCompletionTokens,
+        GenerationParam::ReasoningEffort,
+        GenerationParam::Seed,
+        GenerationParam::Stop,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|param| param.name() == name.trim().to_ascii_lowercase())
+    }
+
+    /// `/set`コマンドと設定ファイルで使う名前
+    pub fn name(&self) -> &'static str {
+        match self {
+            GenerationParam::Temperature => "temperature",
+            GenerationParam::TopP => "top_p",
+            GenerationParam::MaxCompletionTokens => "max_completion_tokens",
+            GenerationParam::ReasoningEffort => "reasoning_effort",
+            GenerationParam::Seed => "seed",
+            GenerationParam::Stop => "stop",
+        }
+    }
+
+    /// 入力できる値の説明
+    pub fn hint(&self) -> &'static str {
+        match self {
+            GenerationParam::Temperature => "0.0 - 2.0",
+            GenerationParam::TopP => "0.0 - 1.0",
+            GenerationParam::MaxCompletionTokens => "positive integer",
+            GenerationParam::ReasoningEffort => "minimal | low | medium | high",
+            GenerationParam::Seed => "integer",
+            GenerationParam::Stop => "up to 4 sequences separated by |",
+        }
+    }
+
+    /// モデルがこのパラメータを受け付けるか
+    ///
+    /// OpenAIの推論モデル（gpt-5系・o系）は`temperature`・`top_p`・`stop`を拒否し、
+    /// それ以外のモデルは`reasoning_effort`を拒否する
+    pub fn is_supported(&self, backend: BackendKind, model: &str) -> bool {
+        match backend {
+            BackendKind::OpenAi => {
+                let reasoning = is_reasoning_model(model);
+                match self {
+                    GenerationParam::Temperature
+                    | GenerationParam::TopP
+                    | GenerationParam::Stop => !reasoning,
+                    GenerationParam::ReasoningEffort => reasoning,
+                    GenerationParam::MaxCompletionTokens | GenerationParam::Seed => true,
+                }
+            }
+            BackendKind::Ollama => !matches!(self, GenerationParam::ReasoningEffort),
+        }
+    }
+}
+
+/// OpenAIの推論モデルか（`gpt-5-chat`系は通常のモデル）
+fn is_reasoning_model(model: &str) -> bool {
+    let model = model.rsplit('/').next().unwrap_or(model);
+    (model.starts_with("gpt-5") && !model.starts_with("gpt-5-chat"))
+        || ["o1", "o3", "o4"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+}
+
+/// 応答の生成パラメータ（未設定の値はプロバイダーの既定値に任せる）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_completion_tokens: Option<u32>,
+    pub reasoning_effort: Option<ReasoningEffort>,
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl GenerationParams {
+    /// 表示用の値（未設定は`None`）
+    pub fn value(&self, param: GenerationParam) -> Option<String> {
+        match param {
+            GenerationParam::Temperature => self.temperature.map(|v| v.to_string()),
+            GenerationParam::TopP => self.top_p.map(|v| v.to_string()),
+            GenerationParam::MaxCompletionTokens => {
+                self.max_completion_tokens.map(|v| v.to_string())
+            }
+            GenerationParam::ReasoningEffort => self.reasoning_effort.map(|v| v.name().to_string()),
+            GenerationParam::Seed => self.seed.map(|v| v.to_string()),
+            GenerationParam::Stop => (!self.stop.is_empty()).then(|| self.stop.join(" | ")),
+        }
+    }
+
+    /// 文字列の値を検証して設定する（空文字列は既定値に戻す）
+    pub fn set(&mut self, param: GenerationParam, value: &str) -> Result<()> {
+        let value = value.trim();
+        if value.is_empty() || value == "default" {
+            self.unset(param);
+            return Ok(());
+        }
+
+        let invalid = || {
+            Error::Config(format!(
+                "invalid {} '{}' (expected {})",
+                param.name(),
+                value,
+                param.hint()
+            ))
+        };
+        match param {
+            GenerationParam::Temperature => {
+                self.temperature = Some(parse_in_range(value, 0.0, 2.0).ok_or_else(invalid)?);
+            }
+            GenerationParam::TopP => {
+                self.top_p = Some(parse_in_range(value, 0.0, 1.0).ok_or_else(invalid)?);
+            }
+            GenerationParam::MaxCompletionTokens => {
+                let tokens = value.parse::<u32>().map_err(|_| invalid())?;
+                if tokens == 0 {
+                    return Err(invalid());
+                }
+                self.max_completion_tokens = Some(tokens);
+            }
+            GenerationParam::ReasoningEffort => {
+                self.reasoning_effort =
+                    Some(ReasoningEffort::from_name(value).ok_or_else(invalid)?);
+            }
+            GenerationParam::Seed => {
+                self.seed = Some(value.parse().map_err(|_| invalid())?);
+            }
+            GenerationParam::Stop => {
+                let stop: Vec<String> = value
+                    .split('|')
+                    .map(|sequence| sequence.trim().replace("\\n", "\n"))
+                    .filter(|sequence| !sequence.is_empty())
+                    .collect();
+                if stop.len() > MAX_STOP_SEQUENCES {
+                    return Err(invalid());
+                }
+                self.stop = stop;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unset(&mut self, param: GenerationParam) {
+        match param {
+            GenerationParam::Temperature => self.temperature = None,
+            GenerationParam::TopP => self.top_p = None,
+            GenerationParam::MaxCompletionTokens => self.max_completion_tokens = None,
+            GenerationParam::ReasoningEffort => self.reasoning_effort = None,
+            GenerationParam::Seed => self.seed = None,
+            GenerationParam::Stop => self.stop.clear(),
+        }
+    }
+
+    /// 設定済みだがモデルが受け付けないため送信しないパラメータ
+    pub fn unsupported(&self, backend: BackendKind, model: &str) -> Vec<GenerationParam> {
+        GenerationParam::ALL
+            .into_iter()
+            .filter(|param| self.value(*param).is_some() && !param.is_supported(backend, model))
+            .collect()
+    }
+
+    /// Chat Completionsのリクエスト本文に、モデルが受け付けるパラメータを追加する
+    pub fn apply_openai(&self, model: &str, body: &mut serde_json::Value) {
+        let supported = |param: GenerationParam| param.is_supported(BackendKind::OpenAi, model);
+
+        if let Some(temperature) = self
+            .temperature
+            .filter(|_| supported(GenerationParam::Temperature))
+        {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = self.top_p.filter(|_| supported(GenerationParam::TopP)) {
+            body["top_p"] = top_p.into();
+        }
+        if let Some(max_tokens) = self.max_completion_tokens {
+            body["max_completion_tokens"] = max_tokens.into();
+        }
+        if let Some(effort) = self
+            .reasoning_effort
+            .filter(|_| supported(GenerationParam::ReasoningEffort))
+        {
+            body["reasoning_effort"] = effort.name().into();
+        }
+        if let Some(seed) = self.seed {
+            body["seed"] = seed.into();
+        }
+        if !self.stop.is_empty() && supported(GenerationParam::Stop) {
+            body["stop"] = self.stop.clone().into();
+        }
+    }
+
+    /// Ollamaの`options`にパラメータを追加する（`ollama_options`の値より優先する）
+    pub fn apply_ollama(&self, options: &mut serde_json::Map<String, serde_json::Value>) {
+        if let Some(temperature) = self.temperature {
+            options.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(top_p) = self.top_p {
+            options.insert("top_p".to_string(), top_p.into());
+        }
+        if let Some(max_tokens) = self.max_completion_tokens {
+            options.insert("num_predict".to_string(), max_tokens.into());
+        }
+        if let Some(seed) = self.seed {
+            options.insert("seed".to_string(), seed.into());
+        }
+        if !self.stop.is_empty() {
+            options.insert("stop".to_string(), self.stop.clone().into());
+        }
+    }
+}
+
+fn parse_in_range(value: &str, min: f64, max: f64) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| (min..=max).contains(v))
+}
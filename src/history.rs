@@ -0,0 +1,486 @@
+use serde::{Deserialize, Serialize};
+
+use crate::attachment::ImageAttachment;
+use crate::summary;
+use crate::tokens;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Message {
+    pub role: String,
+    pub content: MessageContent,
+    /// アシスタントが要求したツール呼び出し
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// ツール実行結果が対応する呼び出しのID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// キャンセルで途中終了した応答か（APIには送信しない）
+    #[serde(skip)]
+    pub truncated: bool,
+}
+
+impl Message {
+    pub fn new(role: &str, content: &str) -> Self {
+        Self {
+            role: role.into(),
+            content: MessageContent::Text(content.into()),
+            tool_calls: vec![],
+            tool_call_id: None,
+            truncated: false,
+        }
+    }
+
+    /// 画像を添付したメッセージ（画像がなければ通常のテキストとして送る）
+    pub fn with_images(role: &str, content: &str, images: &[ImageAttachment]) -> Self {
+        if images.is_empty() {
+            return Self::new(role, content);
+        }
+
+        let mut parts = vec![ContentPart::Text {
+            text: content.into(),
+        }];
+        parts.extend(images.iter().map(|image| ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: image.data_url(),
+            },
+        }));
+        Self {
+            content: MessageContent::Parts(parts),
+            ..Self::new(role, "")
+        }
+    }
+}
+
+/// メッセージの本文（画像を含む場合はパーツの配列として送る）
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// テキスト部分（複数あれば改行で連結する）
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    ContentPart::ImageUrl { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// 添付画像のdata URL
+    pub fn image_urls(&self) -> Vec<&str> {
+        match self {
+            MessageContent::Text(_) => vec![],
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::ImageUrl { image_url } => Some(image_url.url.as_str()),
+                    ContentPart::Text { .. } => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Chat Completions形式のコンテンツパーツ
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+/// Chat Completions形式のツール呼び出し
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    /// JSON文字列としての引数
+    pub arguments: String,
+}
+
+/// コンテキストの使用状況（トークン数は概算）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextUsage {
+    pub used_tokens: usize,
+    /// 応答用の予約分を除いた、履歴に使えるトークン数
+    pub budget_tokens: usize,
+    pub context_limit: usize,
+}
+
+impl ContextUsage {
+    pub fn remaining_tokens(&self) -> usize {
+        self.budget_tokens.saturating_sub(self.used_tokens)
+    }
+}
+
+/// バックエンド共通の会話履歴
+///
+/// システムメッセージは常に保持し、会話メッセージはトークン数が予算を超えると
+/// 古いユーザー発言とその応答をまとめて破棄する。
+/// 要約が有効な場合、破棄したターンは要約されるまで`dropped_messages`に残す
+#[derive(Clone, Debug)]
+pub struct ChatHistory {
+    context_limit: usize,
+    system_messages: Vec<Message>,
+    chat_messages: Vec<Message>,
+    summarize_dropped: bool,
+    /// これまでの会話の要約
+    summary: Option<String>,
+    /// 予算から外れ、まだ要約に取り込まれていないメッセージ
+    dropped_messages: Vec<Message>,
+}
+
+impl Default for ChatHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatHistory {
+    pub fn new() -> Self {
+        Self {
+            context_limit: tokens::DEFAULT_CONTEXT_LIMIT,
+            system_messages: vec![],
+            chat_messages: vec![],
+            summarize_dropped: false,
+            summary: None,
+            dropped_messages: vec![],
+        }
+    }
+
+    /// 破棄したターンを要約用に残すかどうか
+    pub fn set_summarize_dropped(&mut self, enabled: bool) {
+        self.summarize_dropped = enabled;
+        if !enabled {
+            self.dropped_messages.clear();
+        }
+    }
+
+    /// 要約待ちのターンがあるか
+    pub fn needs_summary(&self) -> bool {
+        !self.dropped_messages.is_empty()
+    }
+
+    /// 要約待ちのターンを要約するためのリクエスト
+    pub fn summary_request(&self) -> Option<Vec<Message>> {
+        if !self.needs_summary() {
+            return None;
+        }
+        Some(summary::build_summary_request(
+            self.summary.as_deref(),
+            &self.dropped_messages,
+        ))
+    }
+
+    /// 新しい要約を反映し、要約済みのターンを手放す
+    pub fn apply_summary(&mut self, summary: &str) {
+        self.summary = Some(summary.trim().to_string());
+        self.dropped_messages.clear();
+        self.trim_to_budget();
+    }
+
+    /// モデルのコンテキスト長を設定し、超過していれば古い会話を破棄する
+    pub fn set_context_limit(&mut self, context_limit: usize) {
+        self.context_limit = context_limit;
+        self.trim_to_budget();
+    }
+
+    pub fn context_usage(&self) -> ContextUsage {
+        ContextUsage {
+            used_tokens: self.token_count(),
+            budget_tokens: self.budget_tokens(),
+            context_limit: self.context_limit,
+        }
+    }
+
+    fn budget_tokens(&self) -> usize {
+        self.context_limit
+            .saturating_sub(tokens::response_reserve(self.context_limit))
+    }
+
+    /// `messages()`と同じ並びのトークン数（画像を含むメッセージを複製しないよう参照で数える）
+    fn token_count(&self) -> usize {
+        let summary_tokens = self
+            .summary
+            .as_deref()
+            .map(|summary| tokens::estimate_message_tokens(&summary::summary_message(summary)))
+            .unwrap_or_default();
+        self.system_messages
+            .iter()
+            .chain(&self.chat_messages)
+            .map(tokens::estimate_message_tokens)
+            .sum::<usize>()
+            + summary_tokens
+    }
+
+    /// 予算を超えている間、最も古いターン（ユーザー発言と続く応答）を破棄する
+    ///
+    /// 最新のターンは予算を超えていても残す
+    fn trim_to_budget(&mut self) {
+        let budget = self.budget_tokens();
+        let mut used = self.token_count();
+        // 破棄するメッセージの数（ターンの区切りごとに進める）
+        let mut drop_end = 0;
+
+        while used > budget {
+            // 残りの先頭の次のユーザー発言までを1ターンとみなす
+            let turn_end = self.chat_messages[drop_end..]
+                .iter()
+                .skip(1)
+                .position(|message| message.role == "user")
+                .map(|pos| drop_end + pos + 1);
+
+            match turn_end {
+                Some(turn_end) => {
+                    used -= self.chat_messages[drop_end..turn_end]
+                        .iter()
+                        .map(tokens::estimate_message_tokens)
+                        .sum::<usize>();
+                    drop_end = turn_end;
+                }
+                None => break,
+            }
+        }
+
+        let dropped = self.chat_messages.drain(..drop_end);
+        if self.summarize_dropped {
+            self.dropped_messages.extend(dropped);
+        }
+    }
+
+    pub fn push_system_message(&mut self, prompt: &str) {
+        self.system_messages.push(Message::new("system", prompt));
+    }
+
+    /// システムプロンプトを置き換える（会話履歴は残す）
+    pub fn set_system_prompt(&mut self, prompt: &str) {
+        self.system_messages = vec![Message::new("system", prompt)];
+        self.trim_to_budget();
+    }
+
+    /// 最後のユーザー発言の位置
+    fn last_user_index(&self) -> Option<usize> {
+        self.chat_messages
+            .iter()
+            .rposition(|message| message.role == "user")
+    }
+
+    /// 最後のユーザー発言より後のメッセージ（最後の応答とツール呼び出し）
+    pub fn last_reply(&self) -> Vec<Message> {
+        match self.last_user_index() {
+            Some(index) => self.chat_messages[index + 1..].to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 会話履歴を置き換える（要約は破棄し、予算を超える分は通常どおり破棄・要約する）
+    pub fn load(&mut self, messages: Vec<Message>) {
+        self.summary = None;
+        self.dropped_messages.clear();
+        self.chat_messages = messages;
+        self.trim_to_budget();
+    }
+
+    /// 会話履歴と要約を破棄する（システムプロンプトは残す）
+    pub fn clear(&mut self) {
+        self.chat_messages.clear();
+        self.summary = None;
+        self.dropped_messages.clear();
+    }
+
+    fn push_chat_message(&mut self, role: &str, input: &str, truncated: bool) {
+        self.chat_messages.push(Message {
+            truncated,
+            ..Message::new(role, input)
+        });
+    }
+
+    /// ユーザー発言を追加し、送信前に予算内へ収める
+    pub fn push_user_message(&mut self, input: &str, images: &[ImageAttachment]) {
+        self.chat_messages
+            .push(Message::with_images("user", input, images));
+        self.trim_to_budget();
+    }
+
+    pub fn push_assistant_message(&mut self, input: &str) {
+        self.push_chat_message("assistant", input, false);
+    }
+
+    /// ツール呼び出しを要求したアシスタントの応答を記録する
+    pub fn push_tool_calls(&mut self, content: &str, tool_calls: Vec<ToolCall>) {
+        self.chat_messages.push(Message {
+            tool_calls,
+            ..Message::new("assistant", content)
+        });
+    }
+
+    /// ツールの実行結果を記録する
+    pub fn push_tool_result(&mut self, tool_call_id: &str, result: &str) {
+        self.chat_messages.push(Message {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Message::new("tool", result)
+        });
+    }
+
+    /// キャンセルで途中終了した応答を記録する
+    pub fn push_truncated_assistant_message(&mut self, input: &str) {
+        self.push_chat_message("assistant", input, true);
+    }
+
+    pub fn messages(&self) -> Vec<Message> {
+        self.system_messages
+            .iter()
+            .cloned()
+            .chain(self.summary.as_deref().map(summary::summary_message))
+            .chain(self.chat_messages.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1文字1トークンとして数える、`len`文字の日本語の本文
+    fn text(len: usize) -> String {
+        "あ".repeat(len)
+    }
+
+    fn contents(messages: &[Message]) -> Vec<(String, String)> {
+        messages
+            .iter()
+            .map(|message| (message.role.clone(), message.content.text()))
+            .collect()
+    }
+
+    /// 予算が75トークン（応答用に25トークンを空ける）の履歴
+    fn small_history() -> ChatHistory {
+        let mut history = ChatHistory::new();
+        history.set_context_limit(100);
+        history.push_system_message("sys");
+        history
+    }
+
+    fn push_turn(history: &mut ChatHistory, user: &str, assistant: &str) {
+        history.push_user_message(user, &[]);
+        history.push_assistant_message(assistant);
+    }
+
+    #[test]
+    fn token_count_matches_the_sent_messages() {
+        let mut history = small_history();
+        push_turn(&mut history, "こんにちは", "やあ");
+        history.summary = Some("要約".to_string());
+
+        let expected: usize = history
+            .messages()
+            .iter()
+            .map(tokens::estimate_message_tokens)
+            .sum();
+        assert_eq!(history.context_usage().used_tokens, expected);
+    }
+
+    #[test]
+    fn trimming_drops_whole_oldest_turns_and_keeps_the_system_prompt() {
+        let mut history = small_history();
+        // 1ターン目はツール呼び出しとその結果を含む
+        history.push_user_message(&text(20), &[]);
+        history.push_tool_calls(
+            "",
+            vec![ToolCall {
+                id: "call_1".to_string(),
+                kind: "function".to_string(),
+                function: FunctionCall {
+                    name: "clock".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }],
+        );
+        history.push_tool_result("call_1", "12:00");
+        history.push_assistant_message(&text(20));
+        push_turn(&mut history, "二つ目", &text(20));
+        assert_eq!(history.messages().len(), 7);
+
+        // 3ターン目で予算を超えるので、1ターン目をまとめて破棄する
+        push_turn(&mut history, "三つ目", &text(20));
+
+        assert_eq!(
+            contents(&history.messages()),
+            vec![
+                ("system".to_string(), "sys".to_string()),
+                ("user".to_string(), "二つ目".to_string()),
+                ("assistant".to_string(), text(20)),
+                ("user".to_string(), "三つ目".to_string()),
+                ("assistant".to_string(), text(20)),
+            ]
+        );
+        let usage = history.context_usage();
+        assert!(usage.used_tokens <= usage.budget_tokens);
+    }
+
+    #[test]
+    fn summary_request_contains_the_trimmed_turns() {
+        let mut history = small_history();
+        history.set_summarize_dropped(true);
+        push_turn(&mut history, "最初の質問", &text(30));
+        push_turn(&mut history, "二つ目の質問", &text(30));
+        history.push_user_message("三つ目の質問", &[]);
+        assert!(history.needs_summary());
+
+        let request = history.summary_request().expect("trimmed turns");
+        let transcript = request.last().expect("transcript").content.text();
+        assert!(transcript.contains("user: 最初の質問"));
+        assert!(transcript.contains(&format!("assistant: {}", text(30))));
+        assert!(!transcript.contains("二つ目の質問"));
+        assert!(!transcript.contains("三つ目の質問"));
+
+        // 要約を反映すると、要約済みのターンは手放して要約を送る
+        history.apply_summary("質問");
+        assert!(!history.needs_summary());
+        let messages = contents(&history.messages());
+        assert_eq!(
+            messages[1],
+            (
+                "system".to_string(),
+                "Conversation so far:\n質問".to_string()
+            )
+        );
+        assert!(!messages.iter().any(|(_, content)| content == "最初の質問"));
+    }
+
+    #[test]
+    fn trimming_keeps_the_latest_turn_even_over_budget() {
+        let mut history = small_history();
+        push_turn(&mut history, "一つ目", "はい");
+        history.push_user_message(&text(200), &[]);
+
+        assert_eq!(
+            contents(&history.messages()),
+            vec![
+                ("system".to_string(), "sys".to_string()),
+                ("user".to_string(), text(200)),
+            ]
+        );
+    }
+}
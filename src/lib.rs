@@ -1,6 +1,22 @@
 pub mod app;
+pub mod attachment;
 pub mod audio;
+pub mod backend;
+pub mod cassette;
 pub mod config;
+pub mod error;
 pub mod features;
+pub mod generation;
+pub mod history;
+pub mod mcp;
+pub mod ollama;
 pub mod openai;
+pub mod retry;
 pub mod sound;
+pub mod sse;
+pub mod structured;
+pub mod summary;
+pub mod tokens;
+pub mod tools;
+pub mod usage;
+pub mod voice_params;
@@ -1,11 +1,22 @@
 use env_logger::{Builder, Target};
 use std::fs::OpenOptions;
 use voicevox_chat::app::run_chat_terminal;
+use voicevox_chat::cassette::HttpMode;
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
 
+    // `--record <dir>`・`--replay <dir>`でHTTP通信を記録・再生する
+    let http_mode = match HttpMode::from_args(std::env::args().skip(1)) {
+        Ok(mode) => mode,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("Usage: voicevox_chat [--record <dir> | --replay <dir>]");
+            std::process::exit(2);
+        }
+    };
+
     // Setup logging to file
     let log_file = OpenOptions::new()
         .create(true)
@@ -20,7 +31,7 @@ async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
 
     // チャットターミナルUIを起動
-    if let Err(e) = run_chat_terminal().await {
+    if let Err(e) = run_chat_terminal(http_mode).await {
         eprintln!("Error running terminal UI: {}", e);
     }
 }
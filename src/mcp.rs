@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+use crate::error::{Error, Result};
+use crate::tools::{Tool, ToolDefinition};
+
+/// 対応しているMCPのプロトコルバージョン
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// 1回のリクエストの応答を待つ時間
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 設定ファイルに記述するMCPサーバーの起動方法
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// MCPサーバーが公開しているツール
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "inputSchema", default = "empty_schema")]
+    pub input_schema: serde_json::Value,
+}
+
+fn empty_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<serde_json::Value>>>>>;
+
+/// 子プロセスとして起動したMCPサーバーとのJSON-RPC接続
+pub struct McpClient {
+    name: String,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    /// ドロップ時にプロセスを終了させるために保持する
+    _child: Child,
+}
+
+impl McpClient {
+    /// サーバーを起動し、初期化のハンドシェイクを行う
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<Arc<Self>> {
+        info!("Starting MCP server '{}': {}", name, config.command);
+
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                Error::Mcp(format!(
+                    "failed to start server '{}' ({}): {}",
+                    name, config.command, e
+                ))
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(read_responses(name.to_string(), stdout, pending.clone()));
+
+        // TUIを崩さないよう、サーバーの標準エラー出力はログに流す
+        let server_name = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!("[mcp:{}] {}", server_name, line);
+            }
+        });
+
+        let client = Arc::new(Self {
+            name: name.to_string(),
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending,
+            next_id: AtomicU64::new(1),
+            _child: child,
+        });
+
+        let init = client
+            .request(
+                "initialize",
+                serde_json::json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await?;
+        debug!("MCP server '{}' initialized: {}", name, init);
+        client
+            .notify("notifications/initialized", serde_json::json!({}))
+            .await?;
+
+        Ok(client)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// サーバーのツール一覧を取得する（ページングされていればすべて辿る）
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => serde_json::json!({ "cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+
+            let page: Vec<McpToolInfo> =
+                serde_json::from_value(result["tools"].clone()).map_err(|e| {
+                    Error::Mcp(format!("invalid tools/list from '{}': {}", self.name, e))
+                })?;
+            tools.extend(page);
+
+            cursor = result["nextCursor"].as_str().map(|s| s.to_string());
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// ツールを呼び出し、結果のテキストを返す
+    pub async fn call_tool(&self, name: &str, arguments: serde_json::Value) -> Result<String> {
+        let result = self
+            .request(
+                "tools/call",
+                serde_json::json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+
+        let text = result["content"]
+            .as_array()
+            .map(|content| {
+                content
+                    .iter()
+                    .map(|item| match item["type"].as_str() {
+                        Some("text") => item["text"].as_str().unwrap_or_default().to_string(),
+                        Some(other) => format!("[{} content]", other),
+                        None => item.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+
+        if result["isError"].as_bool().unwrap_or(false) {
+            return Err(Error::Mcp(format!("{} on '{}': {}", name, self.name, text)));
+        }
+        Ok(text)
+    }
+
+    async fn request(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .expect("pending requests lock poisoned")
+            .insert(id, tx);
+
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+        if let Err(e) = self.send(&message).await {
+            self.forget(id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(Error::Mcp(format!(
+                "server '{}' exited before answering {}",
+                self.name, method
+            ))),
+            Err(_) => {
+                self.forget(id);
+                Err(Error::Timeout(format!(
+                    "MCP server '{}' did not answer {} within {} seconds",
+                    self.name,
+                    method,
+                    REQUEST_TIMEOUT.as_secs()
+                )))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: serde_json::Value) -> Result<()> {
+        self.send(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))
+        .await
+    }
+
+    /// 1メッセージを改行区切りのJSONとして書き込む
+    async fn send(&self, message: &serde_json::Value) -> Result<()> {
+        let mut line = message.to_string();
+        line.push('\n');
+
+        let mut stdin = self.stdin.lock().await;
+        let write_error =
+            |e: std::io::Error| Error::Mcp(format!("failed to write to '{}': {}", self.name, e));
+        stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(write_error)?;
+        stdin.flush().await.map_err(write_error)
+    }
+
+    fn forget(&self, id: u64) {
+        self.pending
+            .lock()
+            .expect("pending requests lock poisoned")
+            .remove(&id);
+    }
+}
+
+/// 標準出力から応答を読み、対応するリクエストに届ける
+async fn read_responses(
+    name: String,
+    stdout: tokio::process::ChildStdout,
+    pending: PendingRequests,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                error!("Failed to read from MCP server '{}': {}", name, e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message: serde_json::Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!(
+                    "Ignoring invalid output from MCP server '{}': {} ({})",
+                    name, e, line
+                );
+                continue;
+            }
+        };
+
+        // サーバーからの通知・リクエストには対応しない
+        let Some(id) = message["id"].as_u64() else {
+            debug!("Ignoring message from MCP server '{}': {}", name, line);
+            continue;
+        };
+        if message.get("method").is_some() {
+            debug!("Ignoring request from MCP server '{}': {}", name, line);
+            continue;
+        }
+
+        let result = match message.get("error") {
+            Some(error) => Err(Error::Mcp(format!(
+                "server '{}' returned error {}: {}",
+                name,
+                error["code"],
+                error["message"].as_str().unwrap_or_default()
+            ))),
+            None => Ok(message["result"].clone()),
+        };
+
+        let sender = pending
+            .lock()
+            .expect("pending requests lock poisoned")
+            .remove(&id);
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(result);
+            }
+            None => warn!("Unexpected response id {} from MCP server '{}'", id, name),
+        }
+    }
+
+    info!("MCP server '{}' closed its output", name);
+    // 待機中のリクエストは送信側のドロップで失敗として扱われる
+    pending
+        .lock()
+        .expect("pending requests lock poisoned")
+        .clear();
+}
+
+/// MCPサーバーのツールをLLMから呼び出せるようにしたもの
+pub struct McpTool {
+    client: Arc<McpClient>,
+    info: McpToolInfo,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: McpToolInfo) -> Self {
+        Self { client, info }
+    }
+
+    /// LLMに公開する名前（サーバー間の衝突を避けるためサーバー名を前置する）
+    pub fn exposed_name(&self) -> String {
+        sanitize_tool_name(&format!("{}__{}", self.client.name(), self.info.name))
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.exposed_name(),
+            description: format!("[{}] {}", self.client.name(), self.info.description),
+            parameters: self.info.input_schema.clone(),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        self.client.call_tool(&self.info.name, arguments).await
+    }
+}
+
+/// Function callingのツール名に使えない文字を`_`に置き換える
+fn sanitize_tool_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect()
+}
+
+/// 設定されたMCPサーバーをすべて起動し、ツールを集める
+///
+/// 起動できなかったサーバーはエラーとして返し、残りのサーバーのツールは利用する
+pub async fn connect_servers(
+    servers: &HashMap<String, McpServerConfig>,
+) -> (Vec<Arc<dyn Tool>>, Vec<Error>) {
+    let connections = futures::future::join_all(servers.iter().map(|(name, config)| async move {
+        let client = McpClient::connect(name, config).await?;
+        let tools = client.list_tools().await?;
+        info!("MCP server '{}' provides {} tools", name, tools.len());
+        Ok::<_, Error>((client, tools))
+    }))
+    .await;
+
+    let mut tools: Vec<Arc<dyn Tool>> = Vec::new();
+    let mut errors = Vec::new();
+    for connection in connections {
+        match connection {
+            Ok((client, infos)) => {
+                for info in infos {
+                    tools.push(Arc::new(McpTool::new(client.clone(), info)));
+                }
+            }
+            Err(e) => {
+                error!("Failed to connect MCP server: {}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    (tools, errors)
+}
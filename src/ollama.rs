@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use log::{debug, error};
+use uuid::Uuid;
+
+use crate::attachment::ImageAttachment;
+use crate::backend::{
+    stream_with_retry, ChatBackend, Completion, StreamAttempt, StreamCallback, StreamEvent,
+};
+use crate::cassette::HttpClient;
+use crate::error::{Error, Result};
+use crate::generation::GenerationParams;
+use crate::history::{ChatHistory, ContextUsage, FunctionCall, Message, ToolCall};
+use crate::retry::{with_retry, RetryNotifier, RetryPolicy, RetryStatus};
+use crate::sse::LineDecoder;
+use crate::summary::SummarizationConfig;
+use crate::tokens;
+use crate::tools::{self, ToolDefinition};
+use crate::usage::TokenUsage;
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Ollama のネイティブ API（`/api/chat`）を使うバックエンド
+pub struct OllamaChat {
+    api_key: Option<String>,
+    base_url: String,
+    extra_headers: HashMap<String, String>,
+    client: Arc<HttpClient>,
+    model: String,
+    history: ChatHistory,
+    /// モデルごとのコンテキスト長の上書き
+    context_limits: HashMap<String, usize>,
+    /// `num_ctx`やサンプリングパラメータなど、`options`としてそのまま送る値
+    options: serde_json::Map<String, serde_json::Value>,
+    keep_alive: Option<serde_json::Value>,
+    /// 要約に使うモデル（`None`なら会話と同じモデル）
+    summary_model: Option<String>,
+    /// LLMに公開するツール
+    tools: Vec<ToolDefinition>,
+    /// 会話の応答に使う生成パラメータ（要約には使わない）
+    generation: GenerationParams,
+    /// 会話の応答に求めるJSON Schema（`format`として送る）
+    response_schema: Option<serde_json::Value>,
+    retry_policy: RetryPolicy,
+    /// ストリーミング以外のリクエストを再試行するときの通知先
+    retry_notifier: Option<RetryNotifier>,
+}
+
+impl OllamaChat {
+    pub fn new(client: Arc<HttpClient>) -> Self {
+        let mut ollama = Self {
+            api_key: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            extra_headers: HashMap::new(),
+            client,
+            model: "llama3.2".to_string(),
+            history: ChatHistory::new(),
+            context_limits: HashMap::new(),
+            options: serde_json::Map::new(),
+            keep_alive: None,
+            summary_model: None,
+            tools: vec![],
+            generation: GenerationParams::default(),
+            response_schema: None,
+            retry_policy: RetryPolicy::default(),
+            retry_notifier: None,
+        };
+        ollama.update_context_limit();
+        ollama
+    }
+
+    /// `num_ctx`が指定されていればそれを、なければモデル名からコンテキスト長を決める
+    fn update_context_limit(&mut self) {
+        let limit = match self.options.get("num_ctx").and_then(|v| v.as_u64()) {
+            Some(num_ctx) => num_ctx as usize,
+            None => tokens::context_limit(&self.model, &self.context_limits),
+        };
+        self.history.set_context_limit(limit);
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url.trim_end_matches('/'), path);
+        let mut request = self.client.request(method, url);
+
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        for (name, value) in &self.extra_headers {
+            request = request.header(name, value);
+        }
+
+        request
+    }
+
+    fn chat_body(&self, model: &str, messages: &[Message], stream: bool) -> serde_json::Value {
+        let messages = to_ollama_messages(messages);
+        let mut body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": stream,
+        });
+
+        if !self.options.is_empty() {
+            body["options"] = serde_json::Value::Object(self.options.clone());
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = keep_alive.clone();
+        }
+
+        body
+    }
+
+    /// 会話履歴とツール定義からリクエスト本文を組み立てる
+    fn conversation_body(&self, stream: bool) -> serde_json::Value {
+        let mut body = self.chat_body(&self.model, &self.history.messages(), stream);
+        let mut options = self.options.clone();
+        self.generation.apply_ollama(&mut options);
+        if !options.is_empty() {
+            body["options"] = serde_json::Value::Object(options);
+        }
+        if !self.tools.is_empty() {
+            body["tools"] = self.tools.iter().map(ToolDefinition::to_json).collect();
+        }
+        if let Some(schema) = &self.response_schema {
+            body["format"] = schema.clone();
+        }
+        body
+    }
+
+    async fn send_chat(&self, body: &serde_json::Value) -> Result<reqwest::Response> {
+        debug!(
+            "Sending Ollama chat request: {}",
+            serde_json::to_string(&body).unwrap_or_default()
+        );
+
+        let resp = self
+            .client
+            .send(self.request(reqwest::Method::POST, "api/chat").json(body))
+            .await
+            .map_err(|e| {
+                error!("Failed to send Ollama chat request: {}", e);
+                Error::from(e)
+            })?;
+
+        if !resp.status().is_success() {
+            let error = Error::from_response(resp).await;
+            error!("Ollama chat request failed: {}", error);
+            return Err(error);
+        }
+
+        Ok(resp)
+    }
+
+    pub async fn completion(&self) -> Result<Completion> {
+        self.complete_body(&self.conversation_body(false)).await
+    }
+
+    /// 任意のメッセージ列に対する応答本文を一括で取得する（ツールは使わない）
+    async fn complete_messages(&self, model: &str, messages: &[Message]) -> Result<String> {
+        let body = self.chat_body(model, messages, false);
+        Ok(self.complete_body(&body).await?.content)
+    }
+
+    fn notify_retry(&self, status: &RetryStatus) {
+        if let Some(notifier) = &self.retry_notifier {
+            notifier(status);
+        }
+    }
+
+    async fn complete_body(&self, body: &serde_json::Value) -> Result<Completion> {
+        let resp_json: serde_json::Value = with_retry(
+            &self.retry_policy,
+            || async { Ok(self.send_chat(body).await?.json().await?) },
+            |status| self.notify_retry(status),
+        )
+        .await?;
+
+        if let Some(api_error) = Error::from_error_json(&resp_json) {
+            return Err(api_error);
+        }
+
+        let mut completion = Completion {
+            content: resp_json["message"]["content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            tool_calls: vec![],
+            usage: parse_usage(&resp_json),
+        };
+        push_tool_calls(&mut completion.tool_calls, &resp_json["message"]);
+
+        Ok(completion)
+    }
+
+    pub async fn completion_stream(&self, callback: &mut StreamCallback<'_>) -> Result<Completion> {
+        stream_with_retry(self, &self.retry_policy, callback).await
+    }
+
+    pub async fn summarize_history(&mut self) -> Result<()> {
+        let Some(request) = self.history.summary_request() else {
+            return Ok(());
+        };
+
+        let model = self.summary_model.as_deref().unwrap_or(&self.model);
+        let summary = self.complete_messages(model, &request).await?;
+        self.history.apply_summary(&summary);
+
+        Ok(())
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let resp_json: serde_json::Value = with_retry(
+            &self.retry_policy,
+            || async {
+                let resp = self
+                    .client
+                    .send(self.request(reqwest::Method::GET, "api/tags"))
+                    .await?;
+                if !resp.status().is_success() {
+                    return Err(Error::from_response(resp).await);
+                }
+                Ok(resp.json().await?)
+            },
+            |status| self.notify_retry(status),
+        )
+        .await?;
+
+        let mut models: Vec<String> = resp_json["models"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|model| model["name"].as_str().map(|name| name.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        models.sort();
+
+        Ok(models)
+    }
+}
+
+#[async_trait]
+impl StreamAttempt for OllamaChat {
+    async fn stream_once(
+        &self,
+        callback: &mut StreamCallback<'_>,
+        completion: &mut Completion,
+    ) -> Result<()> {
+        let body = self.conversation_body(true);
+        let resp = self.send_chat(&body).await?;
+
+        let mut bytes_stream = resp.bytes_stream();
+        // NDJSONの1行分が揃うまでバイト列のまま保持する（UTF-8境界対策）
+        let mut lines = LineDecoder::new();
+        let idle_timeout = self.retry_policy.idle_timeout();
+
+        loop {
+            // 一定時間データが届かなければ接続が止まったとみなす
+            let chunk = match tokio::time::timeout(idle_timeout, bytes_stream.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(_) => {
+                    error!("No data received from Ollama for {:?}", idle_timeout);
+                    return Err(Error::Timeout(format!(
+                        "no data received for {} seconds",
+                        idle_timeout.as_secs()
+                    )));
+                }
+            };
+            let bytes = chunk.map_err(|e| {
+                error!("Error reading Ollama stream: {}", e);
+                Error::from(e)
+            })?;
+
+            for line in lines.push(&bytes) {
+                if apply_stream_line(&line, callback, completion)? {
+                    return Ok(());
+                }
+            }
+        }
+
+        // 改行で終わらずに切断された最後の行
+        if let Some(line) = lines.finish() {
+            apply_stream_line(&line, callback, completion)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaChat {
+    fn set_model(&mut self, model: &str) {
+        self.model(model);
+    }
+
+    fn current_model(&self) -> &str {
+        &self.model
+    }
+
+    fn push_system_message(&mut self, prompt: &str) {
+        self.history.push_system_message(prompt);
+    }
+
+    fn set_system_prompt(&mut self, prompt: &str) {
+        self.history.set_system_prompt(prompt);
+    }
+
+    fn reset_history(&mut self) {
+        self.history.clear();
+    }
+
+    fn last_reply(&self) -> Vec<Message> {
+        self.history.last_reply()
+    }
+
+    fn load_history(&mut self, messages: Vec<Message>) {
+        self.history.load(messages);
+    }
+
+    fn push_user_message(&mut self, input: &str, images: &[ImageAttachment]) {
+        self.history.push_user_message(input, images);
+    }
+
+    fn push_assistant_message(&mut self, input: &str) {
+        self.history.push_assistant_message(input);
+    }
+
+    fn push_truncated_assistant_message(&mut self, input: &str) {
+        self.history.push_truncated_assistant_message(input);
+    }
+
+    fn push_tool_calls(&mut self, content: &str, tool_calls: Vec<ToolCall>) {
+        self.history.push_tool_calls(content, tool_calls);
+    }
+
+    fn push_tool_result(&mut self, tool_call_id: &str, result: &str) {
+        self.history.push_tool_result(tool_call_id, result);
+    }
+
+    fn set_tools(&mut self, tools: Vec<ToolDefinition>) {
+        self.tools(tools);
+    }
+
+    fn set_retry_notifier(&mut self, notifier: RetryNotifier) {
+        self.retry_notifier(notifier);
+    }
+
+    fn set_generation_params(&mut self, params: GenerationParams) {
+        self.generation_params(params);
+    }
+
+    fn context_usage(&self) -> ContextUsage {
+        self.history.context_usage()
+    }
+
+    fn messages(&self) -> Vec<Message> {
+        self.history.messages()
+    }
+
+    async fn completion(&self) -> Result<Completion> {
+        OllamaChat::completion(self).await
+    }
+
+    async fn completion_stream(&self, callback: &mut StreamCallback<'_>) -> Result<Completion> {
+        OllamaChat::completion_stream(self, callback).await
+    }
+
+    fn needs_summary(&self) -> bool {
+        self.history.needs_summary()
+    }
+
+    async fn summarize_history(&mut self) -> Result<()> {
+        OllamaChat::summarize_history(self).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        OllamaChat::list_models(self).await
+    }
+}
+
+impl OllamaChat {
+    pub fn api_key(&mut self, api_key: &str) -> &mut Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn base_url(&mut self, base_url: &str) -> &mut Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.extra_headers
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn model(&mut self, model_name: &str) -> &mut Self {
+        self.model = model_name.to_string();
+        self.update_context_limit();
+        self
+    }
+
+    pub fn option(&mut self, name: &str, value: serde_json::Value) -> &mut Self {
+        self.options.insert(name.to_string(), value);
+        self.update_context_limit();
+        self
+    }
+
+    pub fn keep_alive(&mut self, keep_alive: serde_json::Value) -> &mut Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    pub fn context_limits(&mut self, context_limits: HashMap<String, usize>) -> &mut Self {
+        self.context_limits = context_limits;
+        self.update_context_limit();
+        self
+    }
+
+    pub fn summarization(&mut self, config: &SummarizationConfig) -> &mut Self {
+        self.history.set_summarize_dropped(config.enabled);
+        self.summary_model = config.model.clone();
+        self
+    }
+
+    pub fn tools(&mut self, tools: Vec<ToolDefinition>) -> &mut Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn generation_params(&mut self, params: GenerationParams) -> &mut Self {
+        self.generation = params;
+        self
+    }
+
+    pub fn response_schema(&mut self, schema: serde_json::Value) -> &mut Self {
+        self.response_schema = Some(schema);
+        self
+    }
+
+    pub fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn retry_notifier(&mut self, notifier: RetryNotifier) -> &mut Self {
+        self.retry_notifier = Some(notifier);
+        self
+    }
+}
+
+/// NDJSONの1行を読み、本文・思考過程・ツール呼び出しを反映する（最後の行なら`true`）
+fn apply_stream_line(
+    line: &str,
+    callback: &mut StreamCallback<'_>,
+    completion: &mut Completion,
+) -> Result<bool> {
+    if line.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let json = serde_json::from_str::<serde_json::Value>(line).map_err(|e| {
+        error!("Failed to parse Ollama stream line: {} ({})", e, line);
+        Error::SseParse(format!("{} for line: {}", e, line))
+    })?;
+
+    if let Some(api_error) = Error::from_error_json(&json) {
+        error!("Error in Ollama stream: {}", api_error);
+        return Err(api_error);
+    }
+
+    // 思考モデルは本文の前に`thinking`を返す
+    if let Some(thinking) = json["message"]["thinking"].as_str() {
+        if !thinking.is_empty() {
+            callback(StreamEvent::Reasoning(thinking));
+        }
+    }
+    if let Some(content) = json["message"]["content"].as_str() {
+        if !content.is_empty() {
+            callback(StreamEvent::Content(content));
+            completion.content.push_str(content);
+        }
+    }
+    // Ollamaはツール呼び出しを分割せず1行にまとめて返す
+    push_tool_calls(&mut completion.tool_calls, &json["message"]);
+
+    if json["done"].as_bool().unwrap_or(false) {
+        completion.usage = parse_usage(&json);
+        debug!(
+            "Ollama stream finished: done_reason={}",
+            json["done_reason"].as_str().unwrap_or("unknown")
+        );
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// 履歴のメッセージ列をOllamaの形式に変換する
+///
+/// Ollamaはツールの実行結果を呼び出しIDではなくツール名で対応付けるので、結果に`tool_name`を付ける
+fn to_ollama_messages(messages: &[Message]) -> Vec<serde_json::Value> {
+    let mut tool_names: HashMap<&str, &str> = HashMap::new();
+    messages
+        .iter()
+        .map(|message| {
+            for call in &message.tool_calls {
+                tool_names.insert(&call.id, &call.function.name);
+            }
+            let tool_name = message
+                .tool_call_id
+                .as_deref()
+                .and_then(|id| tool_names.get(id).copied());
+            to_ollama_message(message, tool_name)
+        })
+        .collect()
+}
+
+/// 履歴のメッセージをOllamaの形式に変換する（ツール引数は文字列ではなくオブジェクトで送る）
+fn to_ollama_message(message: &Message, tool_name: Option<&str>) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "role": message.role,
+        "content": message.content.text(),
+    });
+    if let Some(tool_name) = tool_name {
+        value["tool_name"] = tool_name.into();
+    }
+
+    // Ollamaは画像をdata URLではなくBase64の配列で受け取る
+    let images: Vec<&str> = message
+        .content
+        .image_urls()
+        .into_iter()
+        .filter_map(|url| url.split_once("base64,").map(|(_, data)| data))
+        .collect();
+    if !images.is_empty() {
+        value["images"] = images.into();
+    }
+
+    if !message.tool_calls.is_empty() {
+        value["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                serde_json::json!({
+                    "function": {
+                        "name": call.function.name,
+                        "arguments": tools::parse_arguments(&call.function.arguments)
+                            .unwrap_or_default(),
+                    }
+                })
+            })
+            .collect();
+    }
+
+    value
+}
+
+/// `message.tool_calls`を読み取り、IDを振って追加する
+///
+/// OllamaはIDを返さないので、UIが呼び出しを区別できるよう会話全体で重複しないIDを振る
+fn push_tool_calls(tool_calls: &mut Vec<ToolCall>, message: &serde_json::Value) {
+    let Some(calls) = message["tool_calls"].as_array() else {
+        return;
+    };
+
+    for call in calls {
+        let Some(name) = call["function"]["name"].as_str() else {
+            continue;
+        };
+        tool_calls.push(ToolCall {
+            id: format!("call_{}", Uuid::new_v4().simple()),
+            kind: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: call["function"]["arguments"].to_string(),
+            },
+        });
+    }
+}
+
+/// 最終行の`prompt_eval_count`・`eval_count`を読み取る
+fn parse_usage(json: &serde_json::Value) -> Option<TokenUsage> {
+    let prompt_tokens = json["prompt_eval_count"].as_u64();
+    let completion_tokens = json["eval_count"].as_u64();
+    if prompt_tokens.is_none() && completion_tokens.is_none() {
+        return None;
+    }
+
+    Some(TokenUsage {
+        prompt_tokens: prompt_tokens.unwrap_or_default(),
+        completion_tokens: completion_tokens.unwrap_or_default(),
+        reasoning_tokens: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 区切りの位置を変えても同じ結果になることを確かめるチャンクサイズ
+    const CHUNK_SIZES: [usize; 5] = [1, 2, 3, 7, usize::MAX];
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path: std::path::PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "sse", name]
+                .iter()
+                .collect();
+        std::fs::read(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+    }
+
+    /// `chunk_size`バイトずつデコードした各行を反映し、最後の行で止める
+    fn stream(bytes: &[u8], chunk_size: usize) -> (Completion, Vec<String>, bool) {
+        let mut decoder = LineDecoder::new();
+        let mut lines: Vec<String> = bytes
+            .chunks(chunk_size.min(bytes.len()).max(1))
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        lines.extend(decoder.finish());
+
+        let mut completion = Completion::default();
+        let mut events = Vec::new();
+        let mut callback = |event: StreamEvent<'_>| match event {
+            StreamEvent::Content(content) => events.push(format!("content:{}", content)),
+            StreamEvent::Reasoning(reasoning) => events.push(format!("reasoning:{}", reasoning)),
+            StreamEvent::Retrying(_) => {}
+        };
+        let mut done = false;
+        for line in &lines {
+            done = apply_stream_line(line, &mut callback, &mut completion).unwrap();
+            if done {
+                break;
+            }
+        }
+        (completion, events, done)
+    }
+
+    #[test]
+    fn reads_tool_calls_from_the_final_line() {
+        let bytes = fixture("ollama_tool_calls.ndjson");
+
+        for chunk_size in CHUNK_SIZES {
+            let (completion, events, done) = stream(&bytes, chunk_size);
+
+            assert!(done, "{}-byte chunks", chunk_size);
+            assert_eq!(
+                events,
+                ["reasoning:天気と時刻を調べる", "content:調べるのだ"],
+                "{}-byte chunks",
+                chunk_size
+            );
+            assert_eq!(completion.content, "調べるのだ");
+
+            let calls: Vec<(&str, &str, &str)> = completion
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    (
+                        call.kind.as_str(),
+                        call.function.name.as_str(),
+                        call.function.arguments.as_str(),
+                    )
+                })
+                .collect();
+            // 引数はオブジェクトで届くが、履歴にはJSON文字列として残す
+            assert_eq!(
+                calls,
+                [
+                    ("function", "get_weather", r#"{"city":"東北"}"#),
+                    ("function", "current_time", r#"{"zone":"Asia/Tokyo"}"#),
+                ]
+            );
+            assert!(completion
+                .tool_calls
+                .iter()
+                .all(|call| call.id.starts_with("call_")));
+            assert_ne!(completion.tool_calls[0].id, completion.tool_calls[1].id);
+
+            assert_eq!(
+                completion.usage,
+                Some(TokenUsage {
+                    prompt_tokens: 42,
+                    completion_tokens: 17,
+                    reasoning_tokens: 0,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn tool_call_ids_are_unique_across_rounds() {
+        let line = r#"{"message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"get_weather","arguments":{}}}]},"done":true}"#;
+        let mut first = Completion::default();
+        let mut second = Completion::default();
+        apply_stream_line(line, &mut |_| {}, &mut first).unwrap();
+        apply_stream_line(line, &mut |_| {}, &mut second).unwrap();
+
+        assert_ne!(first.tool_calls[0].id, second.tool_calls[0].id);
+    }
+
+    #[test]
+    fn skips_blank_lines_and_keeps_streaming() {
+        let mut completion = Completion::default();
+        assert!(!apply_stream_line("", &mut |_| {}, &mut completion).unwrap());
+        assert!(!apply_stream_line(
+            r#"{"message":{"role":"assistant","content":"の"},"done":false}"#,
+            &mut |_| {},
+            &mut completion
+        )
+        .unwrap());
+        assert_eq!(completion.content, "の");
+        assert!(completion.usage.is_none());
+    }
+}
@@ -1,294 +1,673 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use colored::Colorize;
+use async_trait::async_trait;
 use futures::StreamExt;
-use serde::Serialize;
+use log::{debug, error};
+
+use crate::attachment::ImageAttachment;
+use crate::backend::{
+    stream_with_retry, ChatBackend, Completion, StreamAttempt, StreamCallback, StreamEvent,
+};
+use crate::cassette::HttpClient;
+use crate::error::{Error, Result};
+use crate::generation::GenerationParams;
+use crate::history::{ChatHistory, ContextUsage, Message, ToolCall};
+use crate::retry::{with_retry, RetryNotifier, RetryPolicy, RetryStatus};
+use crate::sse::{SseDecoder, SseEvent};
+use crate::summary::SummarizationConfig;
+use crate::tokens;
+use crate::tools::ToolDefinition;
+use crate::usage::TokenUsage;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
 pub struct ChatCompletion {
-    api_key: String,
-    client: Arc<reqwest::Client>,
+    api_key: Option<String>,
+    base_url: String,
+    extra_headers: HashMap<String, String>,
+    client: Arc<HttpClient>,
     model: String,
-    log_size: u32,
-    system_messages: Vec<Message>,
-    chat_messages: Vec<Message>,
-}
-
-#[derive(Serialize, Clone)]
-pub struct Message {
-    pub role: String,
-    pub content: String,
+    history: ChatHistory,
+    /// モデルごとのコンテキスト長の上書き
+    context_limits: HashMap<String, usize>,
+    /// 要約に使うモデル（`None`なら会話と同じモデル）
+    summary_model: Option<String>,
+    /// LLMに公開するツール
+    tools: Vec<ToolDefinition>,
+    /// 会話の応答に使う生成パラメータ（要約には使わない）
+    generation: GenerationParams,
+    /// 会話の応答に求めるJSON Schema（要約には使わない）
+    response_schema: Option<serde_json::Value>,
+    retry_policy: RetryPolicy,
+    /// ストリーミング以外のリクエストを再試行するときの通知先
+    retry_notifier: Option<RetryNotifier>,
 }
 
 impl ChatCompletion {
-    pub fn new(api_key: String, client: Arc<reqwest::Client>) -> Self {
+    pub fn new(api_key: Option<String>, client: Arc<HttpClient>) -> Self {
         let model = std::env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-5-nano".to_string());
 
-        Self {
+        let mut chat_completion = Self {
             api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            extra_headers: HashMap::new(),
             client,
             model: model.clone(),
-            log_size: 30,
-            system_messages: vec![],
-            chat_messages: vec![],
-        }
+            history: ChatHistory::new(),
+            context_limits: HashMap::new(),
+            summary_model: None,
+            tools: vec![],
+            generation: GenerationParams::default(),
+            response_schema: None,
+            retry_policy: RetryPolicy::default(),
+            retry_notifier: None,
+        };
+        chat_completion.update_context_limit();
+        chat_completion
     }
 
     pub fn push_system_message(&mut self, prompt: &str) {
-        let system_message = Message {
-            role: "system".into(),
-            content: prompt.into(),
-        };
+        self.history.push_system_message(prompt);
+    }
 
-        self.system_messages.push(system_message);
+    pub fn push_user_message(&mut self, input: &str, images: &[ImageAttachment]) {
+        self.history.push_user_message(input, images);
     }
 
-    fn push_chat_message(&mut self, role: &str, input: &str) {
-        if self.chat_messages.len() > self.log_size as usize {
-            self.chat_messages.remove(0);
-        }
+    pub fn push_assistant_message(&mut self, input: &str) {
+        self.history.push_assistant_message(input);
+    }
 
-        self.chat_messages.push(Message {
-            role: role.into(),
-            content: input.into(),
-        });
+    pub fn push_truncated_assistant_message(&mut self, input: &str) {
+        self.history.push_truncated_assistant_message(input);
     }
 
-    pub fn push_user_message(&mut self, input: &str) {
-        self.push_chat_message("user", input);
+    pub fn push_tool_calls(&mut self, content: &str, tool_calls: Vec<ToolCall>) {
+        self.history.push_tool_calls(content, tool_calls);
     }
 
-    pub fn push_assistant_message(&mut self, input: &str) {
-        self.push_chat_message("assistant", input);
+    pub fn push_tool_result(&mut self, tool_call_id: &str, result: &str) {
+        self.history.push_tool_result(tool_call_id, result);
     }
 
     pub fn messages(&self) -> Vec<Message> {
-        self.system_messages
-            .iter()
-            .cloned()
-            .chain(self.chat_messages.clone())
-            .collect()
+        self.history.messages()
     }
 
-    pub async fn completion(&self) -> Result<String, reqwest::Error> {
-        let body = serde_json::json!({
+    fn update_context_limit(&mut self) {
+        self.history
+            .set_context_limit(tokens::context_limit(&self.model, &self.context_limits));
+    }
+
+    /// ベースURLに対するリクエストを認証ヘッダーと追加ヘッダー付きで組み立てる
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url.trim_end_matches('/'), path);
+        let mut request = self.client.request(method, url);
+
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        for (name, value) in &self.extra_headers {
+            request = request.header(name, value);
+        }
+
+        request
+    }
+
+    /// 会話履歴とツール定義からリクエスト本文を組み立てる
+    fn chat_body(&self, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
           "model": self.model,
           "messages": self.messages(),
         });
 
+        if !self.tools.is_empty() {
+            body["tools"] = self.tools.iter().map(ToolDefinition::to_json).collect();
+        }
+        self.generation.apply_openai(&self.model, &mut body);
+        if let Some(schema) = &self.response_schema {
+            body["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "reply", "strict": true, "schema": schema },
+            });
+        }
+        if stream {
+            body["stream"] = serde_json::Value::Bool(true);
+            // 最後のチャンクでトークン数を受け取る
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+
+        body
+    }
+
+    /// リクエストを送信し、エラーステータスの場合はAPIエラーとして返す
+    async fn send(&self, body: &serde_json::Value) -> Result<reqwest::Response> {
+        debug!(
+            "Sending chat completion request: {}",
+            serde_json::to_string(body).unwrap_or_default()
+        );
+
         let resp = self
             .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
-            .send()
-            .await;
-
-        let resp = match resp {
-            // レスポンスのステータスコードを確認
-            Ok(response) => match response.error_for_status() {
-                Ok(valid_response) => valid_response,
-                Err(e) => {
-                    eprintln!(
-                        "{}",
-                        format!(
-                            "Request body: {}",
-                            serde_json::to_string_pretty(&body).unwrap_or_default()
-                        )
-                        .yellow()
-                    );
-                    eprintln!("{}", format!("Error in response status: {}", e).red());
-                    return Err(e);
-                }
-            },
-            Err(e) => {
-                eprintln!(
-                    "{}",
-                    format!(
-                        "Request body: {}",
-                        serde_json::to_string_pretty(&body).unwrap_or_default()
-                    )
-                    .yellow()
-                );
-                eprintln!("{}", format!("Error sending request: {}", e).red());
-                return Err(e);
-            }
-        };
+            .send(
+                self.request(reqwest::Method::POST, "chat/completions")
+                    .json(body),
+            )
+            .await
+            .map_err(|e| {
+                error!("Error sending request: {}", e);
+                Error::from(e)
+            })?;
+
+        if !resp.status().is_success() {
+            let error = Error::from_response(resp).await;
+            error!("Error in response status: {}", error);
+            return Err(error);
+        }
 
-        let resp_json: serde_json::Value = resp.json().await?;
-        let choices = resp_json["choices"]
-            .as_array()
-            .expect("choices is not an array");
-        let text = choices[0]["message"]["content"]
-            .as_str()
-            .expect("content is not a string");
+        Ok(resp)
+    }
 
-        Ok(text.to_string())
+    pub async fn completion(&self) -> Result<Completion> {
+        self.complete_body(&self.chat_body(false)).await
     }
 
-    pub async fn completion_stream<F>(
-        &self,
-        mut callback: F,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>>
-    where
-        F: FnMut(&str),
-    {
+    /// 任意のメッセージ列に対する応答本文を一括で取得する（ツールは使わない）
+    async fn complete_messages(&self, model: &str, messages: &[Message]) -> Result<String> {
         let body = serde_json::json!({
-          "model": self.model,
-          "messages": self.messages(),
-          "stream": true,
+          "model": model,
+          "messages": messages,
         });
 
-        let resp = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&body)
-            .send()
-            .await;
-
-        let resp = match resp {
-            Ok(response) => match response.error_for_status() {
-                Ok(valid_response) => valid_response,
-                Err(e) => {
-                    eprintln!(
-                        "{}",
-                        format!(
-                            "Request body: {}",
-                            serde_json::to_string_pretty(&body).unwrap_or_default()
-                        )
-                        .yellow()
-                    );
-                    eprintln!("{}", format!("Error in response status: {}", e).red());
-                    return Err(Box::new(e));
-                }
-            },
-            Err(e) => {
-                eprintln!(
-                    "{}",
-                    format!(
-                        "Request body: {}",
-                        serde_json::to_string_pretty(&body).unwrap_or_default()
-                    )
-                    .yellow()
-                );
-                eprintln!("{}", format!("Error sending request: {}", e).red());
-                return Err(Box::new(e));
+        Ok(self.complete_body(&body).await?.content)
+    }
+
+    fn notify_retry(&self, status: &RetryStatus) {
+        if let Some(notifier) = &self.retry_notifier {
+            notifier(status);
+        }
+    }
+
+    async fn complete_body(&self, body: &serde_json::Value) -> Result<Completion> {
+        let resp_json: serde_json::Value = with_retry(
+            &self.retry_policy,
+            || async { Ok(self.send(body).await?.json().await?) },
+            |status| self.notify_retry(status),
+        )
+        .await?;
+
+        let message = &resp_json["choices"][0]["message"];
+        let tool_calls: Vec<ToolCall> = match message.get("tool_calls") {
+            Some(tool_calls) if !tool_calls.is_null() => serde_json::from_value(tool_calls.clone())
+                .map_err(|e| {
+                    Error::InvalidResponse(format!("invalid choices[0].message.tool_calls: {}", e))
+                })?,
+            _ => vec![],
+        };
+        // ツール呼び出しのみの応答では本文がnullになる
+        let content = match message["content"].as_str() {
+            Some(text) => text.to_string(),
+            None if !tool_calls.is_empty() => String::new(),
+            None => {
+                return Err(Error::InvalidResponse(
+                    "choices[0].message.content is not a string".to_string(),
+                ))
             }
         };
 
-        let mut full_content = String::new();
+        Ok(Completion {
+            content,
+            tool_calls,
+            usage: parse_usage(&resp_json),
+        })
+    }
+
+    pub async fn completion_stream(&self, callback: &mut StreamCallback<'_>) -> Result<Completion> {
+        stream_with_retry(self, &self.retry_policy, callback).await
+    }
+
+    pub async fn summarize_history(&mut self) -> Result<()> {
+        let Some(request) = self.history.summary_request() else {
+            return Ok(());
+        };
+
+        let model = self.summary_model.as_deref().unwrap_or(&self.model);
+        let summary = self.complete_messages(model, &request).await?;
+        self.history.apply_summary(&summary);
+
+        Ok(())
+    }
+
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let resp_json: serde_json::Value = with_retry(
+            &self.retry_policy,
+            || async {
+                let resp = self
+                    .client
+                    .send(self.request(reqwest::Method::GET, "models"))
+                    .await?;
+                if !resp.status().is_success() {
+                    return Err(Error::from_response(resp).await);
+                }
+                Ok(resp.json().await?)
+            },
+            |status| self.notify_retry(status),
+        )
+        .await?;
+
+        let mut models: Vec<String> = resp_json["data"]
+            .as_array()
+            .map(|data| {
+                data.iter()
+                    .filter_map(|model| model["id"].as_str().map(|id| id.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        models.sort();
+
+        Ok(models)
+    }
+}
+
+#[async_trait]
+impl StreamAttempt for ChatCompletion {
+    async fn stream_once(
+        &self,
+        callback: &mut StreamCallback<'_>,
+        completion: &mut Completion,
+    ) -> Result<()> {
+        let body = self.chat_body(true);
+        let resp = self.send(&body).await?;
+
         let mut bytes_stream = resp.bytes_stream();
-        let mut buffer = String::new();
-        let mut byte_buffer = Vec::new(); // UTF-8バイトバッファ
-
-        while let Some(chunk) = bytes_stream.next().await {
-            match chunk {
-                Ok(bytes) => {
-                    // バイトバッファに追加
-                    byte_buffer.extend_from_slice(&bytes);
-
-                    // UTF-8文字列として変換を試行
-                    let chunk_str = match std::str::from_utf8(&byte_buffer) {
-                        Ok(valid_str) => {
-                            // 完全な文字列なので、バッファをクリアして使用
-                            let result = valid_str.to_string();
-                            byte_buffer.clear();
-                            result
-                        }
-                        Err(utf8_error) => {
-                            // 不完全なUTF-8バイト列の場合
-                            let valid_up_to = utf8_error.valid_up_to();
-                            if valid_up_to == 0 {
-                                // 先頭から無効な場合、次のチャンクを待つ
-                                continue;
-                            }
-
-                            // 有効な部分を取得
-                            let valid_str = std::str::from_utf8(&byte_buffer[..valid_up_to])
-                                .unwrap()
-                                .to_string();
-
-                            // 不完全な部分を残す
-                            let remaining_bytes = byte_buffer[valid_up_to..].to_vec();
-                            byte_buffer = remaining_bytes;
-
-                            valid_str
-                        }
-                    };
-
-                    buffer.push_str(&chunk_str);
-
-                    // Process complete lines
-                    while let Some(newline_pos) = buffer.find('\n') {
-                        let line = buffer.drain(..=newline_pos).collect::<String>();
-                        let line = line.trim();
-
-                        if let Some(data) = line.strip_prefix("data: ") {
-                            if data == "[DONE]" {
-                                break;
-                            }
-
-                            // Parse JSON chunk
-                            match serde_json::from_str::<serde_json::Value>(data) {
-                                Ok(json) => {
-                                    if let Some(choices) =
-                                        json.get("choices").and_then(|c| c.as_array())
-                                    {
-                                        if let Some(choice) = choices.first() {
-                                            if let Some(delta) =
-                                                choice.get("delta").and_then(|d| d.as_object())
-                                            {
-                                                if let Some(content) =
-                                                    delta.get("content").and_then(|c| c.as_str())
-                                                {
-                                                    if !content.is_empty() {
-                                                        callback(content);
-                                                        full_content.push_str(content);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!(
-                                        "{}",
-                                        format!("JSON parse error: {} for data: {}", e, data).red()
-                                    );
-                                }
-                            }
-                        }
-                    }
+        let mut decoder = SseDecoder::new();
+        let idle_timeout = self.retry_policy.idle_timeout();
+
+        loop {
+            // 一定時間データが届かなければ接続が止まったとみなす
+            let chunk = match tokio::time::timeout(idle_timeout, bytes_stream.next()).await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(_) => {
+                    error!("No data received for {:?}", idle_timeout);
+                    return Err(Error::Timeout(format!(
+                        "no data received for {} seconds",
+                        idle_timeout.as_secs()
+                    )));
                 }
-                Err(e) => {
-                    eprintln!("{}", format!("Error reading stream: {}", e).red());
-                    return Err(Box::new(e));
+            };
+            let bytes = chunk.map_err(|e| {
+                error!("Error reading stream: {}", e);
+                Error::from(e)
+            })?;
+
+            for event in decoder.push(&bytes) {
+                if event.is_done() {
+                    return Ok(());
                 }
+                apply_stream_event(&event, callback, completion)?;
             }
         }
 
-        Ok(full_content)
+        // 空行で閉じられずに切断された最後のイベント
+        if let Some(event) = decoder.finish().filter(|event| !event.is_done()) {
+            apply_stream_event(&event, callback, completion)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChatBackend for ChatCompletion {
+    fn set_model(&mut self, model: &str) {
+        self.model(model);
+    }
+
+    fn current_model(&self) -> &str {
+        &self.model
+    }
+
+    fn push_system_message(&mut self, prompt: &str) {
+        ChatCompletion::push_system_message(self, prompt);
+    }
+
+    fn set_system_prompt(&mut self, prompt: &str) {
+        self.history.set_system_prompt(prompt);
+    }
+
+    fn reset_history(&mut self) {
+        self.history.clear();
+    }
+
+    fn last_reply(&self) -> Vec<Message> {
+        self.history.last_reply()
+    }
+
+    fn load_history(&mut self, messages: Vec<Message>) {
+        self.history.load(messages);
+    }
+
+    fn push_user_message(&mut self, input: &str, images: &[ImageAttachment]) {
+        ChatCompletion::push_user_message(self, input, images);
+    }
+
+    fn push_assistant_message(&mut self, input: &str) {
+        ChatCompletion::push_assistant_message(self, input);
+    }
+
+    fn push_truncated_assistant_message(&mut self, input: &str) {
+        ChatCompletion::push_truncated_assistant_message(self, input);
+    }
+
+    fn push_tool_calls(&mut self, content: &str, tool_calls: Vec<ToolCall>) {
+        ChatCompletion::push_tool_calls(self, content, tool_calls);
+    }
+
+    fn push_tool_result(&mut self, tool_call_id: &str, result: &str) {
+        ChatCompletion::push_tool_result(self, tool_call_id, result);
+    }
+
+    fn set_tools(&mut self, tools: Vec<ToolDefinition>) {
+        self.tools(tools);
+    }
+
+    fn set_retry_notifier(&mut self, notifier: RetryNotifier) {
+        self.retry_notifier(notifier);
+    }
+
+    fn set_generation_params(&mut self, params: GenerationParams) {
+        self.generation_params(params);
+    }
+
+    fn context_usage(&self) -> ContextUsage {
+        self.history.context_usage()
+    }
+
+    fn messages(&self) -> Vec<Message> {
+        ChatCompletion::messages(self)
+    }
+
+    fn needs_summary(&self) -> bool {
+        self.history.needs_summary()
+    }
+
+    async fn summarize_history(&mut self) -> Result<()> {
+        ChatCompletion::summarize_history(self).await
+    }
+
+    async fn completion(&self) -> Result<Completion> {
+        ChatCompletion::completion(self).await
+    }
+
+    async fn completion_stream(&self, callback: &mut StreamCallback<'_>) -> Result<Completion> {
+        ChatCompletion::completion_stream(self, callback).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        ChatCompletion::list_models(self).await
     }
 }
 
 impl ChatCompletion {
     pub fn api_key(&mut self, api_key: &str) -> &mut Self {
-        self.api_key = api_key.to_string();
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn base_url(&mut self, base_url: &str) -> &mut Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.extra_headers
+            .insert(name.to_string(), value.to_string());
         self
     }
 
     pub fn model(&mut self, model_name: &str) -> &mut Self {
         self.model = model_name.to_string();
+        self.update_context_limit();
         self
     }
 
-    pub fn client(&mut self, client: Arc<reqwest::Client>) -> &mut Self {
+    pub fn client(&mut self, client: Arc<HttpClient>) -> &mut Self {
         self.client = client;
         self
     }
 
-    pub fn log_size(&mut self, size: u32) -> &mut Self {
-        self.log_size = size;
+    pub fn context_limits(&mut self, context_limits: HashMap<String, usize>) -> &mut Self {
+        self.context_limits = context_limits;
+        self.update_context_limit();
+        self
+    }
+
+    pub fn summarization(&mut self, config: &SummarizationConfig) -> &mut Self {
+        self.history.set_summarize_dropped(config.enabled);
+        self.summary_model = config.model.clone();
+        self
+    }
+
+    pub fn tools(&mut self, tools: Vec<ToolDefinition>) -> &mut Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn generation_params(&mut self, params: GenerationParams) -> &mut Self {
+        self.generation = params;
+        self
+    }
+
+    pub fn response_schema(&mut self, schema: serde_json::Value) -> &mut Self {
+        self.response_schema = Some(schema);
+        self
+    }
+
+    pub fn retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn retry_notifier(&mut self, notifier: RetryNotifier) -> &mut Self {
+        self.retry_notifier = Some(notifier);
         self
     }
 }
+
+/// SSEの1イベント分のチャンクを読み、本文・思考過程・ツール呼び出し・トークン数を反映する
+fn apply_stream_event(
+    event: &SseEvent,
+    callback: &mut StreamCallback<'_>,
+    completion: &mut Completion,
+) -> Result<()> {
+    let json = serde_json::from_str::<serde_json::Value>(&event.data).map_err(|e| {
+        error!("JSON parse error: {} for data: {}", e, event.data);
+        Error::SseParse(format!("{} for data: {}", e, event.data))
+    })?;
+
+    // ストリーム途中で返されたエラーオブジェクト（`event: error`で届くこともある）
+    if let Some(api_error) = Error::from_error_json(&json) {
+        error!("Error in stream: {}", api_error);
+        return Err(api_error);
+    }
+    if event.event.as_deref() == Some("error") {
+        error!("Error event in stream: {}", event.data);
+        return Err(Error::InvalidResponse(format!(
+            "error event in stream: {}",
+            event.data
+        )));
+    }
+
+    if let Some(usage) = parse_usage(&json) {
+        completion.usage = Some(usage);
+    }
+
+    let delta = &json["choices"][0]["delta"];
+    // 互換APIによってフィールド名が異なる
+    let reasoning = delta["reasoning_content"]
+        .as_str()
+        .or_else(|| delta["reasoning"].as_str());
+    if let Some(reasoning) = reasoning.filter(|text| !text.is_empty()) {
+        callback(StreamEvent::Reasoning(reasoning));
+    }
+    if let Some(content) = delta["content"].as_str() {
+        if !content.is_empty() {
+            callback(StreamEvent::Content(content));
+            completion.content.push_str(content);
+        }
+    }
+    if let Some(tool_call_deltas) = delta["tool_calls"].as_array() {
+        for tool_call_delta in tool_call_deltas {
+            merge_tool_call_delta(&mut completion.tool_calls, tool_call_delta);
+        }
+    }
+
+    Ok(())
+}
+
+/// ストリーミングで分割されて届くツール呼び出しを`index`ごとに連結する
+fn merge_tool_call_delta(tool_calls: &mut Vec<ToolCall>, delta: &serde_json::Value) {
+    let index = delta["index"]
+        .as_u64()
+        .map(|index| index as usize)
+        .unwrap_or(tool_calls.len().saturating_sub(1));
+
+    while tool_calls.len() <= index {
+        tool_calls.push(ToolCall {
+            kind: "function".to_string(),
+            ..ToolCall::default()
+        });
+    }
+
+    let tool_call = &mut tool_calls[index];
+    if let Some(id) = delta["id"].as_str() {
+        tool_call.id = id.to_string();
+    }
+    if let Some(name) = delta["function"]["name"].as_str() {
+        tool_call.function.name.push_str(name);
+    }
+    if let Some(arguments) = delta["function"]["arguments"].as_str() {
+        tool_call.function.arguments.push_str(arguments);
+    }
+}
+
+/// `usage`を読み取る（ストリーミングでは最後のチャンクにだけ含まれる）
+fn parse_usage(json: &serde_json::Value) -> Option<TokenUsage> {
+    let usage = json.get("usage").filter(|usage| !usage.is_null())?;
+    let count = |value: &serde_json::Value| value.as_u64().unwrap_or_default();
+
+    Some(TokenUsage {
+        prompt_tokens: count(&usage["prompt_tokens"]),
+        completion_tokens: count(&usage["completion_tokens"]),
+        reasoning_tokens: count(&usage["completion_tokens_details"]["reasoning_tokens"]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 区切りの位置を変えても同じ結果になることを確かめるチャンクサイズ
+    const CHUNK_SIZES: [usize; 5] = [1, 2, 3, 7, usize::MAX];
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path: std::path::PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "sse", name]
+                .iter()
+                .collect();
+        std::fs::read(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+    }
+
+    /// `chunk_size`バイトずつデコードし、`[DONE]`までのイベントを反映した応答を返す
+    fn stream(bytes: &[u8], chunk_size: usize) -> (Completion, Vec<String>) {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = bytes
+            .chunks(chunk_size.min(bytes.len()).max(1))
+            .flat_map(|chunk| decoder.push(chunk))
+            .collect();
+        events.extend(decoder.finish());
+
+        let mut completion = Completion::default();
+        let mut contents = Vec::new();
+        let mut callback = |event: StreamEvent<'_>| {
+            if let StreamEvent::Content(content) = event {
+                contents.push(content.to_string());
+            }
+        };
+        for event in events.iter().take_while(|event| !event.is_done()) {
+            apply_stream_event(event, &mut callback, &mut completion).unwrap();
+        }
+        (completion, contents)
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            kind: "function".to_string(),
+            function: crate::history::FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn merges_parallel_tool_calls_split_across_chunks() {
+        let bytes = fixture("openai_tool_calls.sse");
+
+        for chunk_size in CHUNK_SIZES {
+            let (completion, contents) = stream(&bytes, chunk_size);
+
+            assert_eq!(contents, ["調べるのだ"], "{}-byte chunks", chunk_size);
+            assert_eq!(completion.content, "調べるのだ");
+            // `index`ごとに引数を連結し、`index`のない断片は直前の呼び出しに続ける
+            assert_eq!(
+                completion.tool_calls,
+                [
+                    tool_call("call_weather", "get_weather", r#"{"city":"東北"}"#),
+                    tool_call("call_time", "current_time", r#"{"zone":"Asia/Tokyo"}"#),
+                ],
+                "{}-byte chunks",
+                chunk_size
+            );
+            assert_eq!(
+                completion.usage,
+                Some(TokenUsage {
+                    prompt_tokens: 42,
+                    completion_tokens: 17,
+                    reasoning_tokens: 0,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn delta_without_index_starts_the_first_call() {
+        let mut tool_calls = Vec::new();
+        merge_tool_call_delta(
+            &mut tool_calls,
+            &serde_json::json!({"id": "call_1", "function": {"name": "get_", "arguments": "{"}}),
+        );
+        merge_tool_call_delta(
+            &mut tool_calls,
+            &serde_json::json!({"function": {"name": "weather", "arguments": "}"}}),
+        );
+
+        assert_eq!(tool_calls, [tool_call("call_1", "get_weather", "{}")]);
+    }
+
+    #[test]
+    fn pads_calls_when_an_index_is_skipped() {
+        let mut tool_calls = Vec::new();
+        merge_tool_call_delta(
+            &mut tool_calls,
+            &serde_json::json!({"index": 1, "id": "call_2", "function": {"name": "current_time"}}),
+        );
+
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[0].kind, "function");
+        assert_eq!(tool_calls[1], tool_call("call_2", "current_time", ""));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorCategory, Result};
+
+/// 一時的な失敗に対する再試行の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最初の試行を除いた再試行回数の上限
+    pub max_retries: u32,
+    /// 1回目の再試行までの基準待機時間
+    pub base_delay_ms: u64,
+    /// 待機時間の上限（`Retry-After`の指定もこの上限で打ち切る）
+    pub max_delay_ms: u64,
+    /// ストリーミング中に受信が途絶えたとみなすまでの時間
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            idle_timeout_secs: 60,
+        }
+    }
+}
+
+/// 再試行の状況（UI表示用）
+#[derive(Debug, Clone)]
+pub struct RetryStatus {
+    /// これから行う再試行の番号（1始まり）
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay: Duration,
+    pub error: Error,
+}
+
+/// 再試行の状況を受け取る通知先
+pub type RetryNotifier = Arc<dyn Fn(&RetryStatus) + Send + Sync>;
+
+impl RetryPolicy {
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+
+    /// `retries_done`回再試行済みの状態で`error`が起きたときの待機時間
+    ///
+    /// 再試行すべきでない場合は`None`を返す
+    fn next_delay(&self, retries_done: u32, error: &Error) -> Option<Duration> {
+        if retries_done >= self.max_retries || !is_retryable(error) {
+            return None;
+        }
+
+        // 極端に長い`Retry-After`で応答が止まったように見えないよう上限で打ち切る
+        if let Some(retry_after) = error.retry_after() {
+            return Some(retry_after.min(Duration::from_millis(self.max_delay_ms)));
+        }
+
+        // 指数バックオフ + ジッター（待機時間の半分〜全体の範囲でばらつかせる）
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << retries_done.min(20));
+        let capped = exponential.min(self.max_delay_ms);
+        let jittered = capped / 2 + (capped as f64 / 2.0 * jitter_fraction()) as u64;
+
+        Some(Duration::from_millis(jittered))
+    }
+
+    /// 次の再試行の状況を返す（再試行すべきでない場合は`None`）
+    pub fn next_retry(&self, retries_done: u32, error: &Error) -> Option<RetryStatus> {
+        self.next_delay(retries_done, error)
+            .map(|delay| RetryStatus {
+                attempt: retries_done + 1,
+                max_retries: self.max_retries,
+                delay,
+                error: error.clone(),
+            })
+    }
+}
+
+/// 一時的な失敗であれば待機してから`operation`をやり直す
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    mut operation: F,
+    mut on_retry: impl FnMut(&RetryStatus),
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries_done = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) => match policy.next_retry(retries_done, &e) {
+                Some(status) => {
+                    warn!(
+                        "Retrying ({}/{}) in {:?} after error: {}",
+                        status.attempt, status.max_retries, status.delay, e
+                    );
+                    on_retry(&status);
+                    tokio::time::sleep(status.delay).await;
+                    retries_done += 1;
+                }
+                None => return Err(e),
+            },
+        }
+    }
+}
+
+/// 時間をおけば成功する見込みのあるエラーか
+pub fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::Network(_) | Error::Timeout(_) | Error::EngineUnreachable(_) => true,
+        Error::HttpStatus { .. } | Error::Api { .. } => {
+            matches!(
+                error.category(),
+                ErrorCategory::RateLimited | ErrorCategory::ServerError
+            ) || error.status() == Some(408)
+        }
+        _ => false,
+    }
+}
+
+/// 0.0〜1.0の疑似乱数
+fn jitter_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
@@ -2,6 +2,17 @@ use std::{io, sync::mpsc as std_mpsc, thread};
 
 use log::{debug, error, info};
 
+use crate::error::{Error, Result};
+
+/// 再生スレッドへの指示
+#[derive(Debug)]
+pub enum AudioCommand {
+    /// WAVデータを再生待ちの音声の後ろに追加する
+    Play(Vec<u8>),
+    /// 再生中・再生待ちの音声を破棄する
+    Stop,
+}
+
 pub struct Player {
     _stream: rodio::OutputStream,
     sink: rodio::Sink,
@@ -14,19 +25,19 @@ impl Default for Player {
 }
 
 impl Player {
-    pub fn new() -> Result<Self, String> {
+    pub fn new() -> Result<Self> {
         debug!("Initializing audio output device");
 
         let (_stream, stream_handle) = rodio::OutputStream::try_default().map_err(|e| {
             error!("Failed to get default output stream: {}", e);
-            format!("Failed to get default output stream: {}", e)
+            Error::AudioDevice(format!("Failed to get default output stream: {}", e))
         })?;
 
         info!("Successfully initialized audio output stream");
 
         let sink = rodio::Sink::try_new(&stream_handle).map_err(|e| {
             error!("Failed to create audio sink: {}", e);
-            format!("Failed to create sink: {}", e)
+            Error::AudioDevice(format!("Failed to create sink: {}", e))
         })?;
 
         info!("Successfully created audio sink");
@@ -34,23 +45,20 @@ impl Player {
         Ok(Player { _stream, sink })
     }
 
-    pub fn play(&self, bytes: Vec<u8>) -> Result<(), String> {
+    /// 再生中の音声の後ろに追加して再生する
+    pub fn play(&self, bytes: Vec<u8>) -> Result<()> {
         debug!(
-            "Starting audio playback with {} bytes of WAV data",
+            "Queueing audio playback with {} bytes of WAV data",
             bytes.len()
         );
 
         let sink = &self.sink;
 
-        debug!("Stopping and clearing previous audio");
-        sink.stop();
-        sink.clear();
-
         debug!("Creating audio decoder from WAV data");
         let cursor = io::Cursor::new(bytes);
         let source = rodio::Decoder::new(cursor).map_err(|e| {
             error!("Failed to create audio decoder: {}", e);
-            format!("Failed to create decoder: {}", e)
+            Error::AudioDevice(format!("Failed to create decoder: {}", e))
         })?;
 
         debug!("Adding audio source to sink");
@@ -59,13 +67,24 @@ impl Player {
         debug!("Starting audio playback");
         sink.play();
 
-        info!("Audio playback started successfully");
+        info!("Audio source queued ({} pending)", sink.len());
         Ok(())
     }
+
+    pub fn stop(&self) {
+        debug!("Stopping audio playback");
+        self.sink.stop();
+        self.sink.clear();
+    }
 }
 
-pub fn start_audio_loop() -> std_mpsc::Sender<Vec<u8>> {
-    let (tx, rx) = std_mpsc::channel::<Vec<u8>>();
+/// 再生スレッドを起動する
+///
+/// 再生スレッドで発生したエラーは`error_tx`に送られる
+pub fn start_audio_loop(
+    error_tx: tokio::sync::mpsc::UnboundedSender<Error>,
+) -> std_mpsc::Sender<AudioCommand> {
+    let (tx, rx) = std_mpsc::channel::<AudioCommand>();
 
     thread::spawn(move || {
         info!("Starting audio playback thread");
@@ -77,19 +96,28 @@ pub fn start_audio_loop() -> std_mpsc::Sender<Vec<u8>> {
             }
             Err(e) => {
                 error!("Failed to create audio player: {}", e);
+                let _ = error_tx.send(e);
                 return;
             }
         };
 
-        while let Ok(wav_data) = rx.recv() {
-            debug!("Received audio playback request ({} bytes)", wav_data.len());
-
-            match player.play(wav_data) {
-                Ok(_) => {
-                    info!("Audio playback completed successfully");
+        while let Ok(command) = rx.recv() {
+            match command {
+                AudioCommand::Play(wav_data) => {
+                    debug!("Received audio playback request ({} bytes)", wav_data.len());
+
+                    match player.play(wav_data) {
+                        Ok(_) => {
+                            info!("Audio playback completed successfully");
+                        }
+                        Err(e) => {
+                            error!("Audio playback failed: {}", e);
+                            let _ = error_tx.send(e);
+                        }
+                    }
                 }
-                Err(e) => {
-                    error!("Audio playback failed: {}", e);
+                AudioCommand::Stop => {
+                    player.stop();
                 }
             }
         }
@@ -0,0 +1,159 @@
+/// OpenAI互換APIがストリームの終わりに送る`data`
+pub const DONE: &str = "[DONE]";
+
+/// 1件のイベント
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// `event:`フィールド（省略時は`None`）
+    pub event: Option<String>,
+    /// `data:`フィールド（複数行は改行で連結する）
+    pub data: String,
+    /// `id:`フィールド
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// ストリームの終わりを示すイベントか
+    pub fn is_done(&self) -> bool {
+        self.data == DONE
+    }
+}
+
+/// バイト列を行に分割する（`\n`・`\r\n`・`\r`のいずれの改行にも対応する）
+///
+/// NDJSONなど、SSE以外の行単位のストリームにも使う
+#[derive(Debug, Clone, Default)]
+pub struct LineDecoder {
+    buffer: Vec<u8>,
+    /// 直前のチャンクが`\r`で終わった（次の`\n`は同じ改行の一部）
+    pending_cr: bool,
+}
+
+impl LineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// チャンクを追加し、揃った行を改行を除いて返す
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            if std::mem::take(&mut self.pending_cr) && byte == b'\n' {
+                continue;
+            }
+            match byte {
+                b'\n' => lines.push(self.take_line()),
+                b'\r' => {
+                    lines.push(self.take_line());
+                    self.pending_cr = true;
+                }
+                byte => self.buffer.push(byte),
+            }
+        }
+        lines
+    }
+
+    /// ストリームの終わりで、改行で終わっていない最後の行を返す
+    pub fn finish(&mut self) -> Option<String> {
+        self.pending_cr = false;
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(self.take_line())
+        }
+    }
+
+    fn take_line(&mut self) -> String {
+        let line = std::mem::take(&mut self.buffer);
+        // 行の境界はASCIIなので、完全な行は正しいUTF-8のはず（不正なバイトは置き換える）
+        match String::from_utf8(line) {
+            Ok(line) => line,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        }
+    }
+}
+
+/// Server-Sent Eventsのストリームをイベントに分解する
+///
+/// 届いたバイト列を順に渡すと、揃ったイベントを返す。
+/// チャンクの境界が行やUTF-8の文字の途中にあっても、完全な行が揃うまでバイト列のまま保持する
+#[derive(Debug, Clone, Default)]
+pub struct SseDecoder {
+    lines: LineDecoder,
+    /// 先頭のBOMを確認したか
+    started: bool,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// チャンクを追加し、空行で区切られて揃ったイベントを返す
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for line in self.lines.push(bytes) {
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// ストリームの終わりで、空行で閉じられていない最後のイベントを返す
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if let Some(line) = self.lines.finish() {
+            if let Some(event) = self.process_line(line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, mut line: String) -> Option<SseEvent> {
+        if !self.started {
+            self.started = true;
+            if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                line = stripped.to_string();
+            }
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // `:`で始まる行はコメント（接続維持用など）
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            // `retry`と未知のフィールドは使わない
+            _ => {}
+        }
+        None
+    }
+
+    /// 溜めたフィールドをイベントとして取り出す（`data`がなければ捨てる）
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let id = self.id.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(SseEvent {
+            event,
+            data: std::mem::take(&mut self.data).join("\n"),
+            id,
+        })
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// 感情の候補を設定しなかったときにモデルに選ばせる感情
+const DEFAULT_EMOTIONS: [&str; 5] = ["neutral", "happy", "sad", "angry", "surprised"];
+
+/// 応答をJSONで受け取り、感情と話者のスタイルで読み上げの声を切り替える設定
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StructuredOutputConfig {
+    pub enabled: bool,
+    /// モデルに選ばせる感情（空なら既定の候補）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub emotions: Vec<String>,
+    /// `speaker_style`または感情の名前とVOICEVOXのスタイルIDの対応
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub styles: HashMap<String, u32>,
+}
+
+impl StructuredOutputConfig {
+    /// 有効な場合に応答に求めるJSON Schema
+    pub fn schema(&self) -> Option<serde_json::Value> {
+        if !self.enabled {
+            return None;
+        }
+
+        let emotions: Vec<&str> = if self.emotions.is_empty() {
+            DEFAULT_EMOTIONS.to_vec()
+        } else {
+            self.emotions.iter().map(String::as_str).collect()
+        };
+        let mut styles: Vec<&str> = self.styles.keys().map(String::as_str).collect();
+        styles.sort();
+
+        let mut speaker_style = serde_json::json!({
+            "type": "string",
+            "description": "Voice style used to read the reply aloud",
+        });
+        if !styles.is_empty() {
+            speaker_style["enum"] = styles.into();
+        }
+
+        Some(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "description": "The reply shown to the user and read aloud",
+                },
+                "emotion": {
+                    "type": "string",
+                    "enum": emotions,
+                },
+                "speaker_style": speaker_style,
+            },
+            "required": ["text", "emotion", "speaker_style"],
+            "additionalProperties": false,
+        }))
+    }
+
+    /// 応答に合うスタイルID（`speaker_style`を優先し、なければ感情で選ぶ）
+    pub fn style_id(&self, reply: &StructuredReply) -> Option<u32> {
+        [&reply.speaker_style, &reply.emotion]
+            .into_iter()
+            .flatten()
+            .find_map(|name| self.styles.get(name).copied())
+    }
+}
+
+/// JSONで受け取った応答
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct StructuredReply {
+    pub text: String,
+    #[serde(default)]
+    pub emotion: Option<String>,
+    #[serde(default)]
+    pub speaker_style: Option<String>,
+}
+
+impl StructuredReply {
+    /// 応答の全文を読み取る（JSONでなければ`None`）
+    pub fn parse(content: &str) -> Option<Self> {
+        serde_json::from_str(content.trim()).ok()
+    }
+}
+
+/// `TextFieldExtractor`が読んでいる文字列の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StringRole {
+    Key,
+    Text,
+    Other,
+}
+
+/// 読んでいるエスケープシーケンス
+#[derive(Debug, Clone)]
+enum Escape {
+    Start,
+    Unicode(String),
+}
+
+/// ストリーミング中のJSONから最上位の`text`フィールドの値だけを取り出す
+///
+/// チャンクの境界がエスケープシーケンスや文字列の途中にあっても、
+/// 受け取った分までの本文を順に返す。先頭が`{`でなければJSONではないとみなしてそのまま返す
+#[derive(Debug, Clone)]
+pub struct TextFieldExtractor {
+    field: &'static str,
+    started: bool,
+    passthrough: bool,
+    depth: usize,
+    expecting_key: bool,
+    /// 直前に読んだ最上位のキー
+    key: String,
+    /// 値を読んでいる最上位のキー
+    value_key: Option<String>,
+    string: Option<StringRole>,
+    escape: Option<Escape>,
+    /// サロゲートペアの前半
+    high_surrogate: Option<u32>,
+}
+
+impl Default for TextFieldExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextFieldExtractor {
+    pub fn new() -> Self {
+        Self {
+            field: "text",
+            started: false,
+            passthrough: false,
+            depth: 0,
+            expecting_key: false,
+            key: String::new(),
+            value_key: None,
+            string: None,
+            escape: None,
+            high_surrogate: None,
+        }
+    }
+
+    /// チャンクを読み進め、新たに得られた`text`の本文を返す
+    pub fn push(&mut self, chunk: &str) -> String {
+        let mut text = String::new();
+        for c in chunk.chars() {
+            self.push_char(c, &mut text);
+        }
+        text
+    }
+
+    fn push_char(&mut self, c: char, text: &mut String) {
+        if self.passthrough {
+            text.push(c);
+            return;
+        }
+        if !self.started {
+            if c.is_whitespace() {
+                return;
+            }
+            self.started = true;
+            if c == '{' {
+                self.depth = 1;
+                self.expecting_key = true;
+            } else {
+                self.passthrough = true;
+                text.push(c);
+            }
+            return;
+        }
+
+        if let Some(role) = self.string {
+            self.push_string_char(role, c, text);
+            return;
+        }
+
+        match c {
+            '"' => {
+                let role = if self.depth != 1 {
+                    StringRole::Other
+                } else if self.expecting_key {
+                    self.key.clear();
+                    StringRole::Key
+                } else if self.value_key.as_deref() == Some(self.field) {
+                    StringRole::Text
+                } else {
+                    StringRole::Other
+                };
+                self.string = Some(role);
+            }
+            ':' if self.depth == 1 => {
+                self.expecting_key = false;
+                self.value_key = Some(std::mem::take(&mut self.key));
+            }
+            ',' if self.depth == 1 => {
+                self.expecting_key = true;
+                self.value_key = None;
+            }
+            '{' | '[' => self.depth += 1,
+            '}' | ']' => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    fn push_string_char(&mut self, role: StringRole, c: char, text: &mut String) {
+        let decoded = match self.escape.take() {
+            Some(Escape::Start) => match c {
+                'n' => Some('\n'),
+                't' => Some('\t'),
+                'r' => Some('\r'),
+                'b' => Some('\u{8}'),
+                'f' => Some('\u{c}'),
+                'u' => {
+                    self.escape = Some(Escape::Unicode(String::new()));
+                    None
+                }
+                c => Some(c),
+            },
+            Some(Escape::Unicode(mut hex)) => {
+                hex.push(c);
+                if hex.len() < 4 {
+                    self.escape = Some(Escape::Unicode(hex));
+                    None
+                } else {
+                    self.decode_unicode(&hex)
+                }
+            }
+            None => match c {
+                '\\' => {
+                    self.escape = Some(Escape::Start);
+                    None
+                }
+                '"' => {
+                    self.string = None;
+                    None
+                }
+                c => Some(c),
+            },
+        };
+
+        if let Some(c) = decoded {
+            match role {
+                StringRole::Key => self.key.push(c),
+                StringRole::Text => text.push(c),
+                StringRole::Other => {}
+            }
+        }
+    }
+
+    /// `\uXXXX`を文字にする（サロゲートペアは後半が届くまで待つ）
+    fn decode_unicode(&mut self, hex: &str) -> Option<char> {
+        let code = u32::from_str_radix(hex, 16).ok()?;
+        match code {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(code);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                char::from_u32(0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00))
+            }
+            code => char::from_u32(code),
+        }
+    }
+}
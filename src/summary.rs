@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::history::Message;
+
+/// 要約を依頼するときのシステムプロンプト
+const SUMMARY_PROMPT: &str = "You maintain the memory of a long-running conversation. \
+Merge the previous summary and the new transcript into one concise summary in the \
+language of the conversation. Keep facts about the user (name, preferences, plans), \
+promises made by the assistant and any open topics. Write at most 200 words and \
+output only the summary.";
+
+/// 古いターンを破棄する代わりに要約して残す設定
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummarizationConfig {
+    pub enabled: bool,
+    /// 要約に使うモデル（省略時は会話と同じモデル）
+    pub model: Option<String>,
+}
+
+/// 以前の要約と破棄されたターンから要約用のリクエストを組み立てる
+pub fn build_summary_request(previous_summary: Option<&str>, dropped: &[Message]) -> Vec<Message> {
+    let mut transcript = String::new();
+    if let Some(summary) = previous_summary {
+        transcript.push_str("Previous summary:\n");
+        transcript.push_str(summary);
+        transcript.push_str("\n\n");
+    }
+
+    transcript.push_str("New transcript:\n");
+    for message in dropped {
+        transcript.push_str(&format!("{}: {}", message.role, message.content.text()));
+        // 要約用のリクエストには画像を含めない
+        for _ in message.content.image_urls() {
+            transcript.push_str(" [image]");
+        }
+        transcript.push('\n');
+    }
+
+    vec![
+        Message::new("system", SUMMARY_PROMPT),
+        Message::new("user", &transcript),
+    ]
+}
+
+/// 履歴に差し込む「これまでの会話」のシステムメッセージ
+pub fn summary_message(summary: &str) -> Message {
+    Message::new("system", &format!("Conversation so far:\n{}", summary))
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::history::Message;
+
+/// メッセージごとに付く役割やフォーマット分のトークン数
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// 添付画像1枚分のトークン数（高解像度の画像を想定した概算）
+const IMAGE_TOKENS: usize = 765;
+
+/// モデルが不明な場合のコンテキスト長（ローカルモデルを想定して控えめにする）
+pub const DEFAULT_CONTEXT_LIMIT: usize = 8_192;
+
+/// テキストのトークン数を概算する
+///
+/// 英数字は約4文字で1トークン、日本語などの非ASCII文字は1文字1トークンとして数える。
+/// 実際のトークナイザーより多めに見積もる方向に寄せている
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut ascii_run: usize = 0;
+
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            if ch.is_ascii_whitespace() {
+                tokens += ascii_run.div_ceil(4);
+                ascii_run = 0;
+            } else {
+                ascii_run += 1;
+            }
+        } else {
+            tokens += ascii_run.div_ceil(4);
+            ascii_run = 0;
+            tokens += 1;
+        }
+    }
+
+    tokens + ascii_run.div_ceil(4)
+}
+
+/// 1メッセージ分のトークン数を概算する
+pub fn estimate_message_tokens(message: &Message) -> usize {
+    let tool_call_tokens: usize = message
+        .tool_calls
+        .iter()
+        .map(|call| {
+            estimate_tokens(&call.function.name) + estimate_tokens(&call.function.arguments)
+        })
+        .sum();
+
+    let image_tokens = message.content.image_urls().len() * IMAGE_TOKENS;
+
+    MESSAGE_OVERHEAD_TOKENS
+        + estimate_tokens(&message.content.text())
+        + image_tokens
+        + tool_call_tokens
+}
+
+/// モデル名からコンテキスト長を求める
+///
+/// `overrides`に登録されたモデルはその値を優先する
+pub fn context_limit(model: &str, overrides: &HashMap<String, usize>) -> usize {
+    if let Some(limit) = overrides.get(model) {
+        return *limit;
+    }
+
+    let model = model.to_ascii_lowercase();
+    if model.starts_with("gpt-5") {
+        400_000
+    } else if model.starts_with("gpt-4.1") {
+        1_047_576
+    } else if model.starts_with("gpt-4o") || model.starts_with("gpt-4-turbo") {
+        128_000
+    } else if model.starts_with("o1") || model.starts_with("o3") || model.starts_with("o4") {
+        200_000
+    } else if model.starts_with("gpt-3.5") {
+        16_385
+    } else {
+        DEFAULT_CONTEXT_LIMIT
+    }
+}
+
+/// 応答の生成用に空けておくトークン数
+pub fn response_reserve(context_limit: usize) -> usize {
+    (context_limit / 4).min(16_384)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_words_count_about_four_characters_per_token() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hi"), 1);
+        assert_eq!(estimate_tokens("word"), 1);
+        assert_eq!(estimate_tokens("words"), 2);
+        assert_eq!(estimate_tokens("hello world"), 4);
+        // 空白の連続はトークンに数えない
+        assert_eq!(estimate_tokens("  a   b  "), 2);
+    }
+
+    #[test]
+    fn non_ascii_characters_count_one_token_each() {
+        assert_eq!(estimate_tokens("こんにちは"), 5);
+        assert_eq!(estimate_tokens("絵文字🎉"), 4);
+    }
+
+    #[test]
+    fn mixed_text_splits_ascii_runs_at_non_ascii_characters() {
+        // "GPT" + "を" + "使う" + "API" + "。"
+        assert_eq!(estimate_tokens("GPTを使うAPI。"), 1 + 1 + 2 + 1 + 1);
+    }
+
+    #[test]
+    fn message_tokens_include_overhead_images_and_tool_calls() {
+        let message = Message::new("user", "こんにちは");
+        assert_eq!(
+            estimate_message_tokens(&message),
+            MESSAGE_OVERHEAD_TOKENS + 5
+        );
+
+        let mut message = Message::new("assistant", "");
+        message.tool_calls.push(crate::history::ToolCall {
+            function: crate::history::FunctionCall {
+                name: "clock".to_string(),
+                arguments: "{}".to_string(),
+            },
+            ..Default::default()
+        });
+        assert_eq!(
+            estimate_message_tokens(&message),
+            MESSAGE_OVERHEAD_TOKENS + 2 + 1
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::error::{Error, Result};
+use crate::history::ToolCall;
+
+pub mod builtin;
+
+/// ツールを実行する前にユーザーへ確認するかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPermission {
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
+/// 確認ダイアログでのユーザーの回答
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionDecision {
+    AllowOnce,
+    AlwaysAllow,
+    DenyOnce,
+    AlwaysDeny,
+}
+
+impl PermissionDecision {
+    pub fn is_allowed(&self) -> bool {
+        matches!(
+            self,
+            PermissionDecision::AllowOnce | PermissionDecision::AlwaysAllow
+        )
+    }
+
+    /// 設定に保存する許可設定（今回限りの回答は`None`）
+    pub fn persistent_permission(&self) -> Option<ToolPermission> {
+        match self {
+            PermissionDecision::AlwaysAllow => Some(ToolPermission::Allow),
+            PermissionDecision::AlwaysDeny => Some(ToolPermission::Deny),
+            PermissionDecision::AllowOnce | PermissionDecision::DenyOnce => None,
+        }
+    }
+}
+
+/// ツール実行の確認要求（UIが回答を送り返す）
+#[derive(Debug, Clone)]
+pub struct PermissionRequest {
+    pub tool_name: String,
+    pub arguments: String,
+    responder: Arc<Mutex<Option<oneshot::Sender<PermissionDecision>>>>,
+}
+
+impl PermissionRequest {
+    pub fn new(tool_name: &str, arguments: &str) -> (Self, oneshot::Receiver<PermissionDecision>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            tool_name: tool_name.to_string(),
+            arguments: arguments.to_string(),
+            responder: Arc::new(Mutex::new(Some(tx))),
+        };
+        (request, rx)
+    }
+
+    /// 回答を送る（2回目以降の呼び出しは無視される）
+    pub fn respond(&self, decision: PermissionDecision) {
+        if let Some(tx) = self.responder.lock().ok().and_then(|mut tx| tx.take()) {
+            let _ = tx.send(decision);
+        }
+    }
+}
+
+/// ツール関連の設定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// 組み込みツールをLLMに公開するか
+    pub enabled: bool,
+    /// `read_file`・`list_directory`で参照を許可するディレクトリ
+    pub allowed_dirs: Vec<PathBuf>,
+    /// ツールごとの許可設定（未指定は`ask`）
+    pub permissions: HashMap<String, ToolPermission>,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            allowed_dirs: vec![],
+            permissions: HashMap::new(),
+        }
+    }
+}
+
+impl ToolsConfig {
+    pub fn permission(&self, tool_name: &str) -> ToolPermission {
+        self.permissions.get(tool_name).copied().unwrap_or_default()
+    }
+}
+
+/// LLMに公開するツールの定義
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// 引数のJSON Schema
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    /// Chat Completions / Ollama の`tools`に渡す形式
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
+}
+
+/// LLMから呼び出せるツール
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn definition(&self) -> ToolDefinition;
+
+    /// ツールを実行し、LLMに返す結果の文字列を返す
+    async fn call(&self, arguments: serde_json::Value) -> Result<String>;
+}
+
+/// ChatWorkerが呼び出しを振り分けるツールの一覧
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ツールを登録する（同名のツールは置き換える）
+    pub fn register(&mut self, tool: Arc<dyn Tool>) -> &mut Self {
+        let name = tool.definition().name;
+        self.tools.retain(|t| t.definition().name != name);
+        self.tools.push(tool);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools
+            .iter()
+            .find(|tool| tool.definition().name == name)
+            .cloned()
+    }
+
+    /// ツール呼び出しを実行する
+    ///
+    /// 失敗した場合もLLMが状況を把握できるよう、エラー内容を結果として返す
+    pub async fn call(&self, call: &ToolCall) -> String {
+        info!(
+            "Calling tool '{}' with {}",
+            call.function.name, call.function.arguments
+        );
+
+        match self.try_call(call).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Tool '{}' failed: {}", call.function.name, e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    async fn try_call(&self, call: &ToolCall) -> Result<String> {
+        let tool = self
+            .get(&call.function.name)
+            .ok_or_else(|| Error::Tool(format!("unknown tool '{}'", call.function.name)))?;
+
+        tool.call(parse_arguments(&call.function.arguments)?).await
+    }
+}
+
+/// 引数の文字列をJSONとして読み取る（空の場合は空オブジェクトとみなす）
+pub fn parse_arguments(arguments: &str) -> Result<serde_json::Value> {
+    if arguments.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(arguments)
+        .map_err(|e| Error::Tool(format!("invalid arguments {}: {}", arguments, e)))
+}
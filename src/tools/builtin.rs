@@ -0,0 +1,676 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{info, warn};
+use tokio::sync::mpsc;
+
+use super::{Tool, ToolDefinition, ToolRegistry, ToolsConfig};
+use crate::error::{Error, Result};
+
+/// `read_file`で返す最大バイト数
+const MAX_READ_BYTES: usize = 64 * 1024;
+
+/// `calculate`で許す括弧や単項演算子の入れ子の深さ（深い再帰でスタックを使い果たさないため）
+const MAX_EXPRESSION_DEPTH: usize = 64;
+
+/// `set_timer`で指定できる最大秒数（24時間）
+const MAX_TIMER_SECONDS: u64 = 24 * 60 * 60;
+
+/// 組み込みツールを登録したレジストリを作る
+///
+/// `set_timer`の時間が来ると`reminder_tx`にリマインダーの文言を送る
+pub fn builtin_tools(
+    config: &ToolsConfig,
+    reminder_tx: mpsc::UnboundedSender<String>,
+) -> ToolRegistry {
+    let allowed_dirs = AllowedDirs::new(&config.allowed_dirs);
+
+    let mut registry = ToolRegistry::new();
+    registry
+        .register(Arc::new(CurrentTimeTool))
+        .register(Arc::new(CalculatorTool))
+        .register(Arc::new(ReadFileTool {
+            allowed_dirs: allowed_dirs.clone(),
+        }))
+        .register(Arc::new(ListDirectoryTool { allowed_dirs }))
+        .register(Arc::new(TimerTool { reminder_tx }));
+    registry
+}
+
+fn string_argument<'a>(arguments: &'a serde_json::Value, name: &str) -> Result<&'a str> {
+    arguments[name]
+        .as_str()
+        .ok_or_else(|| Error::Tool(format!("missing string argument '{}'", name)))
+}
+
+/// 現在の日時
+struct CurrentTimeTool;
+
+#[async_trait]
+impl Tool for CurrentTimeTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "get_current_time".to_string(),
+            description: "Get the current local date, time and day of the week.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {},
+            }),
+        }
+    }
+
+    async fn call(&self, _arguments: serde_json::Value) -> Result<String> {
+        Ok(chrono::Local::now()
+            .format("%Y-%m-%d %H:%M:%S (%A, UTC%:z)")
+            .to_string())
+    }
+}
+
+/// 四則演算・剰余・累乗の計算
+struct CalculatorTool;
+
+#[async_trait]
+impl Tool for CalculatorTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "calculate".to_string(),
+            description:
+                "Evaluate an arithmetic expression. Supports + - * / % ^, parentheses and sqrt()."
+                    .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "Expression to evaluate, e.g. \"(1 + 2) * 3 ^ 2\"",
+                    },
+                },
+                "required": ["expression"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let expression = string_argument(&arguments, "expression")?;
+        let value = Calculator::new(expression).evaluate()?;
+        Ok(format_number(value))
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+/// 再帰下降による数式の評価器
+struct Calculator {
+    chars: Vec<char>,
+    pos: usize,
+    /// 現在の入れ子の深さ
+    depth: usize,
+}
+
+impl Calculator {
+    fn new(expression: &str) -> Self {
+        Self {
+            chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn evaluate(mut self) -> Result<f64> {
+        let value = self.expression()?;
+        if let Some(c) = self.peek() {
+            return Err(self.error(&format!("unexpected '{}'", c)));
+        }
+        if !value.is_finite() {
+            return Err(Error::Tool("result is not a finite number".to_string()));
+        }
+        Ok(value)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn consume(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Tool(format!("{} at position {}", message, self.pos))
+    }
+
+    /// 入れ子を1段深くして`parse`を呼ぶ（深すぎる場合はエラー）
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<f64>) -> Result<f64> {
+        if self.depth >= MAX_EXPRESSION_DEPTH {
+            return Err(self.error(&format!(
+                "expression is nested more than {} levels deep",
+                MAX_EXPRESSION_DEPTH
+            )));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    // expression = term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.consume('+') {
+                value += self.term()?;
+            } else if self.consume('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // term = unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.consume('*') {
+                value *= self.unary()?;
+            } else if self.consume('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err(Error::Tool("division by zero".to_string()));
+                }
+                value /= divisor;
+            } else if self.consume('%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err(Error::Tool("division by zero".to_string()));
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // unary = ('-' | '+') unary | power  （-2^2 は -(2^2) とする）
+    fn unary(&mut self) -> Result<f64> {
+        if self.consume('-') {
+            return Ok(-self.nested(Self::unary)?);
+        }
+        if self.consume('+') {
+            return self.nested(Self::unary);
+        }
+        self.power()
+    }
+
+    // power = primary ('^' unary)?  （右結合）
+    fn power(&mut self) -> Result<f64> {
+        let base = self.primary()?;
+        if self.consume('^') {
+            let exponent = self.nested(Self::unary)?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    // primary = number | '(' expression ')' | 'sqrt' '(' expression ')'
+    fn primary(&mut self) -> Result<f64> {
+        if self.consume('(') {
+            let value = self.nested(Self::expression)?;
+            if !self.consume(')') {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(value);
+        }
+
+        if self.chars[self.pos..].starts_with(&['s', 'q', 'r', 't']) {
+            self.pos += 4;
+            let value = self.nested(Self::primary)?;
+            if value < 0.0 {
+                return Err(Error::Tool("sqrt of a negative number".to_string()));
+            }
+            return Ok(value.sqrt());
+        }
+
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(match self.peek() {
+                Some(c) => self.error(&format!("unexpected '{}'", c)),
+                None => self.error("unexpected end of expression"),
+            });
+        }
+
+        let number: String = self.chars[start..self.pos].iter().collect();
+        number
+            .parse::<f64>()
+            .map_err(|_| self.error(&format!("invalid number '{}'", number)))
+    }
+}
+
+/// ファイル系ツールが参照できるディレクトリ
+#[derive(Clone)]
+struct AllowedDirs {
+    dirs: Vec<PathBuf>,
+}
+
+impl AllowedDirs {
+    fn new(dirs: &[PathBuf]) -> Self {
+        // 存在しないディレクトリは無視し、比較のため正規化しておく
+        let dirs = dirs
+            .iter()
+            .filter_map(|dir| match dir.canonicalize() {
+                Ok(dir) => Some(dir),
+                Err(e) => {
+                    warn!("Ignoring allowed directory {:?}: {}", dir, e);
+                    None
+                }
+            })
+            .collect();
+        Self { dirs }
+    }
+
+    /// 許可されたディレクトリ配下のパスに解決する
+    ///
+    /// 相対パスは許可ディレクトリを順に基準として探す
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        if self.dirs.is_empty() {
+            return Err(Error::Tool(
+                "no directories are allowed; add tools.allowed_dirs to the config file".to_string(),
+            ));
+        }
+
+        let requested = Path::new(path);
+        let candidates: Vec<PathBuf> = if requested.is_absolute() {
+            vec![requested.to_path_buf()]
+        } else {
+            self.dirs.iter().map(|dir| dir.join(requested)).collect()
+        };
+
+        for candidate in candidates {
+            let Ok(resolved) = candidate.canonicalize() else {
+                continue;
+            };
+            if self.dirs.iter().any(|dir| resolved.starts_with(dir)) {
+                return Ok(resolved);
+            }
+            return Err(Error::Tool(format!(
+                "'{}' is outside the allowed directories",
+                path
+            )));
+        }
+
+        Err(Error::Tool(format!("'{}' was not found", path)))
+    }
+
+    fn describe(&self) -> String {
+        if self.dirs.is_empty() {
+            "none".to_string()
+        } else {
+            self.dirs
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    }
+}
+
+/// 許可ディレクトリ内のテキストファイルの読み取り
+struct ReadFileTool {
+    allowed_dirs: AllowedDirs,
+}
+
+#[async_trait]
+impl Tool for ReadFileTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "read_file".to_string(),
+            description: format!(
+                "Read a text file. Only files under these directories can be read: {}",
+                self.allowed_dirs.describe()
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path, or a path relative to an allowed directory",
+                    },
+                },
+                "required": ["path"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let path = self
+            .allowed_dirs
+            .resolve(string_argument(&arguments, "path")?)?;
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| Error::Tool(format!("failed to read {}: {}", path.display(), e)))?;
+
+        let truncated = bytes.len() > MAX_READ_BYTES;
+        let mut content =
+            String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_READ_BYTES)]).into_owned();
+        if truncated {
+            content.push_str(&format!(
+                "\n[truncated: showing the first {} of {} bytes]",
+                MAX_READ_BYTES,
+                bytes.len()
+            ));
+        }
+        Ok(content)
+    }
+}
+
+/// 許可ディレクトリ内の一覧表示
+struct ListDirectoryTool {
+    allowed_dirs: AllowedDirs,
+}
+
+#[async_trait]
+impl Tool for ListDirectoryTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "list_directory".to_string(),
+            description: format!(
+                "List the entries of a directory. Only these directories and their subdirectories can be listed: {}",
+                self.allowed_dirs.describe()
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Absolute path, or a path relative to an allowed directory (\".\" for the first one)",
+                    },
+                },
+                "required": ["path"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let path = self
+            .allowed_dirs
+            .resolve(string_argument(&arguments, "path")?)?;
+        let list_error =
+            |e: std::io::Error| Error::Tool(format!("failed to list {}: {}", path.display(), e));
+
+        let mut entries = tokio::fs::read_dir(&path).await.map_err(list_error)?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(list_error)? {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().await.is_ok_and(|t| t.is_dir()) {
+                name.push('/');
+            }
+            names.push(name);
+        }
+        names.sort();
+
+        if names.is_empty() {
+            Ok(format!("{} is empty", path.display()))
+        } else {
+            Ok(names.join("\n"))
+        }
+    }
+}
+
+/// 指定時間後に読み上げられるリマインダー
+struct TimerTool {
+    reminder_tx: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Tool for TimerTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "set_timer".to_string(),
+            description: "Set a timer. When it expires, the message is read aloud to the user."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "seconds": {
+                        "type": "integer",
+                        "description": "Seconds until the reminder (at most 86400)",
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "Reminder to read aloud, written in the character's voice",
+                    },
+                },
+                "required": ["seconds", "message"],
+            }),
+        }
+    }
+
+    async fn call(&self, arguments: serde_json::Value) -> Result<String> {
+        let seconds = arguments["seconds"]
+            .as_u64()
+            .ok_or_else(|| Error::Tool("missing integer argument 'seconds'".to_string()))?;
+        if seconds == 0 || seconds > MAX_TIMER_SECONDS {
+            return Err(Error::Tool(format!(
+                "seconds must be between 1 and {}",
+                MAX_TIMER_SECONDS
+            )));
+        }
+        let message = string_argument(&arguments, "message")?.to_string();
+
+        info!("Timer set for {} seconds: {}", seconds, message);
+        let reminder_tx = self.reminder_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(seconds)).await;
+            let _ = reminder_tx.send(message);
+        });
+
+        Ok(format!("Timer set for {} seconds.", seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculate(expression: &str) -> Result<f64> {
+        Calculator::new(expression).evaluate()
+    }
+
+    fn calculate_error(expression: &str) -> String {
+        match calculate(expression) {
+            Ok(value) => panic!("'{}' evaluated to {}", expression, value),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn calculator_follows_operator_precedence() {
+        assert_eq!(calculate("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(calculate("(1 + 2) * 3").unwrap(), 9.0);
+        assert_eq!(calculate("10 - 4 - 3").unwrap(), 3.0);
+        assert_eq!(calculate("12 / 4 / 3").unwrap(), 1.0);
+        assert_eq!(calculate("7 % 4 * 2").unwrap(), 6.0);
+        assert_eq!(calculate("2 * 3 ^ 2").unwrap(), 18.0);
+        assert_eq!(calculate("sqrt(16) + 1").unwrap(), 5.0);
+        assert_eq!(calculate("1.5 * 4").unwrap(), 6.0);
+    }
+
+    #[test]
+    fn calculator_handles_unary_minus() {
+        assert_eq!(calculate("-3 + 5").unwrap(), 2.0);
+        assert_eq!(calculate("2 * -3").unwrap(), -6.0);
+        assert_eq!(calculate("--4").unwrap(), 4.0);
+        assert_eq!(calculate("-(1 + 2)").unwrap(), -3.0);
+        // -2^2 は -(2^2)
+        assert_eq!(calculate("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(calculate("2 ^ -1").unwrap(), 0.5);
+    }
+
+    #[test]
+    fn calculator_power_is_right_associative() {
+        assert_eq!(calculate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(calculate("(2 ^ 3) ^ 2").unwrap(), 64.0);
+    }
+
+    #[test]
+    fn calculator_rejects_division_by_zero() {
+        assert!(calculate_error("1 / 0").contains("division by zero"));
+        assert!(calculate_error("5 % (2 - 2)").contains("division by zero"));
+        assert!(calculate_error("sqrt(-1)").contains("negative"));
+        assert!(calculate_error("10 ^ 1000").contains("finite"));
+    }
+
+    #[test]
+    fn calculator_rejects_malformed_input() {
+        for expression in [
+            "", "1 +", "(1 + 2", "1 + 2)", "2 * * 3", "1..2", "abc", "sqrt",
+        ] {
+            assert!(calculate(expression).is_err(), "'{}'", expression);
+        }
+        assert_eq!(
+            calculate_error("1 + x"),
+            "tool failed: unexpected 'x' at position 2"
+        );
+    }
+
+    #[test]
+    fn calculator_limits_nesting_depth() {
+        let depth = MAX_EXPRESSION_DEPTH;
+        let within = format!("{}1{}", "(".repeat(depth - 1), ")".repeat(depth - 1));
+        assert_eq!(calculate(&within).unwrap(), 1.0);
+
+        for expression in [
+            format!("{}1{}", "(".repeat(depth + 1), ")".repeat(depth + 1)),
+            format!("{}1", "-".repeat(100_000)),
+            format!("{}4", "sqrt".repeat(depth + 1)),
+            "2^".repeat(depth + 1) + "1",
+        ] {
+            assert!(calculate_error(&expression).contains("nested more than"));
+        }
+    }
+
+    /// テスト用の一時ディレクトリ（終了時に削除する）
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("voicevox-chat-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir.canonicalize().unwrap())
+        }
+
+        fn path(&self, relative: &str) -> PathBuf {
+            self.0.join(relative)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// `allowed/`と、その外の`secret.txt`を持つディレクトリ
+    fn sandbox(name: &str) -> (TempDir, AllowedDirs) {
+        let root = TempDir::new(name);
+        std::fs::create_dir_all(root.path("allowed/sub")).unwrap();
+        std::fs::write(root.path("allowed/sub/note.txt"), "note").unwrap();
+        std::fs::write(root.path("secret.txt"), "secret").unwrap();
+        let allowed = AllowedDirs::new(&[root.path("allowed")]);
+        (root, allowed)
+    }
+
+    #[test]
+    fn resolve_accepts_paths_inside_allowed_dirs() {
+        let (root, allowed) = sandbox("resolve-inside");
+        let note = root.path("allowed/sub/note.txt");
+
+        assert_eq!(allowed.resolve("sub/note.txt").unwrap(), note);
+        assert_eq!(allowed.resolve(note.to_str().unwrap()).unwrap(), note);
+        assert_eq!(allowed.resolve("sub/../sub/./note.txt").unwrap(), note);
+        assert_eq!(allowed.resolve(".").unwrap(), root.path("allowed"));
+    }
+
+    #[test]
+    fn resolve_rejects_parent_directory_escapes() {
+        let (root, allowed) = sandbox("resolve-parent");
+
+        for path in [
+            "../secret.txt".to_string(),
+            "sub/../../secret.txt".to_string(),
+            "..".to_string(),
+            root.path("secret.txt").display().to_string(),
+            root.path("allowed/../secret.txt").display().to_string(),
+        ] {
+            let error = allowed.resolve(&path).unwrap_err().to_string();
+            assert!(
+                error.contains("outside the allowed directories"),
+                "{}: {}",
+                path,
+                error
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_symlinks_that_leave_allowed_dirs() {
+        let (root, allowed) = sandbox("resolve-symlink");
+        std::os::unix::fs::symlink(root.path("secret.txt"), root.path("allowed/link.txt")).unwrap();
+        std::os::unix::fs::symlink(root.path(""), root.path("allowed/escape")).unwrap();
+        std::os::unix::fs::symlink(
+            root.path("allowed/sub/note.txt"),
+            root.path("allowed/inner.txt"),
+        )
+        .unwrap();
+
+        for path in ["link.txt", "escape/secret.txt", "escape"] {
+            let error = allowed.resolve(path).unwrap_err().to_string();
+            assert!(
+                error.contains("outside the allowed directories"),
+                "{}: {}",
+                path,
+                error
+            );
+        }
+        assert_eq!(
+            allowed.resolve("inner.txt").unwrap(),
+            root.path("allowed/sub/note.txt")
+        );
+    }
+
+    #[test]
+    fn resolve_reports_missing_files_and_unconfigured_dirs() {
+        let (_root, allowed) = sandbox("resolve-missing");
+        assert!(allowed
+            .resolve("missing.txt")
+            .unwrap_err()
+            .to_string()
+            .contains("was not found"));
+
+        let none = AllowedDirs::new(&[]);
+        assert!(none
+            .resolve("anything")
+            .unwrap_err()
+            .to_string()
+            .contains("no directories are allowed"));
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+/// 1回以上のリクエストで消費したトークン数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    /// 推論トークンを含む出力トークン数
+    pub completion_tokens: u64,
+    /// 出力トークンのうち推論に使われた分
+    pub reasoning_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn is_empty(&self) -> bool {
+        self.prompt_tokens == 0 && self.completion_tokens == 0
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+    }
+}
+
+/// モデルの料金（USD / 100万トークン）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl ModelPrice {
+    /// 推論トークンは出力トークンとして課金される
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input + usage.completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// 1日分の利用額（日付が変わったらリセットする）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailySpending {
+    /// `YYYY-MM-DD`（ローカル時刻）
+    pub date: String,
+    pub cost: f64,
+}
+
+fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// セッション中のトークン数と料金の集計
+#[derive(Debug, Clone, Default)]
+pub struct UsageTracker {
+    prices: HashMap<String, ModelPrice>,
+    daily_cap: Option<f64>,
+    daily: DailySpending,
+    daily_changed: bool,
+    pub session: TokenUsage,
+    pub session_cost: f64,
+    pub last_turn: Option<TokenUsage>,
+    pub last_turn_cost: Option<f64>,
+}
+
+impl UsageTracker {
+    pub fn new(
+        prices: HashMap<String, ModelPrice>,
+        daily_cap: Option<f64>,
+        daily: DailySpending,
+    ) -> Self {
+        Self {
+            prices,
+            daily_cap,
+            daily,
+            ..Self::default()
+        }
+    }
+
+    /// モデルの料金（完全一致がなければ最も長い前方一致）
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices.get(model).or_else(|| {
+            self.prices
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, price)| price)
+        })
+    }
+
+    /// 1ターン分の利用を記録し、料金が分かれば返す
+    pub fn record(&mut self, model: &str, usage: TokenUsage) -> Option<f64> {
+        let cost = self.price(model).map(|price| price.cost(&usage));
+
+        self.session += usage;
+        self.last_turn = Some(usage);
+        self.last_turn_cost = cost;
+
+        if let Some(cost) = cost {
+            self.session_cost += cost;
+            self.roll_over_day();
+            self.daily.cost += cost;
+            self.daily_changed = true;
+        }
+
+        cost
+    }
+
+    fn roll_over_day(&mut self) {
+        let today = today();
+        if self.daily.date != today {
+            self.daily = DailySpending {
+                date: today,
+                cost: 0.0,
+            };
+            self.daily_changed = true;
+        }
+    }
+
+    /// 今日の利用額
+    pub fn daily_cost(&self) -> f64 {
+        if self.daily.date == today() {
+            self.daily.cost
+        } else {
+            0.0
+        }
+    }
+
+    pub fn daily_cap(&self) -> Option<f64> {
+        self.daily_cap
+    }
+
+    /// 1日の上限額に達しているか
+    pub fn cap_reached(&self) -> bool {
+        self.daily_cap.is_some_and(|cap| self.daily_cost() >= cap)
+    }
+
+    /// 設定ファイルに保存すべき今日の利用額を取り出す
+    pub fn take_daily_update(&mut self) -> Option<DailySpending> {
+        if std::mem::take(&mut self.daily_changed) {
+            Some(self.daily.clone())
+        } else {
+            None
+        }
+    }
+
+    /// ステータス行の表示（例: "last 1.2k/340 tok $0.0004 · session ... · today $0.05/$1.00"）
+    pub fn status_line(&self) -> Option<String> {
+        let last_turn = self.last_turn?;
+
+        let mut parts = vec![format!(
+            "last {}{}",
+            format_usage(&last_turn),
+            format_cost(self.last_turn_cost)
+        )];
+        parts.push(format!(
+            "session {}{}",
+            format_usage(&self.session),
+            format_cost((self.session_cost > 0.0).then_some(self.session_cost))
+        ));
+        match self.daily_cap {
+            Some(cap) => parts.push(format!("today ${:.2}/${:.2}", self.daily_cost(), cap)),
+            None if self.daily_cost() > 0.0 => {
+                parts.push(format!("today ${:.2}", self.daily_cost()))
+            }
+            None => {}
+        }
+
+        Some(format!(" {} ", parts.join(" · ")))
+    }
+}
+
+/// 入力/出力トークン数の表示（推論トークンがあれば併記する）
+pub fn format_usage(usage: &TokenUsage) -> String {
+    let mut text = format!(
+        "{}/{} tok",
+        format_count(usage.prompt_tokens),
+        format_count(usage.completion_tokens)
+    );
+    if usage.reasoning_tokens > 0 {
+        text.push_str(&format!(
+            " ({} reasoning)",
+            format_count(usage.reasoning_tokens)
+        ));
+    }
+    text
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    match cost {
+        Some(cost) => format!(" ${:.4}", cost),
+        None => String::new(),
+    }
+}
+
+fn format_count(count: u64) -> String {
+    if count >= 1_000 {
+        format!("{:.1}k", count as f64 / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(input: f64, output: f64) -> ModelPrice {
+        ModelPrice { input, output }
+    }
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            reasoning_tokens: 0,
+        }
+    }
+
+    fn tracker(daily_cap: Option<f64>, daily: DailySpending) -> UsageTracker {
+        let prices = HashMap::from([
+            ("gpt-5".to_string(), price(1.25, 10.0)),
+            ("gpt-5-mini".to_string(), price(0.25, 2.0)),
+        ]);
+        UsageTracker::new(prices, daily_cap, daily)
+    }
+
+    fn spent_today(cost: f64) -> DailySpending {
+        DailySpending {
+            date: today(),
+            cost,
+        }
+    }
+
+    #[test]
+    fn exact_match_wins_over_prefix() {
+        let tracker = tracker(None, DailySpending::default());
+
+        assert_eq!(tracker.price("gpt-5").unwrap().input, 1.25);
+        assert_eq!(tracker.price("gpt-5-mini").unwrap().input, 0.25);
+    }
+
+    #[test]
+    fn longest_prefix_wins() {
+        let tracker = tracker(None, DailySpending::default());
+
+        // `gpt-5`と`gpt-5-mini`の両方に前方一致する
+        assert_eq!(tracker.price("gpt-5-mini-2025-08-07").unwrap().input, 0.25);
+        assert_eq!(tracker.price("gpt-5-2025-08-07").unwrap().input, 1.25);
+        assert!(tracker.price("gpt-4o").is_none());
+    }
+
+    #[test]
+    fn cost_is_per_million_tokens() {
+        let price = price(1.25, 10.0);
+
+        assert_eq!(price.cost(&usage(1_000_000, 0)), 1.25);
+        assert_eq!(price.cost(&usage(0, 1_000_000)), 10.0);
+        assert!((price.cost(&usage(2_000, 500)) - 0.0075).abs() < 1e-12);
+        assert_eq!(price.cost(&TokenUsage::default()), 0.0);
+    }
+
+    #[test]
+    fn record_accumulates_session_and_daily_cost() {
+        let mut tracker = tracker(None, spent_today(0.5));
+
+        let cost = tracker.record("gpt-5", usage(1_000_000, 0));
+        assert_eq!(cost, Some(1.25));
+        tracker.record("gpt-5", usage(1_000_000, 0));
+
+        assert_eq!(tracker.session, usage(2_000_000, 0));
+        assert_eq!(tracker.session_cost, 2.5);
+        assert_eq!(tracker.daily_cost(), 3.0);
+    }
+
+    #[test]
+    fn unknown_model_is_counted_without_cost() {
+        let mut tracker = tracker(None, spent_today(0.5));
+
+        assert_eq!(tracker.record("llama3", usage(100, 20)), None);
+        assert_eq!(tracker.session, usage(100, 20));
+        assert_eq!(tracker.last_turn_cost, None);
+        assert_eq!(tracker.daily_cost(), 0.5);
+        assert!(tracker.take_daily_update().is_none());
+    }
+
+    #[test]
+    fn daily_cost_resets_on_a_new_day() {
+        let yesterday = DailySpending {
+            date: "2000-01-01".to_string(),
+            cost: 5.0,
+        };
+        let mut tracker = tracker(Some(1.0), yesterday);
+
+        // 前日の利用額は上限の判定に含めない
+        assert_eq!(tracker.daily_cost(), 0.0);
+        assert!(!tracker.cap_reached());
+
+        tracker.record("gpt-5", usage(400_000, 0));
+        let daily = tracker.take_daily_update().unwrap();
+        assert_eq!(daily.date, today());
+        assert_eq!(daily.cost, 0.5);
+    }
+
+    #[test]
+    fn cap_is_reached_at_the_limit() {
+        assert!(!tracker(None, spent_today(100.0)).cap_reached());
+        assert!(!tracker(Some(1.0), spent_today(0.99)).cap_reached());
+        assert!(tracker(Some(1.0), spent_today(1.0)).cap_reached());
+
+        let mut tracker = tracker(Some(1.0), spent_today(0.0));
+        tracker.record("gpt-5", usage(0, 100_000));
+        assert!(tracker.cap_reached());
+    }
+
+    #[test]
+    fn daily_update_is_taken_once() {
+        let mut tracker = tracker(None, spent_today(0.0));
+        assert!(tracker.take_daily_update().is_none());
+
+        tracker.record("gpt-5-mini", usage(1_000_000, 0));
+        let daily = tracker.take_daily_update().unwrap();
+        assert_eq!(daily.cost, 0.25);
+        assert!(tracker.take_daily_update().is_none());
+    }
+}
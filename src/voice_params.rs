@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::AudioQuery;
+use crate::error::{Error, Result};
+
+/// 調整できる読み上げパラメータの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceParam {
+    SpeedScale,
+    PitchScale,
+    IntonationScale,
+    VolumeScale,
+    PrePhonemeLength,
+    PostPhonemeLength,
+    PauseLengthScale,
+}
+
+impl VoiceParam {
+    pub const ALL: [VoiceParam; 7] = [
+        VoiceParam::SpeedScale,
+        VoiceParam::PitchScale,
+        VoiceParam::IntonationScale,
+        VoiceParam::VolumeScale,
+        VoiceParam::PrePhonemeLength,
+        VoiceParam::PostPhonemeLength,
+        VoiceParam::PauseLengthScale,
+    ];
+
+    /// 設定ファイルで使う名前
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoiceParam::SpeedScale => "speed_scale",
+            VoiceParam::PitchScale => "pitch_scale",
+            VoiceParam::IntonationScale => "intonation_scale",
+            VoiceParam::VolumeScale => "volume_scale",
+            VoiceParam::PrePhonemeLength => "pre_phoneme_length",
+            VoiceParam::PostPhonemeLength => "post_phoneme_length",
+            VoiceParam::PauseLengthScale => "pause_length_scale",
+        }
+    }
+
+    /// 受け付ける値の範囲（VOICEVOXのエディタと同じ）
+    pub fn range(&self) -> (f64, f64) {
+        match self {
+            VoiceParam::SpeedScale => (0.5, 2.0),
+            VoiceParam::PitchScale => (-0.15, 0.15),
+            VoiceParam::IntonationScale | VoiceParam::VolumeScale => (0.0, 2.0),
+            VoiceParam::PrePhonemeLength | VoiceParam::PostPhonemeLength => (0.0, 1.5),
+            VoiceParam::PauseLengthScale => (0.0, 2.0),
+        }
+    }
+
+    /// 設定パネルで`h`/`l`を押したときの増減の幅
+    pub fn step(&self) -> f64 {
+        match self {
+            VoiceParam::PitchScale => 0.01,
+            _ => 0.05,
+        }
+    }
+
+    /// エンジンが`audio_query`で返す標準の値
+    pub fn engine_default(&self) -> f64 {
+        match self {
+            VoiceParam::PitchScale => 0.0,
+            VoiceParam::PrePhonemeLength | VoiceParam::PostPhonemeLength => 0.1,
+            _ => 1.0,
+        }
+    }
+
+    /// 入力できる値の説明
+    pub fn hint(&self) -> String {
+        let (min, max) = self.range();
+        format!("{} - {} (default {})", min, max, self.engine_default())
+    }
+}
+
+/// 話者ごとの読み上げパラメータ（未設定の値はエンジンが返した値を使う）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intonation_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_phoneme_length: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_phoneme_length: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_length_scale: Option<f64>,
+}
+
+impl VoiceParams {
+    fn field(&self, param: VoiceParam) -> Option<f64> {
+        match param {
+            VoiceParam::SpeedScale => self.speed_scale,
+            VoiceParam::PitchScale => self.pitch_scale,
+            VoiceParam::IntonationScale => self.intonation_scale,
+            VoiceParam::VolumeScale => self.volume_scale,
+            VoiceParam::PrePhonemeLength => self.pre_phoneme_length,
+            VoiceParam::PostPhonemeLength => self.post_phoneme_length,
+            VoiceParam::PauseLengthScale => self.pause_length_scale,
+        }
+    }
+
+    fn field_mut(&mut self, param: VoiceParam) -> &mut Option<f64> {
+        match param {
+            VoiceParam::SpeedScale => &mut self.speed_scale,
+            VoiceParam::PitchScale => &mut self.pitch_scale,
+            VoiceParam::IntonationScale => &mut self.intonation_scale,
+            VoiceParam::VolumeScale => &mut self.volume_scale,
+            VoiceParam::PrePhonemeLength => &mut self.pre_phoneme_length,
+            VoiceParam::PostPhonemeLength => &mut self.post_phoneme_length,
+            VoiceParam::PauseLengthScale => &mut self.pause_length_scale,
+        }
+    }
+
+    /// 表示用の値（未設定は`None`）
+    pub fn value(&self, param: VoiceParam) -> Option<String> {
+        self.field(param).map(|v| v.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 文字列の値を検証して設定する（空文字列は既定値に戻す）
+    pub fn set(&mut self, param: VoiceParam, value: &str) -> Result<()> {
+        let value = value.trim();
+        if value.is_empty() || value == "default" {
+            self.unset(param);
+            return Ok(());
+        }
+
+        let (min, max) = param.range();
+        let parsed = value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "invalid {} '{}' (expected {})",
+                    param.name(),
+                    value,
+                    param.hint()
+                ))
+            })?;
+        *self.field_mut(param) = Some(parsed);
+        Ok(())
+    }
+
+    /// `step`の`steps`倍だけ増減する（範囲の外には出さない）
+    pub fn adjust(&mut self, param: VoiceParam, steps: i32) {
+        let (min, max) = param.range();
+        let current = self.field(param).unwrap_or_else(|| param.engine_default());
+        let adjusted = (current + param.step() * steps as f64).clamp(min, max);
+        // 刻み幅の誤差が表示に出ないよう丸める
+        *self.field_mut(param) = Some((adjusted * 100.0).round() / 100.0);
+    }
+
+    pub fn unset(&mut self, param: VoiceParam) {
+        *self.field_mut(param) = None;
+    }
+
+    /// `audio_query`の結果に設定済みの値を上書きする
+    pub fn apply(&self, query: &mut AudioQuery) {
+        if let Some(v) = self.speed_scale {
+            query.speed_scale = v;
+        }
+        if let Some(v) = self.pitch_scale {
+            query.pitch_scale = v;
+        }
+        if let Some(v) = self.intonation_scale {
+            query.intonation_scale = v;
+        }
+        if let Some(v) = self.volume_scale {
+            query.volume_scale = v;
+        }
+        if let Some(v) = self.pre_phoneme_length {
+            query.pre_phoneme_length = v;
+        }
+        if let Some(v) = self.post_phoneme_length {
+            query.post_phoneme_length = v;
+        }
+        // 古いエンジンは`pauseLengthScale`を持たないので、その場合は送らない
+        if let (Some(v), Some(scale)) = (self.pause_length_scale, query.pause_length_scale.as_mut())
+        {
+            *scale = v;
+        }
+    }
+
+    /// 設定画面に表示する要約（例: "speed_scale=1.3, pitch_scale=0.02"）
+    pub fn summary(&self) -> String {
+        VoiceParam::ALL
+            .iter()
+            .filter_map(|param| Some(format!("{}={}", param.name(), self.value(*param)?)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+
+use futures::StreamExt;
+use voicevox_chat::audio;
+use voicevox_chat::cassette::{HttpClient, HttpMode};
+use voicevox_chat::sse::SseDecoder;
+
+fn replay_client() -> HttpClient {
+    let dir: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "cassette"]
+        .iter()
+        .collect();
+    HttpClient::new(&HttpMode::Replay(dir)).expect("fixture cassette loads")
+}
+
+#[test]
+fn parses_command_line_flags() {
+    let args = |args: &[&str]| HttpMode::from_args(args.iter().map(|arg| arg.to_string()));
+
+    assert_eq!(args(&[]).unwrap(), HttpMode::Live);
+    assert_eq!(
+        args(&["--replay", "session"]).unwrap(),
+        HttpMode::Replay(PathBuf::from("session"))
+    );
+    assert_eq!(
+        args(&["--record", "session"]).unwrap(),
+        HttpMode::Record(PathBuf::from("session"))
+    );
+    assert!(args(&["--replay"]).is_err());
+    assert!(args(&["--verbose"]).is_err());
+}
+
+#[tokio::test]
+async fn replays_recorded_stream_chunk_by_chunk() {
+    let client = replay_client();
+
+    // ホストやクエリが違っても、メソッドとパスが同じなら記録した応答を返す
+    let response = client
+        .send(client.post("http://127.0.0.1:1/v1/chat/completions"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    let mut decoder = SseDecoder::new();
+    let mut stream = response.bytes_stream();
+    let mut chunk_count = 0;
+    let mut events = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunk_count += 1;
+        events.extend(decoder.push(&chunk.unwrap()));
+    }
+    assert_eq!(chunk_count, 3);
+    assert_eq!(events.len(), 3);
+    assert!(events[2].is_done());
+}
+
+#[tokio::test]
+async fn replays_binary_chunks() {
+    let client = replay_client();
+
+    let response = client
+        .send(client.post("http://localhost:50021/synthesis?speaker=1"))
+        .await
+        .unwrap();
+    let bytes = response.bytes().await.unwrap();
+    assert!(bytes.starts_with(b"RIFF"));
+    assert_eq!(&bytes[8..12], b"WAVE");
+}
+
+#[tokio::test]
+async fn unrecorded_request_returns_error_response() {
+    let client = replay_client();
+
+    // 1件しか記録していないので、2回目は記録なしになる
+    for expected in [200, 404] {
+        let response = client
+            .send(client.post("http://localhost:50021/synthesis?speaker=1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), expected);
+    }
+
+    let response = client
+        .send(client.request(reqwest::Method::GET, "http://localhost:11434/api/tags"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["type"], "cassette_miss");
+}
+
+#[tokio::test]
+async fn lists_speaker_styles_from_recording() {
+    let client = replay_client();
+
+    // 再生時はURLを照合に使わない
+    let speakers = audio::fetch_speakers(&client, "http://localhost:50021")
+        .await
+        .unwrap();
+    let names: Vec<&str> = speakers.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["四国めたん", "ずんだもん"]);
+
+    let zundamon: Vec<(&str, u32)> = speakers[1]
+        .styles
+        .iter()
+        .map(|style| (style.name.as_str(), style.id))
+        .collect();
+    assert_eq!(
+        zundamon,
+        vec![("ノーマル", 3), ("あまあま", 1), ("ツンツン", 7)]
+    );
+}
+
+/// 1回だけ`body`を返すHTTPサーバーを起動し、そのURLを返す
+fn serve_once(body: &'static str) -> String {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1/models", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    });
+    url
+}
+
+/// 空の記録用ディレクトリ
+fn record_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("voicevox-chat-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn recorded_files(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn records_the_response_as_soon_as_the_body_is_read() {
+    let dir = record_dir("record-sync");
+    let client = HttpClient::new(&HttpMode::Record(dir.clone())).unwrap();
+    let url = serve_once(r#"{"data":[{"id":"gpt-5-nano"}]}"#);
+
+    let body = client
+        .send(client.request(reqwest::Method::GET, url))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, r#"{"data":[{"id":"gpt-5-nano"}]}"#);
+
+    // 別のタスクを待たずに書き出されている
+    let files = recorded_files(&dir);
+    assert_eq!(files.len(), 1);
+    assert!(files[0].ends_with("0000-GET-v1_models.json"));
+    let recorded = std::fs::read_to_string(&files[0]).unwrap();
+    assert!(recorded.contains("gpt-5-nano"));
+
+    // 記録した応答をそのまま再生できる
+    let replay = HttpClient::new(&HttpMode::Replay(dir.clone())).unwrap();
+    let replayed = replay
+        .send(replay.request(reqwest::Method::GET, "http://localhost/v1/models"))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(replayed, body);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn records_a_response_dropped_before_its_body_is_read() {
+    let dir = record_dir("record-dropped");
+    let client = HttpClient::new(&HttpMode::Record(dir.clone())).unwrap();
+    let url = serve_once(r#"{"data":[]}"#);
+
+    let response = client
+        .send(client.request(reqwest::Method::GET, url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    drop(response);
+
+    assert_eq!(recorded_files(&dir).len(), 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
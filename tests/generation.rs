@@ -0,0 +1,79 @@
+use voicevox_chat::generation::{GenerationParam, GenerationParams};
+
+fn set(param: GenerationParam, value: &str) -> Result<GenerationParams, String> {
+    let mut params = GenerationParams::default();
+    params
+        .set(param, value)
+        .map(|_| params)
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn temperature_accepts_values_from_zero_to_two() {
+    for (value, expected) in [("0", 0.0), ("0.7", 0.7), (" 2.0 ", 2.0)] {
+        let params = set(GenerationParam::Temperature, value).unwrap();
+        assert_eq!(params.temperature, Some(expected), "{}", value);
+    }
+}
+
+#[test]
+fn temperature_rejects_out_of_range_and_non_numbers() {
+    for value in ["-0.1", "2.01", "warm", "NaN", "inf"] {
+        let error = set(GenerationParam::Temperature, value).unwrap_err();
+        assert!(
+            error.contains("invalid temperature"),
+            "{}: {}",
+            value,
+            error
+        );
+        assert!(error.contains("0.0 - 2.0"), "{}: {}", value, error);
+    }
+}
+
+#[test]
+fn top_p_accepts_values_from_zero_to_one() {
+    for (value, expected) in [("0", 0.0), ("0.95", 0.95), ("1", 1.0)] {
+        let params = set(GenerationParam::TopP, value).unwrap();
+        assert_eq!(params.top_p, Some(expected), "{}", value);
+    }
+    for value in ["-0.5", "1.5", "high"] {
+        assert!(set(GenerationParam::TopP, value).is_err(), "{}", value);
+    }
+}
+
+#[test]
+fn max_completion_tokens_must_be_a_positive_integer() {
+    let params = set(GenerationParam::MaxCompletionTokens, "1024").unwrap();
+    assert_eq!(params.max_completion_tokens, Some(1024));
+
+    for value in ["0", "-1", "1.5", "lots", "4294967296"] {
+        assert!(
+            set(GenerationParam::MaxCompletionTokens, value).is_err(),
+            "{}",
+            value
+        );
+    }
+}
+
+#[test]
+fn rejected_values_leave_the_previous_value() {
+    let mut params = GenerationParams::default();
+    params.set(GenerationParam::Temperature, "0.5").unwrap();
+    assert!(params.set(GenerationParam::Temperature, "3").is_err());
+    assert_eq!(params.temperature, Some(0.5));
+}
+
+#[test]
+fn empty_or_default_resets_to_the_provider_default() {
+    let mut params = GenerationParams::default();
+    params.set(GenerationParam::TopP, "0.9").unwrap();
+    params
+        .set(GenerationParam::MaxCompletionTokens, "256")
+        .unwrap();
+
+    params.set(GenerationParam::TopP, "").unwrap();
+    params
+        .set(GenerationParam::MaxCompletionTokens, "default")
+        .unwrap();
+    assert_eq!(params, GenerationParams::default());
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use voicevox_chat::mcp::{self, McpClient, McpServerConfig};
+
+/// `cargo test`がビルドしたサンプルのエコーサーバー
+fn echo_server() -> McpServerConfig {
+    // target/debug/deps/mcp-xxxx から target/debug/examples を辿る
+    let mut path: PathBuf = std::env::current_exe().unwrap();
+    path.pop();
+    if path.ends_with("deps") {
+        path.pop();
+    }
+    path.push("examples");
+    path.push(format!("mcp_echo_server{}", std::env::consts::EXE_SUFFIX));
+
+    McpServerConfig {
+        command: path.to_string_lossy().into_owned(),
+        ..McpServerConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn lists_and_calls_tools() {
+    let client = McpClient::connect("echo", &echo_server()).await.unwrap();
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].name, "echo");
+    assert_eq!(tools[0].input_schema["required"][0], "text");
+
+    let result = client
+        .call_tool("echo", serde_json::json!({ "text": "こんにちは" }))
+        .await
+        .unwrap();
+    assert_eq!(result, "こんにちは");
+}
+
+#[tokio::test]
+async fn tool_errors_are_reported() {
+    let client = McpClient::connect("echo", &echo_server()).await.unwrap();
+
+    let result = client.call_tool("missing", serde_json::json!({})).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn exposes_tools_with_server_prefix() {
+    let servers = HashMap::from([("echo".to_string(), echo_server())]);
+    let (tools, errors) = mcp::connect_servers(&servers).await;
+    assert!(errors.is_empty());
+    assert_eq!(tools.len(), 1);
+
+    let definition = tools[0].definition();
+    assert_eq!(definition.name, "echo__echo");
+    assert!(definition.description.starts_with("[echo]"));
+
+    let result = tools[0]
+        .call(serde_json::json!({ "text": "hello" }))
+        .await
+        .unwrap();
+    assert_eq!(result, "hello");
+}
+
+#[tokio::test]
+async fn missing_server_is_reported() {
+    let servers = HashMap::from([(
+        "broken".to_string(),
+        McpServerConfig {
+            command: "/nonexistent/mcp-server".to_string(),
+            ..McpServerConfig::default()
+        },
+    )]);
+    let (tools, errors) = mcp::connect_servers(&servers).await;
+    assert!(tools.is_empty());
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use voicevox_chat::error::Error;
+use voicevox_chat::retry::RetryPolicy;
+
+fn rate_limited(retry_after: Duration) -> Error {
+    Error::from_status_body(429, "").with_retry_after(Some(retry_after))
+}
+
+#[test]
+fn retry_after_is_used_when_within_max_delay() {
+    let policy = RetryPolicy::default();
+    let status = policy
+        .next_retry(0, &rate_limited(Duration::from_secs(3)))
+        .expect("429 should be retried");
+    assert_eq!(status.delay, Duration::from_secs(3));
+}
+
+#[test]
+fn retry_after_is_clamped_to_max_delay() {
+    let policy = RetryPolicy {
+        max_delay_ms: 10_000,
+        ..RetryPolicy::default()
+    };
+    let status = policy
+        .next_retry(0, &rate_limited(Duration::from_secs(3600)))
+        .expect("429 should be retried");
+    assert_eq!(status.delay, Duration::from_secs(10));
+}
+
+/// `headers`付きの429応答をエラーに変換する
+async fn rate_limited_response(headers: &[(&str, &str)]) -> Error {
+    let mut builder = http::Response::builder().status(429);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    Error::from_response(reqwest::Response::from(builder.body("").unwrap())).await
+}
+
+#[tokio::test]
+async fn retry_after_header_is_read_in_seconds_or_milliseconds() {
+    let error = rate_limited_response(&[("retry-after", "2.5")]).await;
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(2500)));
+
+    let error = rate_limited_response(&[("retry-after-ms", "750"), ("retry-after", "9")]).await;
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(750)));
+
+    let error = rate_limited_response(&[("retry-after", "-3")]).await;
+    assert_eq!(error.retry_after(), Some(Duration::ZERO));
+}
+
+#[tokio::test]
+async fn huge_retry_after_header_is_clamped_to_max_delay() {
+    let policy = RetryPolicy::default();
+    for headers in [
+        [("retry-after", "1e20")],
+        [("retry-after", "1e308")],
+        [("retry-after-ms", "1e300")],
+    ] {
+        let error = rate_limited_response(&headers).await;
+        let status = policy.next_retry(0, &error).expect("429 should be retried");
+        assert_eq!(
+            status.delay,
+            Duration::from_millis(policy.max_delay_ms),
+            "{:?}",
+            headers
+        );
+    }
+}
+
+#[tokio::test]
+async fn non_finite_retry_after_header_is_ignored() {
+    for value in ["inf", "-inf", "NaN", "soon"] {
+        let error = rate_limited_response(&[("retry-after", value)]).await;
+        assert_eq!(error.retry_after(), None, "{}", value);
+        // ヘッダーがない場合と同じく指数バックオフで待つ
+        let status = RetryPolicy::default().next_retry(0, &error).unwrap();
+        assert!(status.delay <= Duration::from_millis(500), "{}", value);
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+
+use voicevox_chat::error::{Error, ErrorCategory};
+use voicevox_chat::sse::{LineDecoder, SseDecoder, SseEvent};
+
+/// 区切りの位置を変えても同じイベントになることを確かめるチャンクサイズ
+const CHUNK_SIZES: [usize; 5] = [1, 2, 3, 7, usize::MAX];
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", "sse", name]
+        .iter()
+        .collect();
+    std::fs::read(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+/// `chunk_size`バイトずつ渡してすべてのイベントを集める
+fn decode(bytes: &[u8], chunk_size: usize) -> Vec<SseEvent> {
+    let mut decoder = SseDecoder::new();
+    let mut events: Vec<SseEvent> = bytes
+        .chunks(chunk_size.min(bytes.len()).max(1))
+        .flat_map(|chunk| decoder.push(chunk))
+        .collect();
+    events.extend(decoder.finish());
+    events
+}
+
+/// どのチャンクサイズでも同じ結果になることを確かめてから返す
+fn decode_fixture(name: &str) -> Vec<SseEvent> {
+    let bytes = fixture(name);
+    let expected = decode(&bytes, usize::MAX);
+    for chunk_size in CHUNK_SIZES {
+        assert_eq!(
+            decode(&bytes, chunk_size),
+            expected,
+            "{} split into {}-byte chunks",
+            name,
+            chunk_size
+        );
+    }
+    expected
+}
+
+fn delta_content(event: &SseEvent) -> String {
+    let json: serde_json::Value = serde_json::from_str(&event.data).unwrap();
+    json["choices"][0]["delta"]["content"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[test]
+fn decodes_chat_completion_stream() {
+    let events = decode_fixture("openai_chat.sse");
+
+    assert_eq!(events.len(), 6);
+    assert!(events.last().unwrap().is_done());
+    assert!(events[..5].iter().all(|event| !event.is_done()));
+
+    let content: String = events[..4].iter().map(delta_content).collect();
+    assert_eq!(content, "こんにちは、ずんだもんなのだ！");
+
+    let usage: serde_json::Value = serde_json::from_str(&events[4].data).unwrap();
+    assert_eq!(usage["usage"]["completion_tokens"], 8);
+}
+
+#[test]
+fn crlf_line_endings_match_lf() {
+    assert_eq!(
+        decode_fixture("openai_chat_crlf.sse"),
+        decode_fixture("openai_chat.sse")
+    );
+}
+
+#[test]
+fn joins_multi_line_data() {
+    let events = decode_fixture("multiline_data.sse");
+
+    assert_eq!(
+        events,
+        vec![
+            SseEvent {
+                event: None,
+                data: "first line\nsecond line".to_string(),
+                id: Some("1".to_string()),
+            },
+            SseEvent {
+                event: None,
+                data: "no space after colon\n".to_string(),
+                id: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn skips_comments_and_keeps_event_names() {
+    let events = decode_fixture("comments_and_events.sse");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event.as_deref(), Some("message"));
+    assert_eq!(delta_content(&events[0]), "hi");
+
+    assert_eq!(events[1].event.as_deref(), Some("error"));
+    let json: serde_json::Value = serde_json::from_str(&events[1].data).unwrap();
+    let error = Error::from_error_json(&json).unwrap();
+    assert_eq!(error.category(), ErrorCategory::RateLimited);
+}
+
+#[test]
+fn flushes_unterminated_last_event() {
+    let events = decode_fixture("unterminated.sse");
+
+    let content: Vec<String> = events.iter().map(delta_content).collect();
+    assert_eq!(content, vec!["途中", "で切断"]);
+}
+
+#[test]
+fn strips_byte_order_mark() {
+    let mut bytes = "\u{feff}".as_bytes().to_vec();
+    bytes.extend_from_slice(b"data: hello\n\n");
+
+    let events = decode(&bytes, 1);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "hello");
+}
+
+#[test]
+fn line_decoder_handles_split_crlf_and_utf8() {
+    let mut lines = LineDecoder::new();
+    let text = "ずんだ\r\nもち\n".as_bytes();
+
+    let mut decoded = Vec::new();
+    for byte in text {
+        decoded.extend(lines.push(std::slice::from_ref(byte)));
+    }
+    assert_eq!(decoded, vec!["ずんだ", "もち"]);
+    assert_eq!(lines.finish(), None);
+}
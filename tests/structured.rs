@@ -0,0 +1,87 @@
+use voicevox_chat::structured::{StructuredReply, TextFieldExtractor};
+
+/// `json`を`at`の位置で2つのチャンクに分けて渡し、取り出した本文を返す
+fn extract_split(json: &str, at: usize) -> String {
+    let mut extractor = TextFieldExtractor::new();
+    let (head, tail) = json.split_at(at);
+    extractor.push(head) + &extractor.push(tail)
+}
+
+/// 文字の境界すべてでチャンクを分けても同じ本文が得られることを確かめる
+fn assert_extracts(json: &str, expected: &str) {
+    for (at, _) in json.char_indices().chain([(json.len(), ' ')]) {
+        assert_eq!(extract_split(json, at), expected, "split at byte {}", at);
+    }
+
+    let mut extractor = TextFieldExtractor::new();
+    let one_by_one: String = json
+        .chars()
+        .map(|c| extractor.push(&c.to_string()))
+        .collect();
+    assert_eq!(one_by_one, expected, "one character per chunk");
+}
+
+#[test]
+fn extracts_text_split_inside_escapes() {
+    assert_extracts(
+        r#"{"text":"He said \"hi\"\n\tand left\\"}"#,
+        "He said \"hi\"\n\tand left\\",
+    );
+}
+
+#[test]
+fn extracts_text_split_inside_unicode_escapes_and_surrogate_pairs() {
+    assert_extracts(
+        r#"{"text":"caf\u00e9 \u3053\u3093 \ud83c\udf89!"}"#,
+        "café こん 🎉!",
+    );
+}
+
+#[test]
+fn extracts_text_that_is_not_the_first_key() {
+    assert_extracts(
+        r#"{"emotion":"happy", "speaker_style" : "sweet", "text": "やったー！"}"#,
+        "やったー！",
+    );
+}
+
+#[test]
+fn ignores_text_keys_in_nested_objects_and_arrays() {
+    assert_extracts(
+        r#"{"meta":{"text":"nested","items":[{"text":"deeper"}]},"tags":["text",":"],"text":"top"}"#,
+        "top",
+    );
+}
+
+#[test]
+fn ignores_text_as_a_value_of_another_key() {
+    assert_extracts(r#"{"emotion":"text","text":"ok"}"#, "ok");
+}
+
+#[test]
+fn passes_non_json_output_through() {
+    assert_extracts("  Just a plain reply.", "Just a plain reply.");
+    assert_eq!(StructuredReply::parse("Just a plain reply."), None);
+}
+
+#[test]
+fn invalid_json_yields_no_text_and_no_reply() {
+    // 文字列でない`text`は表示しない
+    assert_extracts(r#"{"text": 42, "emotion": "sad"}"#, "");
+    assert_eq!(StructuredReply::parse(r#"{"text": 42}"#), None);
+
+    // 途中で切れた応答は受け取った分だけ表示する
+    assert_extracts(r#"{"text":"partial"#, "partial");
+    assert_eq!(StructuredReply::parse(r#"{"text":"partial"#), None);
+}
+
+#[test]
+fn parses_the_complete_reply() {
+    let reply = StructuredReply::parse(
+        " {\"text\":\"こんにちは\",\"emotion\":\"happy\",\"speaker_style\":\"sweet\"}\n",
+    )
+    .unwrap();
+    assert_eq!(reply.text, "こんにちは");
+    assert_eq!(reply.emotion.as_deref(), Some("happy"));
+    assert_eq!(reply.speaker_style.as_deref(), Some("sweet"));
+}
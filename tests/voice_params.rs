@@ -0,0 +1,82 @@
+use voicevox_chat::audio::AudioQuery;
+use voicevox_chat::voice_params::{VoiceParam, VoiceParams};
+
+/// エンジンの`audio_query`が返すJSON（アクセント句は一部のみ）
+const AUDIO_QUERY: &str = r#"{
+  "accent_phrases": [
+    {"moras": [{"text": "コ", "consonant": "k", "consonant_length": 0.05, "vowel": "o", "vowel_length": 0.1, "pitch": 5.7}], "accent": 1, "pause_mora": null, "is_interrogative": false}
+  ],
+  "speedScale": 1.0,
+  "pitchScale": 0.0,
+  "intonationScale": 1.0,
+  "volumeScale": 1.0,
+  "prePhonemeLength": 0.1,
+  "postPhonemeLength": 0.1,
+  "pauseLength": null,
+  "pauseLengthScale": 1.0,
+  "outputSamplingRate": 24000,
+  "outputStereo": false,
+  "kana": "コ'"
+}"#;
+
+fn parse(json: &str) -> AudioQuery {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn round_trips_fields_it_does_not_model() {
+    let query = parse(AUDIO_QUERY);
+    let original: serde_json::Value = serde_json::from_str(AUDIO_QUERY).unwrap();
+    assert_eq!(serde_json::to_value(&query).unwrap(), original);
+}
+
+#[test]
+fn overrides_only_configured_values() {
+    let mut params = VoiceParams::default();
+    params.set(VoiceParam::SpeedScale, "1.3").unwrap();
+    params.set(VoiceParam::PauseLengthScale, "0.5").unwrap();
+
+    let mut query = parse(AUDIO_QUERY);
+    params.apply(&mut query);
+
+    let json = serde_json::to_value(&query).unwrap();
+    assert_eq!(json["speedScale"], 1.3);
+    assert_eq!(json["pauseLengthScale"], 0.5);
+    assert_eq!(json["pitchScale"], 0.0);
+    assert_eq!(json["outputSamplingRate"], 24000);
+}
+
+#[test]
+fn leaves_out_pause_scale_for_older_engines() {
+    let old: serde_json::Value = {
+        let mut json: serde_json::Value = serde_json::from_str(AUDIO_QUERY).unwrap();
+        json.as_object_mut().unwrap().remove("pauseLengthScale");
+        json
+    };
+    let mut params = VoiceParams::default();
+    params.set(VoiceParam::PauseLengthScale, "0.5").unwrap();
+
+    let mut query: AudioQuery = serde_json::from_value(old).unwrap();
+    params.apply(&mut query);
+    assert!(serde_json::to_value(&query)
+        .unwrap()
+        .get("pauseLengthScale")
+        .is_none());
+}
+
+#[test]
+fn validates_and_clamps_values() {
+    let mut params = VoiceParams::default();
+    assert!(params.set(VoiceParam::SpeedScale, "3").is_err());
+    assert!(params.set(VoiceParam::PitchScale, "fast").is_err());
+    assert!(params.is_empty());
+
+    params.adjust(VoiceParam::SpeedScale, 2);
+    assert_eq!(params.speed_scale, Some(1.1));
+    params.adjust(VoiceParam::PitchScale, -100);
+    assert_eq!(params.pitch_scale, Some(-0.15));
+
+    params.set(VoiceParam::SpeedScale, "default").unwrap();
+    params.unset(VoiceParam::PitchScale);
+    assert!(params.is_empty());
+}